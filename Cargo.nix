@@ -4429,6 +4429,21 @@ rec {
         authors = [
           "Stackable GmbH <info@stackable.tech>"
         ];
+        dependencies = [
+          {
+            name = "serde";
+            packageId = "serde";
+            features = [ "derive" ];
+          }
+          {
+            name = "serde_json";
+            packageId = "serde_json";
+          }
+          {
+            name = "tracing";
+            packageId = "tracing";
+          }
+        ];
         buildDependencies = [
           {
             name = "bindgen";
@@ -11724,7 +11739,11 @@ rec {
           {
             name = "tokio";
             packageId = "tokio";
-            features = [ "rt-multi-thread" "macros" "fs" "process" "io-std" ];
+            features = [ "rt-multi-thread" "macros" "fs" "process" "io-std" "signal" "time" ];
+          }
+          {
+            name = "tokio-util";
+            packageId = "tokio-util";
           }
           {
             name = "tracing";
@@ -11754,10 +11773,19 @@ rec {
           }
         ];
         devDependencies = [
+          {
+            name = "axum";
+            packageId = "axum";
+            features = [ "http2" ];
+          }
           {
             name = "rstest";
             packageId = "rstest";
           }
+          {
+            name = "tempfile";
+            packageId = "tempfile";
+          }
         ];
         features = {
           "full" = [ "openapi" ];
@@ -11823,7 +11851,7 @@ rec {
           {
             name = "tokio";
             packageId = "tokio";
-            features = [ "rt-multi-thread" "macros" "fs" "process" "io-std" ];
+            features = [ "rt-multi-thread" "macros" "fs" "process" "io-std" "signal" "time" ];
           }
           {
             name = "tower-http";
@@ -12495,7 +12523,11 @@ rec {
           {
             name = "tokio";
             packageId = "tokio";
-            features = [ "rt-multi-thread" "macros" "fs" "process" "io-std" ];
+            features = [ "rt-multi-thread" "macros" "fs" "process" "io-std" "signal" "time" ];
+          }
+          {
+            name = "tokio-util";
+            packageId = "tokio-util";
           }
           {
             name = "toml";
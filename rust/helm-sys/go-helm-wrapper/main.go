@@ -2,26 +2,131 @@ package main
 
 /*
 #include <stdlib.h>
+
+typedef void (*helm_log_callback)(int level, char *release_name, char *namespace, char *message);
+
+static inline void call_helm_log_callback(helm_log_callback callback, int level, char *release_name, char *namespace, char *message) {
+	callback(level, release_name, namespace, message);
+}
 */
 import "C"
 
 import (
 	"context"
+	"crypto/sha256"
+	"crypto/tls"
+	"crypto/x509"
 	"encoding/json"
+	"errors"
 	"fmt"
+	"net"
+	"net/http"
+	"os"
+	"strings"
+	"sync"
 	"time"
 	"unsafe"
 
 	gohelm "github.com/mittwald/go-helm-client"
 	"helm.sh/helm/v3/pkg/action"
+	"helm.sh/helm/v3/pkg/registry"
+	"helm.sh/helm/v3/pkg/releaseutil"
 	"helm.sh/helm/v3/pkg/repo"
+	"helm.sh/helm/v3/pkg/storage/driver"
+	apierrors "k8s.io/apimachinery/pkg/api/errors"
+	"k8s.io/apimachinery/pkg/util/wait"
+	"k8s.io/klog/v2"
+	"oras.land/oras-go/v2/errdef"
+	"oras.land/oras-go/v2/registry/remote/auth"
+	"oras.land/oras-go/v2/registry/remote/credentials"
+	"oras.land/oras-go/v2/registry/remote/errcode"
 
 	// Needed for authentication against clusters, e.g. GCP
 	// see https://github.com/kubernetes/client-go/issues/242
 	_ "k8s.io/client-go/plugin/pkg/client/auth"
 )
 
-const HELM_ERROR_PREFIX = "ERROR:"
+// Error kinds which are understood by the Rust code. Any error which cannot
+// be classified is reported using ErrorKindOther.
+const (
+	ErrorKindReleaseNotFound = "releaseNotFound"
+	ErrorKindConflict        = "conflict"
+	ErrorKindTimeout         = "timeout"
+	ErrorKindAuthFailed      = "authFailed"
+	ErrorKindChartNotFound   = "chartNotFound"
+	ErrorKindOther           = "other"
+)
+
+// Log levels which are understood by the Rust log callback.
+const (
+	LogLevelDebug = 0
+	LogLevelInfo  = 1
+	LogLevelWarn  = 2
+	LogLevelError = 3
+)
+
+// The callback registered by the Rust code, which emits log messages as
+// tracing events. Log messages are dropped until a callback is registered.
+var logCallback C.helm_log_callback
+
+// The cancel functions of all running operations, keyed by the operation ID
+// passed by the Rust code, see go_cancel_helm_operation. Operations which were
+// cancelled before they started are recorded with a nil cancel function.
+var (
+	operationsMutex sync.Mutex
+	operations      = map[uint64]context.CancelFunc{}
+)
+
+// The access to OCI registries configured by go_configure_helm_registry, keyed
+// by the registry host. The credentials are only kept in memory and never
+// written to the Helm registry config of the user.
+var (
+	registriesMutex sync.RWMutex
+	registries      = map[string]registryAccess{}
+)
+
+// The credentials and CA files of the Helm repos added by go_add_helm_repo,
+// keyed by the repo URL. They are also used for repos with the same URL which
+// are added without credentials, e.g. by stacks and demos.
+var (
+	repositoriesMutex sync.Mutex
+	repositories      = map[string]repo.Entry{}
+)
+
+type registryAccess struct {
+	username string
+	password string
+
+	// The PEM encoded CA bundle used to verify the registry, empty if only the
+	// system root certificates are trusted.
+	caBundle []byte
+
+	// Whether the registry is accessed using plain HTTP instead of HTTPS, e.g.
+	// a local registry.
+	plainHTTP bool
+}
+
+// Every exported function returns this envelope as a JSON document. Returning
+// structured data (like GoSlices of objects) directly was a nightmare to share
+// between Go and Rust. On success, Data contains the function specific
+// result (which can be null), on failure Error describes what went wrong.
+type Result struct {
+	Ok    bool        `json:"ok"`
+	Data  interface{} `json:"data"`
+	Error *Error      `json:"error,omitempty"`
+}
+
+type Error struct {
+	Kind    string `json:"kind"`
+	Message string `json:"message"`
+
+	// The status of the affected Helm release at the time of the failure, if
+	// the release exists.
+	Status string `json:"status,omitempty"`
+
+	// The affected Kubernetes resource in the form <kind>/<name>, if known.
+	Resource string `json:"resource,omitempty"`
+}
 
 type Release struct {
 	Name        string `json:"name"`
@@ -31,15 +136,51 @@ type Release struct {
 	LastUpdated string `json:"lastUpdated"`
 }
 
+type ChartValues struct {
+	Values map[string]interface{} `json:"values"`
+
+	// The raw values.schema.json of the chart, empty if the chart doesn't
+	// provide one.
+	Schema string `json:"schema"`
+}
+
+type ReleaseRevision struct {
+	Revision     int    `json:"revision"`
+	ChartVersion string `json:"chartVersion"`
+	AppVersion   string `json:"appVersion"`
+	Status       string `json:"status"`
+	Updated      string `json:"updated"`
+	Description  string `json:"description"`
+}
+
 func main() {
 
 }
 
+func init() {
+	// The Kubernetes client libraries log using klog, which writes to stderr
+	// by default and thereby bypasses the Rust log callback
+	klog.LogToStderr(false)
+	klog.SetOutput(klogWriter{})
+}
+
+//export go_set_log_callback
+func go_set_log_callback(callback C.helm_log_callback) {
+	logCallback = callback
+}
+
+// Installs a release. The installation is aborted (and the release marked as
+// failed) once the timeout expires or the operation is cancelled. If the chart
+// digest is not empty, only a chart with this digest is installed, see
+// pinChart.
+//
 //export go_install_helm_release
-func go_install_helm_release(releaseName *C.char, chartName *C.char, chartVersion *C.char, valuesYaml *C.char, namespace *C.char, suppressOutput bool) *C.char {
-	helmClient := getHelmClient(namespace, suppressOutput)
+func go_install_helm_release(releaseName *C.char, chartName *C.char, chartVersion *C.char, valuesYaml *C.char, namespace *C.char, chartDigest *C.char, operationId C.ulonglong, timeoutMillis C.longlong) *C.char {
+	ctx, timeout, done := startOperation(operationId, timeoutMillis)
+	defer done()
+
+	helmClient := getHelmClient(C.GoString(releaseName), namespace)
 
-	timeout, _ := time.ParseDuration("20m")
 	chartSpec := gohelm.ChartSpec{
 		ReleaseName: C.GoString(releaseName),
 		ChartName:   C.GoString(chartName),
@@ -51,45 +192,245 @@ func go_install_helm_release(releaseName *C.char, chartName *C.char, chartVersio
 		Timeout:     timeout,
 	}
 
-	if _, err := helmClient.InstallChart(context.Background(), &chartSpec, nil); err != nil {
-		return C.CString(fmt.Sprintf("%s%s", HELM_ERROR_PREFIX, err))
+	if digest := C.GoString(chartDigest); digest != "" {
+		cleanup, err := pinChart(helmClient, &chartSpec, digest)
+		if err != nil {
+			return failure(err)
+		}
+		defer cleanup()
+	}
+
+	if _, err := helmClient.InstallChart(ctx, &chartSpec, nil); err != nil {
+		return releaseFailure(helmClient, chartSpec.ReleaseName, err)
+	}
+
+	return success(nil)
+}
+
+// Upgrades an existing release in-place. If force is set, conflicting
+// resources are replaced (deleted and re-created) instead of patched. The
+// release is not rolled back automatically, this is left to the caller. The upgrade is aborted (and the
+// release marked as failed) once the timeout expires or the operation is
+// cancelled. If the chart digest is not empty, only a chart with this digest
+// is installed, see pinChart.
+//
+//export go_upgrade_helm_release
+func go_upgrade_helm_release(releaseName *C.char, chartName *C.char, chartVersion *C.char, valuesYaml *C.char, namespace *C.char, chartDigest *C.char, force bool, operationId C.ulonglong, timeoutMillis C.longlong) *C.char {
+	ctx, timeout, done := startOperation(operationId, timeoutMillis)
+	defer done()
+
+	helmClient := getHelmClient(C.GoString(releaseName), namespace)
+
+	chartSpec := gohelm.ChartSpec{
+		ReleaseName: C.GoString(releaseName),
+		ChartName:   C.GoString(chartName),
+		Version:     C.GoString(chartVersion),
+		ValuesYaml:  C.GoString(valuesYaml),
+		Namespace:   C.GoString(namespace),
+		UpgradeCRDs: true,
+		Force:       force,
+		Wait:        true,
+		Timeout:     timeout,
+	}
+
+	if digest := C.GoString(chartDigest); digest != "" {
+		cleanup, err := pinChart(helmClient, &chartSpec, digest)
+		if err != nil {
+			return failure(err)
+		}
+		defer cleanup()
+	}
+
+	if _, err := helmClient.UpgradeChart(ctx, &chartSpec, nil); err != nil {
+		return releaseFailure(helmClient, chartSpec.ReleaseName, err)
+	}
+
+	return success(nil)
+}
+
+// Rolls back a release to the provided revision. A revision of 0 rolls back
+// to the previous revision. Helm doesn't support cancelling rollbacks, so only
+// the timeout is respected.
+//
+//export go_rollback_helm_release
+func go_rollback_helm_release(releaseName *C.char, namespace *C.char, revision C.int, operationId C.ulonglong, timeoutMillis C.longlong) *C.char {
+	ctx, timeout, done := startOperation(operationId, timeoutMillis)
+	defer done()
+
+	// The operation can only be skipped if it was cancelled before it started
+	if err := ctx.Err(); err != nil {
+		return failure(err)
+	}
+
+	helmClient := getHelmClient(C.GoString(releaseName), namespace)
+	actionConfig := helmClient.(*gohelm.HelmClient).ActionConfig
+
+	client := action.NewRollback(actionConfig)
+	client.Version = int(revision)
+	client.Wait = true
+	client.Timeout = timeout
+
+	if err := client.Run(C.GoString(releaseName)); err != nil {
+		return releaseFailure(helmClient, C.GoString(releaseName), err)
+	}
+
+	return success(nil)
+}
+
+// Returns the revision history of a release, sorted by revision.
+//
+//export go_helm_release_history
+func go_helm_release_history(releaseName *C.char, namespace *C.char) *C.char {
+	actionConfig := getActionConfig(C.GoString(releaseName), namespace)
+
+	client := action.NewHistory(actionConfig)
+	releases, err := client.Run(C.GoString(releaseName))
+	if err != nil {
+		return failure(err)
+	}
+	releaseutil.SortByRevision(releases)
+
+	var result = make([]ReleaseRevision, len(releases))
+	for i, release := range releases {
+		result[i] = ReleaseRevision{
+			Revision:     release.Version,
+			ChartVersion: release.Chart.Metadata.Version,
+			AppVersion:   release.Chart.Metadata.AppVersion,
+			Status:       release.Info.Status.String(),
+			Updated:      release.Info.LastDeployed.String(),
+			Description:  release.Info.Description,
+		}
+	}
+
+	return success(result)
+}
+
+// Returns the user-supplied values of the deployed revision of a release.
+//
+//export go_helm_release_values
+func go_helm_release_values(releaseName *C.char, namespace *C.char) *C.char {
+	actionConfig := getActionConfig(C.GoString(releaseName), namespace)
+
+	client := action.NewGetValues(actionConfig)
+	values, err := client.Run(C.GoString(releaseName))
+	if err != nil {
+		return failure(err)
+	}
+
+	// Releases installed without values return nil, which would be encoded
+	// as null
+	if values == nil {
+		values = map[string]interface{}{}
 	}
 
-	return C.CString("")
+	return success(values)
 }
 
+// Uninstalls a release. Helm doesn't support cancelling uninstalls, so only the
+// timeout is respected.
+//
 //export go_uninstall_helm_release
-func go_uninstall_helm_release(releaseName *C.char, namespace *C.char, suppressOutput bool) *C.char {
-	helmClient := getHelmClient(namespace, suppressOutput)
+func go_uninstall_helm_release(releaseName *C.char, namespace *C.char, operationId C.ulonglong, timeoutMillis C.longlong) *C.char {
+	ctx, timeout, done := startOperation(operationId, timeoutMillis)
+	defer done()
 
-	if err := helmClient.UninstallReleaseByName(C.GoString(releaseName)); err != nil {
-		return C.CString(fmt.Sprintf("%s%s", HELM_ERROR_PREFIX, err))
+	// The operation can only be skipped if it was cancelled before it started
+	if err := ctx.Err(); err != nil {
+		return failure(err)
 	}
 
-	return C.CString("")
+	helmClient := getHelmClient(C.GoString(releaseName), namespace)
+	chartSpec := gohelm.ChartSpec{
+		ReleaseName: C.GoString(releaseName),
+		Namespace:   C.GoString(namespace),
+		Timeout:     timeout,
+	}
+
+	if err := helmClient.UninstallRelease(&chartSpec); err != nil {
+		return releaseFailure(helmClient, C.GoString(releaseName), err)
+	}
+
+	return success(nil)
 }
 
+// Renders the chart locally and returns the resulting Kubernetes manifests
+// (including CRDs) as a multi-document YAML string. Nothing is installed.
+//
+//export go_template_helm_release
+func go_template_helm_release(releaseName *C.char, chartName *C.char, chartVersion *C.char, valuesYaml *C.char, namespace *C.char) *C.char {
+	helmClient := getHelmClient(C.GoString(releaseName), namespace)
+
+	chartSpec := gohelm.ChartSpec{
+		ReleaseName: C.GoString(releaseName),
+		ChartName:   C.GoString(chartName),
+		Version:     C.GoString(chartVersion),
+		ValuesYaml:  C.GoString(valuesYaml),
+		Namespace:   C.GoString(namespace),
+	}
+
+	manifests, err := helmClient.TemplateChart(&chartSpec, nil)
+	if err != nil {
+		return failure(err)
+	}
+
+	return success(string(manifests))
+}
+
+// Loads the chart and returns its default values and values schema. Nothing is
+// rendered or installed.
+//
+//export go_helm_chart_values
+func go_helm_chart_values(chartName *C.char, chartVersion *C.char) *C.char {
+	helmClient := getHelmClient("", nil)
+
+	chart, _, err := helmClient.GetChart(C.GoString(chartName), &action.ChartPathOptions{
+		Version: C.GoString(chartVersion),
+	})
+	if err != nil {
+		return failure(err)
+	}
+
+	// Charts without a values.yaml have nil values, which would be encoded
+	// as null
+	values := chart.Values
+	if values == nil {
+		values = map[string]interface{}{}
+	}
+
+	return success(ChartValues{
+		Values: values,
+		Schema: string(chart.Schema),
+	})
+}
+
+// Returns whether the release exists. A missing release is not an error, but
+// any other failure (e.g. missing permissions) is reported.
+//
 //export go_helm_release_exists
-func go_helm_release_exists(releaseName *C.char, namespace *C.char) bool {
-	helmClient := getHelmClient(namespace, true)
+func go_helm_release_exists(releaseName *C.char, namespace *C.char) *C.char {
+	helmClient := getHelmClient(C.GoString(releaseName), namespace)
+
+	release, err := helmClient.GetRelease(C.GoString(releaseName))
+	if err != nil {
+		if classifyError(err).Kind == ErrorKindReleaseNotFound {
+			return success(false)
+		}
+
+		return failure(err)
+	}
 
-	release, _ := helmClient.GetRelease(C.GoString(releaseName))
-	return release != nil
+	return success(release != nil)
 }
 
-// Returning a JSON document as GoSlices (array) of objects was a nightmare to
-// share between Go and Rust. We also introduce magic return values here. Any
-// non-empty result string starting with 'ERROR:' will be treated as an error
-// by the Rust code and it will abort operations.
 //
 //export go_helm_list_releases
 func go_helm_list_releases(namespace *C.char) *C.char {
-	helmClient := getHelmClient(namespace, true)
+	helmClient := getHelmClient("", namespace)
 
 	// List all releases, not only the deployed ones (e.g. include pending installations)
 	releases, err := helmClient.ListReleasesByStateMask(action.ListAll)
 	if err != nil {
-		return C.CString(fmt.Sprintf("%s%s", HELM_ERROR_PREFIX, err))
+		return failure(err)
 	}
 
 	var result = make([]Release, len(releases))
@@ -103,33 +444,97 @@ func go_helm_list_releases(namespace *C.char) *C.char {
 		}
 	}
 
-	json, err := json.Marshal(result)
-	if err != nil {
-		return C.CString(fmt.Sprintf("%s%s", HELM_ERROR_PREFIX, err))
-	}
-
-	return C.CString(string(json))
+	return success(result)
 }
 
-// Adds a Helm repo to the temporary repositories file. We also introduce
-// magic return values here. Any non-empty result string starting with
-// 'ERROR:' will be treated as an error by the Rust code and it will abort
-// operations.
+// Adds a Helm repo to the temporary repositories file. The credentials and
+// the CA file are optional and can be empty, in which case the ones of a
+// previously added repo with the same URL are used.
 //
 //export go_add_helm_repo
-func go_add_helm_repo(name *C.char, url *C.char) *C.char {
-	helmClient := getHelmClient(C.CString("default"), true) // Namespace doesn't matter
+func go_add_helm_repo(name *C.char, url *C.char, username *C.char, password *C.char, caFile *C.char) *C.char {
+	namespace := C.CString("default") // Namespace doesn't matter
+	defer C.free(unsafe.Pointer(namespace))
+
+	helmClient := getHelmClient("", namespace)
 
 	chartRepo := repo.Entry{
-		Name: C.GoString(name),
-		URL:  C.GoString(url),
+		Name:     C.GoString(name),
+		URL:      C.GoString(url),
+		Username: C.GoString(username),
+		Password: C.GoString(password),
+		CAFile:   C.GoString(caFile),
 	}
 
+	repositoriesMutex.Lock()
+	repositoryUrl := strings.TrimSuffix(chartRepo.URL, "/")
+	if chartRepo.Username != "" || chartRepo.CAFile != "" {
+		repositories[repositoryUrl] = chartRepo
+	} else if known, found := repositories[repositoryUrl]; found {
+		chartRepo.Username = known.Username
+		chartRepo.Password = known.Password
+		chartRepo.CAFile = known.CAFile
+	}
+	repositoriesMutex.Unlock()
+
 	if err := helmClient.AddOrUpdateChartRepo(chartRepo); err != nil {
-		return C.CString(fmt.Sprintf("%s%s", HELM_ERROR_PREFIX, err))
+		return failure(err)
+	}
+
+	return success(nil)
+}
+
+// Configures access to the OCI registry at host for all subsequent operations.
+// The credentials and the CA file are optional and can be empty. If plainHTTP
+// is set, the registry is accessed using plain HTTP instead of HTTPS. Registries
+// which are not configured use the credentials stored in the Helm registry
+// config of the user (like "helm registry login" does), which is never
+// modified.
+//
+//export go_configure_helm_registry
+func go_configure_helm_registry(host *C.char, username *C.char, password *C.char, caFile *C.char, plainHTTP bool) *C.char {
+	access := registryAccess{
+		username:  C.GoString(username),
+		password:  C.GoString(password),
+		plainHTTP: plainHTTP,
+	}
+
+	if caFile := C.GoString(caFile); caFile != "" {
+		caBundle, err := os.ReadFile(caFile)
+		if err != nil {
+			return failure(fmt.Errorf("failed to read CA file %q: %w", caFile, err))
+		}
+		if !x509.NewCertPool().AppendCertsFromPEM(caBundle) {
+			return failure(fmt.Errorf("CA file %q doesn't contain any PEM encoded certificates", caFile))
+		}
+		access.caBundle = caBundle
+	}
+
+	registriesMutex.Lock()
+	registries[C.GoString(host)] = access
+	registriesMutex.Unlock()
+
+	return success(nil)
+}
+
+// Cancels the running operation with the provided ID. Install and upgrade
+// operations are aborted and the affected release is marked as failed, so
+// that it isn't left in a pending state. Operations which haven't started yet
+// are cancelled as soon as they start.
+//
+//export go_cancel_helm_operation
+func go_cancel_helm_operation(operationId C.ulonglong) {
+	operationsMutex.Lock()
+	defer operationsMutex.Unlock()
+
+	if cancel, found := operations[uint64(operationId)]; found {
+		if cancel != nil {
+			cancel()
+		}
+		return
 	}
 
-	return C.CString("")
+	operations[uint64(operationId)] = nil
 }
 
 //export free_go_string
@@ -137,14 +542,20 @@ func free_go_string(ptr *C.char) {
 	C.free(unsafe.Pointer(ptr))
 }
 
-func getHelmClient(namespace *C.char, suppressOutput bool) gohelm.Client {
-	options := gohelm.Options{
-		Namespace: C.GoString(namespace),
-		Debug:     false,
+// Returns a Helm client, which forwards all log messages and output to the
+// Rust log callback. The releaseName is only used to annotate log messages
+// and can be empty.
+func getHelmClient(releaseName string, namespace *C.char) gohelm.Client {
+	logger := releaseLogger{
+		releaseName: releaseName,
+		namespace:   C.GoString(namespace),
 	}
 
-	if suppressOutput {
-		options.DebugLog = func(format string, v ...interface{}) {}
+	options := gohelm.Options{
+		Namespace: logger.namespace,
+		Debug:     false,
+		DebugLog:  logger.debugLog,
+		Output:    logger,
 	}
 
 	helmClient, err := gohelm.New(&options)
@@ -152,5 +563,340 @@ func getHelmClient(namespace *C.char, suppressOutput bool) gohelm.Client {
 		panic(err)
 	}
 
+	// The default registry client only knows the credentials stored in the
+	// Helm registry config and only trusts the system root certificates
+	if registryClient := newRegistryClient(logger, helmClient.(*gohelm.HelmClient).Settings.RegistryConfig); registryClient != nil {
+		helmClient.(*gohelm.HelmClient).ActionConfig.RegistryClient = registryClient
+	}
+
 	return helmClient
 }
+
+// Returns a registry client which uses the access configured for each registry
+// by go_configure_helm_registry and falls back to the credentials stored in the
+// Helm registry config at registryConfig. Returns nil if no registry is
+// configured, in which case the default registry client can be used.
+func newRegistryClient(logger releaseLogger, registryConfig string) *registry.Client {
+	registriesMutex.RLock()
+	configured := make(map[string]registryAccess, len(registries))
+	for host, access := range registries {
+		configured[host] = access
+	}
+	registriesMutex.RUnlock()
+
+	if len(configured) == 0 {
+		return nil
+	}
+
+	rootCAs, err := x509.SystemCertPool()
+	if err != nil {
+		rootCAs = x509.NewCertPool()
+	}
+	plainHTTP := false
+	for _, access := range configured {
+		rootCAs.AppendCertsFromPEM(access.caBundle)
+		plainHTTP = plainHTTP || access.plainHTTP
+	}
+
+	transport := http.DefaultTransport.(*http.Transport).Clone()
+	transport.TLSClientConfig = &tls.Config{RootCAs: rootCAs}
+	httpClient := &http.Client{Transport: transport}
+
+	// The store is only read, "helm registry login" is the only one writing it
+	var storedCredentials auth.CredentialFunc
+	if store, err := credentials.NewStore(registryConfig, credentials.StoreOptions{}); err == nil {
+		storedCredentials = credentials.Credential(store)
+	}
+
+	authorizer := auth.Client{
+		Client: httpClient,
+		Cache:  auth.NewCache(),
+		Credential: func(ctx context.Context, hostport string) (auth.Credential, error) {
+			if access, found := configured[hostport]; found && access.username != "" {
+				return auth.Credential{Username: access.username, Password: access.password}, nil
+			}
+			if storedCredentials != nil {
+				return storedCredentials(ctx, hostport)
+			}
+			return auth.EmptyCredential, nil
+		},
+	}
+
+	options := []registry.ClientOption{
+		registry.ClientOptWriter(logger),
+		registry.ClientOptHTTPClient(httpClient),
+		registry.ClientOptAuthorizer(authorizer),
+	}
+
+	// Helm only supports plain HTTP for all registries of a client, which is
+	// fine, as only a single registry is configured for the operator charts
+	if plainHTTP {
+		options = append(options, registry.ClientOptPlainHTTP())
+	}
+
+	registryClient, err := registry.NewClient(options...)
+	if err != nil {
+		panic(err)
+	}
+
+	return registryClient
+}
+
+// Registers the operation with the provided ID and returns its context, which
+// is cancelled once the timeout expires or go_cancel_helm_operation is called.
+// The returned function must be called once the operation is done.
+func startOperation(operationId C.ulonglong, timeoutMillis C.longlong) (context.Context, time.Duration, func()) {
+	id := uint64(operationId)
+	timeout := time.Duration(timeoutMillis) * time.Millisecond
+	ctx, cancel := context.WithTimeout(context.Background(), timeout)
+
+	operationsMutex.Lock()
+	if cancelled, found := operations[id]; found && cancelled == nil {
+		// The operation was cancelled before it started
+		cancel()
+	}
+	operations[id] = cancel
+	operationsMutex.Unlock()
+
+	return ctx, timeout, func() {
+		operationsMutex.Lock()
+		delete(operations, id)
+		operationsMutex.Unlock()
+
+		cancel()
+	}
+}
+
+// Pulls the chart of the chartSpec and verifies that it has the expected
+// digest, e.g. from a lockfile. For OCI charts, this is the digest of the
+// manifest, for index.yaml-based repositories the digest of the chart archive.
+// The chartSpec is changed to use the verified chart archive, so that a chart
+// which is re-pushed in the meantime is never installed. The returned function
+// removes the chart archive once it's no longer needed.
+func pinChart(helmClient gohelm.Client, chartSpec *gohelm.ChartSpec, digest string) (func(), error) {
+	client := helmClient.(*gohelm.HelmClient)
+
+	if registry.IsOCI(chartSpec.ChartName) {
+		reference := fmt.Sprintf("%s:%s", strings.TrimPrefix(chartSpec.ChartName, registry.OCIScheme+"://"), chartSpec.Version)
+		result, err := client.ActionConfig.RegistryClient.Pull(reference)
+		if err != nil {
+			return nil, err
+		}
+		if result.Manifest.Digest != digest {
+			return nil, digestMismatch(chartSpec, digest, result.Manifest.Digest)
+		}
+
+		chartFile, err := os.CreateTemp("", "stackablectl-chart-*.tgz")
+		if err != nil {
+			return nil, err
+		}
+		cleanup := func() { os.Remove(chartFile.Name()) }
+
+		_, err = chartFile.Write(result.Chart.Data)
+		if closeErr := chartFile.Close(); err == nil {
+			err = closeErr
+		}
+		if err != nil {
+			cleanup()
+			return nil, err
+		}
+
+		chartSpec.ChartName = chartFile.Name()
+		return cleanup, nil
+	}
+
+	pathOptions := action.ChartPathOptions{Version: chartSpec.Version}
+	chartPath, err := pathOptions.LocateChart(chartSpec.ChartName, client.Settings)
+	if err != nil {
+		return nil, err
+	}
+
+	chartArchive, err := os.ReadFile(chartPath)
+	if err != nil {
+		return nil, err
+	}
+
+	// Helm repository indexes contain the hex encoded digest without algorithm
+	actualDigest := fmt.Sprintf("%x", sha256.Sum256(chartArchive))
+	if actualDigest != strings.TrimPrefix(digest, "sha256:") {
+		return nil, digestMismatch(chartSpec, digest, actualDigest)
+	}
+
+	chartSpec.ChartName = chartPath
+	return func() {}, nil
+}
+
+func digestMismatch(chartSpec *gohelm.ChartSpec, expected string, actual string) error {
+	return fmt.Errorf("digest of chart %q in version %s changed from %s to %s, it was likely re-pushed", chartSpec.ChartName, chartSpec.Version, expected, actual)
+}
+
+// Returns the action configuration of the Helm client, which is needed for
+// actions not exposed by the Helm client itself.
+func getActionConfig(releaseName string, namespace *C.char) *action.Configuration {
+	helmClient := getHelmClient(releaseName, namespace)
+	return helmClient.(*gohelm.HelmClient).ActionConfig
+}
+
+func success(data interface{}) *C.char {
+	return toCString(Result{Ok: true, Data: data})
+}
+
+func failure(err error) *C.char {
+	return toCString(Result{Ok: false, Error: classifyError(err)})
+}
+
+// Same as failure, but additionally reports the current status of the
+// release, e.g. to tell a failed from a pending release.
+func releaseFailure(helmClient gohelm.Client, releaseName string, err error) *C.char {
+	result := classifyError(err)
+
+	if release, getErr := helmClient.GetRelease(releaseName); getErr == nil && release != nil && release.Info != nil {
+		result.Status = release.Info.Status.String()
+
+		// Helm doesn't export the error returned while another operation is
+		// in progress, but the release is pending in that case
+		if result.Kind == ErrorKindOther && release.Info.Status.IsPending() {
+			result.Kind = ErrorKindConflict
+		}
+	}
+
+	return toCString(Result{Ok: false, Error: result})
+}
+
+func toCString(result Result) *C.char {
+	json, err := json.Marshal(result)
+	if err != nil {
+		// All our result types can be serialized, so this can only happen
+		// because of a programming error.
+		panic(err)
+	}
+
+	return C.CString(string(json))
+}
+
+// Classifies the error into one of the well-known error kinds using the typed
+// errors of Helm, the Kubernetes API and OCI registries. Errors which Helm
+// only reports as plain messages (e.g. HTTP errors of index.yaml-based
+// repositories) are reported using ErrorKindOther, as their message can
+// change between Helm versions.
+func classifyError(err error) *Error {
+	result := &Error{
+		Kind:    ErrorKindOther,
+		Message: err.Error(),
+	}
+
+	var apiStatus apierrors.APIStatus
+	if errors.As(err, &apiStatus) {
+		if details := apiStatus.Status().Details; details != nil && details.Name != "" {
+			if details.Kind != "" {
+				result.Resource = fmt.Sprintf("%s/%s", details.Kind, details.Name)
+			} else {
+				result.Resource = details.Name
+			}
+		}
+	}
+
+	// Errors of OCI registries carry the HTTP status code of the response
+	var registryError *errcode.ErrorResponse
+	isRegistryError := errors.As(err, &registryError)
+
+	var netError net.Error
+	isNetError := errors.As(err, &netError)
+
+	var chartNotFound repo.ChartNotFoundError
+
+	switch {
+	case errors.Is(err, driver.ErrReleaseNotFound),
+		errors.Is(err, driver.ErrNoDeployedReleases):
+		result.Kind = ErrorKindReleaseNotFound
+	case apierrors.IsUnauthorized(err),
+		apierrors.IsForbidden(err),
+		isRegistryError && registryError.StatusCode == http.StatusUnauthorized,
+		isRegistryError && registryError.StatusCode == http.StatusForbidden:
+		result.Kind = ErrorKindAuthFailed
+	case errors.Is(err, context.DeadlineExceeded),
+		wait.Interrupted(err),
+		apierrors.IsTimeout(err),
+		apierrors.IsServerTimeout(err),
+		isNetError && netError.Timeout():
+		result.Kind = ErrorKindTimeout
+	case apierrors.IsConflict(err),
+		apierrors.IsAlreadyExists(err),
+		errors.Is(err, driver.ErrReleaseExists):
+		result.Kind = ErrorKindConflict
+	case errors.Is(err, repo.ErrNoChartVersion),
+		errors.Is(err, repo.ErrNoChartName),
+		errors.As(err, &chartNotFound),
+		errors.Is(err, errdef.ErrNotFound),
+		isRegistryError && registryError.StatusCode == http.StatusNotFound:
+		result.Kind = ErrorKindChartNotFound
+	}
+
+	return result
+}
+
+// Forwards a log message to the Rust log callback.
+func logToRust(level int, releaseName string, namespace string, message string) {
+	if logCallback == nil {
+		return
+	}
+
+	cReleaseName := C.CString(releaseName)
+	defer C.free(unsafe.Pointer(cReleaseName))
+	cNamespace := C.CString(namespace)
+	defer C.free(unsafe.Pointer(cNamespace))
+	cMessage := C.CString(message)
+	defer C.free(unsafe.Pointer(cMessage))
+
+	C.call_helm_log_callback(logCallback, C.int(level), cReleaseName, cNamespace, cMessage)
+}
+
+// Annotates all log messages of a Helm client with the release name and
+// namespace. It is used as the debug log as well as the output (e.g. repo
+// update progress) of the Helm client.
+type releaseLogger struct {
+	releaseName string
+	namespace   string
+}
+
+func (l releaseLogger) debugLog(format string, v ...interface{}) {
+	logToRust(LogLevelDebug, l.releaseName, l.namespace, fmt.Sprintf(format, v...))
+}
+
+// Write logs every non-empty line at info level.
+func (l releaseLogger) Write(p []byte) (int, error) {
+	for _, line := range strings.Split(string(p), "\n") {
+		if line = strings.TrimSpace(line); line != "" {
+			logToRust(LogLevelInfo, l.releaseName, l.namespace, line)
+		}
+	}
+
+	return len(p), nil
+}
+
+// Forwards klog messages to the Rust log callback. Every message starts with
+// a header like "E1017 12:00:00.000000   12345 file.go:42] ", where the first
+// character is the severity.
+type klogWriter struct{}
+
+func (klogWriter) Write(p []byte) (int, error) {
+	message := strings.TrimSpace(string(p))
+	if message == "" {
+		return len(p), nil
+	}
+
+	level := LogLevelInfo
+	switch message[0] {
+	case 'W':
+		level = LogLevelWarn
+	case 'E', 'F':
+		level = LogLevelError
+	}
+
+	if _, rest, found := strings.Cut(message, "] "); found {
+		message = rest
+	}
+
+	logToRust(level, "", "", message)
+	return len(p), nil
+}
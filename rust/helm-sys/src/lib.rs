@@ -5,9 +5,143 @@
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
-use std::ffi::{CStr, CString, c_char};
+use std::{
+    ffi::{CStr, CString, c_char, c_int, c_longlong, c_ulonglong},
+    fmt::Display,
+    sync::{
+        Once,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
-pub const HELM_ERROR_PREFIX: &str = "ERROR:";
+use serde::{Deserialize, de::DeserializeOwned};
+use tracing::{debug, error, info, warn};
+
+/// The target of all tracing events emitted for log messages of the Go
+/// wrapper and the Helm SDK.
+pub const LOG_TARGET: &str = "helm";
+
+// Log levels passed to the log callback, see the Go wrapper
+const LOG_LEVEL_DEBUG: c_int = 0;
+const LOG_LEVEL_INFO: c_int = 1;
+const LOG_LEVEL_WARN: c_int = 2;
+
+static REGISTER_LOG_CALLBACK: Once = Once::new();
+
+/// Errors returned by the Go wrapper. The wrapper classifies the underlying
+/// Helm and Kubernetes errors into [`ErrorKind`]s, so that callers can react
+/// to them without parsing error messages.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+
+    /// The status of the affected Helm release at the time of the failure,
+    /// e.g. `failed` or `pending-install`.
+    pub status: Option<String>,
+
+    /// The affected Kubernetes resource in the form `<kind>/<name>`.
+    pub resource: Option<String>,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+
+        if let Some(resource) = &self.resource {
+            write!(f, " (resource: {resource})")?;
+        }
+
+        if let Some(status) = &self.status {
+            write!(f, " (release status: {status})")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    fn other(message: impl Into<String>) -> Self {
+        Self {
+            kind: ErrorKind::Other,
+            message: message.into(),
+            status: None,
+            resource: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    ReleaseNotFound,
+    Conflict,
+    Timeout,
+    AuthFailed,
+    ChartNotFound,
+
+    #[serde(other)]
+    Other,
+}
+
+/// Identifies a running Helm operation, so that it can be cancelled from
+/// another thread using [`cancel_helm_operation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OperationId(u64);
+
+impl Default for OperationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OperationId {
+    /// Returns a new operation ID, which is unique within this process.
+    pub fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Describes a long-running Helm operation, like an install or upgrade.
+///
+/// The Go wrapper aborts the operation once the `timeout` expires or it is
+/// cancelled using [`cancel_helm_operation`] with the `id`.
+#[derive(Clone, Copy, Debug)]
+pub struct Operation {
+    pub id: OperationId,
+    pub timeout: Duration,
+}
+
+impl Operation {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            id: OperationId::new(),
+            timeout,
+        }
+    }
+
+    fn ffi_args(&self) -> (c_ulonglong, c_longlong) {
+        (
+            self.id.0,
+            c_longlong::try_from(self.timeout.as_millis()).unwrap_or(c_longlong::MAX),
+        )
+    }
+}
+
+/// The result envelope every Go function returns as a JSON document.
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    ok: bool,
+
+    #[serde(default)]
+    data: serde_json::Value,
+    error: Option<Error>,
+}
 
 pub fn install_helm_release(
     release_name: &str,
@@ -15,13 +149,19 @@ pub fn install_helm_release(
     chart_version: &str,
     values_yaml: &str,
     namespace: &str,
-    suppress_output: bool,
-) -> String {
+    chart_digest: &str,
+    operation: &Operation,
+) -> Result<(), Error> {
     let release_name = CString::new(release_name).unwrap();
     let chart_name = CString::new(chart_name).unwrap();
     let chart_version = CString::new(chart_version).unwrap();
     let values_yaml = CString::new(values_yaml).unwrap();
     let namespace = CString::new(namespace).unwrap();
+    let chart_digest = CString::new(chart_digest).unwrap();
+
+    let (operation_id, timeout_millis) = operation.ffi_args();
+
+    register_log_callback();
 
     unsafe {
         let c = go_install_helm_release(
@@ -30,76 +170,355 @@ pub fn install_helm_release(
             chart_version.as_ptr() as *mut c_char,
             values_yaml.as_ptr() as *mut c_char,
             namespace.as_ptr() as *mut c_char,
-            suppress_output as u8,
+            chart_digest.as_ptr() as *mut c_char,
+            operation_id,
+            timeout_millis,
+        );
+
+        decode_result(c)
+    }
+}
+
+pub fn upgrade_helm_release(
+    release_name: &str,
+    chart_name: &str,
+    chart_version: &str,
+    values_yaml: &str,
+    namespace: &str,
+    chart_digest: &str,
+    force: bool,
+    operation: &Operation,
+) -> Result<(), Error> {
+    let release_name = CString::new(release_name).unwrap();
+    let chart_name = CString::new(chart_name).unwrap();
+    let chart_version = CString::new(chart_version).unwrap();
+    let values_yaml = CString::new(values_yaml).unwrap();
+    let namespace = CString::new(namespace).unwrap();
+    let chart_digest = CString::new(chart_digest).unwrap();
+
+    let (operation_id, timeout_millis) = operation.ffi_args();
+
+    register_log_callback();
+
+    unsafe {
+        let c = go_upgrade_helm_release(
+            release_name.as_ptr() as *mut c_char,
+            chart_name.as_ptr() as *mut c_char,
+            chart_version.as_ptr() as *mut c_char,
+            values_yaml.as_ptr() as *mut c_char,
+            namespace.as_ptr() as *mut c_char,
+            chart_digest.as_ptr() as *mut c_char,
+            force as u8,
+            operation_id,
+            timeout_millis,
+        );
+
+        decode_result(c)
+    }
+}
+
+pub fn rollback_helm_release(
+    release_name: &str,
+    namespace: &str,
+    revision: i32,
+    operation: &Operation,
+) -> Result<(), Error> {
+    let release_name = CString::new(release_name).unwrap();
+    let namespace = CString::new(namespace).unwrap();
+    let (operation_id, timeout_millis) = operation.ffi_args();
+
+    register_log_callback();
+
+    unsafe {
+        let c = go_rollback_helm_release(
+            release_name.as_ptr() as *mut c_char,
+            namespace.as_ptr() as *mut c_char,
+            revision,
+            operation_id,
+            timeout_millis,
+        );
+
+        decode_result(c)
+    }
+}
+
+/// Returns the revision history of the release, deserialized into `T`.
+pub fn helm_release_history<T: DeserializeOwned>(
+    release_name: &str,
+    namespace: &str,
+) -> Result<T, Error> {
+    let release_name = CString::new(release_name).unwrap();
+    let namespace = CString::new(namespace).unwrap();
+
+    register_log_callback();
+
+    unsafe {
+        let c = go_helm_release_history(
+            release_name.as_ptr() as *mut c_char,
+            namespace.as_ptr() as *mut c_char,
         );
 
-        cstr_ptr_to_string(c)
+        decode_result(c)
+    }
+}
+
+/// Returns the user-supplied values of the deployed revision of the release,
+/// deserialized into `T`.
+pub fn helm_release_values<T: DeserializeOwned>(
+    release_name: &str,
+    namespace: &str,
+) -> Result<T, Error> {
+    let release_name = CString::new(release_name).unwrap();
+    let namespace = CString::new(namespace).unwrap();
+
+    register_log_callback();
+
+    unsafe {
+        let c = go_helm_release_values(
+            release_name.as_ptr() as *mut c_char,
+            namespace.as_ptr() as *mut c_char,
+        );
+
+        decode_result(c)
     }
 }
 
 pub fn uninstall_helm_release(
     release_name: &str,
     namespace: &str,
-    suppress_output: bool,
-) -> String {
+    operation: &Operation,
+) -> Result<(), Error> {
     let release_name = CString::new(release_name).unwrap();
     let namespace = CString::new(namespace).unwrap();
+    let (operation_id, timeout_millis) = operation.ffi_args();
+
+    register_log_callback();
 
     unsafe {
         let c = go_uninstall_helm_release(
             release_name.as_ptr() as *mut c_char,
             namespace.as_ptr() as *mut c_char,
-            suppress_output as u8,
+            operation_id,
+            timeout_millis,
+        );
+
+        decode_result(c)
+    }
+}
+
+pub fn template_helm_release(
+    release_name: &str,
+    chart_name: &str,
+    chart_version: &str,
+    values_yaml: &str,
+    namespace: &str,
+) -> Result<String, Error> {
+    let release_name = CString::new(release_name).unwrap();
+    let chart_name = CString::new(chart_name).unwrap();
+    let chart_version = CString::new(chart_version).unwrap();
+    let values_yaml = CString::new(values_yaml).unwrap();
+    let namespace = CString::new(namespace).unwrap();
+
+    register_log_callback();
+
+    unsafe {
+        let c = go_template_helm_release(
+            release_name.as_ptr() as *mut c_char,
+            chart_name.as_ptr() as *mut c_char,
+            chart_version.as_ptr() as *mut c_char,
+            values_yaml.as_ptr() as *mut c_char,
+            namespace.as_ptr() as *mut c_char,
+        );
+
+        decode_result(c)
+    }
+}
+
+/// Returns the default values and the values schema of the chart,
+/// deserialized into `T`.
+pub fn helm_chart_values<T: DeserializeOwned>(
+    chart_name: &str,
+    chart_version: &str,
+) -> Result<T, Error> {
+    let chart_name = CString::new(chart_name).unwrap();
+    let chart_version = CString::new(chart_version).unwrap();
+
+    register_log_callback();
+
+    unsafe {
+        let c = go_helm_chart_values(
+            chart_name.as_ptr() as *mut c_char,
+            chart_version.as_ptr() as *mut c_char,
         );
 
-        cstr_ptr_to_string(c)
+        decode_result(c)
     }
 }
 
-// TODO (@NickLarsenNZ): Add tracing to helm-sys, maybe?
-// #[instrument]
-pub fn check_helm_release_exists(release_name: &str, namespace: &str) -> bool {
+pub fn check_helm_release_exists(release_name: &str, namespace: &str) -> Result<bool, Error> {
     let release_name = CString::new(release_name).unwrap();
     let namespace = CString::new(namespace).unwrap();
 
+    register_log_callback();
+
     unsafe {
-        go_helm_release_exists(
+        let c = go_helm_release_exists(
             release_name.as_ptr() as *mut c_char,
             namespace.as_ptr() as *mut c_char,
-        ) != 0
+        );
+
+        decode_result(c)
     }
 }
 
-pub fn list_helm_releases(namespace: &str) -> String {
+/// Returns the releases in the namespace, deserialized into `T`.
+pub fn list_helm_releases<T: DeserializeOwned>(namespace: &str) -> Result<T, Error> {
     let namespace = CString::new(namespace).unwrap();
 
+    register_log_callback();
+
     unsafe {
         let c = go_helm_list_releases(namespace.as_ptr() as *mut c_char);
-        cstr_ptr_to_string(c)
+        decode_result(c)
     }
 }
 
-pub fn add_helm_repository(repository_name: &str, repository_url: &str) -> String {
+/// Adds a Helm repository. The `username`, `password` and `ca_file` are
+/// optional and ignored if empty.
+pub fn add_helm_repository(
+    repository_name: &str,
+    repository_url: &str,
+    username: &str,
+    password: &str,
+    ca_file: &str,
+) -> Result<(), Error> {
     let repository_name = CString::new(repository_name).unwrap();
     let repository_url = CString::new(repository_url).unwrap();
+    let username = CString::new(username).unwrap();
+    let password = CString::new(password).unwrap();
+    let ca_file = CString::new(ca_file).unwrap();
+
+    register_log_callback();
 
     unsafe {
         let c = go_add_helm_repo(
             repository_name.as_ptr() as *mut c_char,
             repository_url.as_ptr() as *mut c_char,
+            username.as_ptr() as *mut c_char,
+            password.as_ptr() as *mut c_char,
+            ca_file.as_ptr() as *mut c_char,
         );
 
-        cstr_ptr_to_string(c)
+        decode_result(c)
     }
 }
 
-/// Checks if the result string is an error, and if so, returns the error message as a string.
-pub fn to_helm_error(result: &str) -> Option<String> {
-    if !result.is_empty() && result.starts_with(HELM_ERROR_PREFIX) {
-        return Some(result.replace(HELM_ERROR_PREFIX, ""));
+/// Configures access to the OCI registry at `host` for all subsequent calls.
+/// The credentials are only kept in memory and never written to the Helm
+/// registry config of the user. Empty values are ignored. If `plain_http` is
+/// set, the registry is accessed using plain HTTP instead of HTTPS.
+pub fn configure_helm_registry(
+    host: &str,
+    username: &str,
+    password: &str,
+    ca_file: &str,
+    plain_http: bool,
+) -> Result<(), Error> {
+    let host = CString::new(host).unwrap();
+    let username = CString::new(username).unwrap();
+    let password = CString::new(password).unwrap();
+    let ca_file = CString::new(ca_file).unwrap();
+
+    register_log_callback();
+
+    unsafe {
+        let c = go_configure_helm_registry(
+            host.as_ptr() as *mut c_char,
+            username.as_ptr() as *mut c_char,
+            password.as_ptr() as *mut c_char,
+            ca_file.as_ptr() as *mut c_char,
+            plain_http as u8,
+        );
+
+        decode_result(c)
     }
+}
+
+/// Cancels the running [`Operation`] with the provided `id`. The cancelled
+/// call into the Go wrapper returns shortly after, install and upgrade
+/// operations mark the affected release as failed. Operations which haven't
+/// started yet are cancelled as soon as they start.
+pub fn cancel_helm_operation(id: OperationId) {
+    unsafe { go_cancel_helm_operation(id.0) }
+}
 
-    None
+/// Registers [`log_callback`] with the Go wrapper, so that log messages of the
+/// Go wrapper and the Helm SDK are emitted as [`tracing`] events. This only
+/// needs to happen once and is done before every call into the Go wrapper.
+fn register_log_callback() {
+    REGISTER_LOG_CALLBACK.call_once(|| unsafe { go_set_log_callback(Some(log_callback)) });
+}
+
+/// Emits a log message of the Go wrapper as a [`tracing`] event. Log messages
+/// can be emitted on arbitrary threads, so instead of relying on the current
+/// span, every event is emitted in a span with the release name and namespace
+/// the message belongs to. The strings are owned (and freed) by Go.
+extern "C" fn log_callback(
+    level: c_int,
+    release_name: *mut c_char,
+    namespace: *mut c_char,
+    message: *mut c_char,
+) {
+    let (release_name, namespace, message) = unsafe {
+        (
+            CStr::from_ptr(release_name).to_string_lossy(),
+            CStr::from_ptr(namespace).to_string_lossy(),
+            CStr::from_ptr(message).to_string_lossy(),
+        )
+    };
+
+    // The span uses the highest level, so that it is enabled whenever the
+    // event is. Empty fields (e.g. for klog messages) are not recorded.
+    let span = tracing::error_span!(
+        target: LOG_TARGET,
+        "helm",
+        release_name = non_empty(&release_name),
+        namespace = non_empty(&namespace),
+    );
+    let _guard = span.enter();
+
+    match level {
+        LOG_LEVEL_DEBUG => debug!(target: LOG_TARGET, "{message}"),
+        LOG_LEVEL_INFO => info!(target: LOG_TARGET, "{message}"),
+        LOG_LEVEL_WARN => warn!(target: LOG_TARGET, "{message}"),
+        _ => error!(target: LOG_TARGET, "{message}"),
+    }
+}
+
+fn non_empty(value: &str) -> Option<&str> {
+    (!value.is_empty()).then_some(value)
+}
+
+/// Decodes the result envelope returned by the Go wrapper. The data of
+/// successful calls is deserialized into `T`, failed calls are turned into an
+/// [`Error`]. The pointer **cannot** be used afterwards.
+unsafe fn decode_result<T: DeserializeOwned>(c: *mut c_char) -> Result<T, Error> {
+    let raw = unsafe { cstr_ptr_to_string(c) };
+    parse_result(&raw)
+}
+
+fn parse_result<T: DeserializeOwned>(raw: &str) -> Result<T, Error> {
+    let envelope: Envelope = serde_json::from_str(raw)
+        .map_err(|err| Error::other(format!("failed to parse result of Helm wrapper: {err}")))?;
+
+    if !envelope.ok {
+        return Err(envelope
+            .error
+            .unwrap_or_else(|| Error::other("Helm wrapper failed without providing an error")));
+    }
+
+    serde_json::from_value(envelope.data)
+        .map_err(|err| Error::other(format!("failed to parse data of Helm wrapper: {err}")))
 }
 
 /// Converts a raw C string pointer into an owned Rust [`String`]. This function
@@ -112,3 +531,35 @@ unsafe fn cstr_ptr_to_string(c: *mut c_char) -> String {
 
     s
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_successful_result() {
+        let exists: bool = parse_result(r#"{"ok":true,"data":true}"#).unwrap();
+        assert!(exists);
+
+        parse_result::<()>(r#"{"ok":true,"data":null}"#).unwrap();
+    }
+
+    #[test]
+    fn parse_failed_result() {
+        let error = parse_result::<()>(
+            r#"{"ok":false,"data":null,"error":{"kind":"conflict","message":"already exists","status":"failed","resource":"ConfigMap/foo"}}"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Conflict);
+        assert_eq!(error.status.as_deref(), Some("failed"));
+        assert_eq!(error.resource.as_deref(), Some("ConfigMap/foo"));
+
+        let error = parse_result::<()>(
+            r#"{"ok":false,"data":null,"error":{"kind":"somethingNew","message":"boom"}}"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Other);
+    }
+}
@@ -21,11 +21,15 @@ pub const HELM_REPO_NAME_STABLE: &str = "stackable-stable";
 pub const HELM_REPO_NAME_TEST: &str = "stackable-test";
 pub const HELM_REPO_NAME_DEV: &str = "stackable-dev";
 pub const HELM_REPO_INDEX_FILE: &str = "index.yaml";
+pub const HELM_CHART_FILE: &str = "Chart.yaml";
 
 pub const HELM_OCI_BASE: &str = "oci.stackable.tech";
-pub const HELM_OCI_REGISTRY: &str = "oci://oci.stackable.tech/sdp-charts";
+pub const HELM_OCI_PROJECT: &str = "sdp-charts";
 
 pub const HELM_DEFAULT_CHART_VERSION: &str = "0.0.0-dev";
+// Helm itself waits up to 20 minutes for resources to become ready, so this
+// needs to be a little longer than that.
+pub const HELM_DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 25); // 25 minutes
 
 /// Tuple of (product name, group, version, kind)
 /// Group is usually `<product name>.stackable.tech`.
@@ -85,3 +89,5 @@ pub const PRODUCTS: &[(&str, &str, &str, &str)] = &[
 ];
 
 pub const OCI_INDEX_PAGE_SIZE: usize = 20;
+pub const OCI_TAGS_PAGE_SIZE: usize = 100;
+pub const OCI_CATALOG_PAGE_SIZE: usize = 1000;
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use snafu::{ResultExt, Snafu};
+
+use crate::constants::REDACTED_PASSWORD;
+
+#[derive(Debug, Snafu)]
+pub enum AuthError {
+    #[snafu(display("failed to read CA bundle from {path:?}"))]
+    ReadCaFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to parse CA bundle at {path:?}"))]
+    ParseCaFile {
+        source: reqwest::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to initialize http client"))]
+    InitializeClient { source: reqwest::Error },
+}
+
+/// Credentials and TLS settings used to access (private) Helm repositories and
+/// OCI registries. The default is anonymous access, verified using the system
+/// root certificates.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct RepositoryAuth {
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    /// Path to a PEM-encoded CA bundle, which is used to verify the TLS
+    /// certificate of the repository in addition to the system root
+    /// certificates.
+    pub ca_file: Option<PathBuf>,
+}
+
+// Make sure the password never ends up in logs
+impl std::fmt::Debug for RepositoryAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RepositoryAuth")
+            .field("username", &self.username)
+            .field(
+                "password",
+                &self.password.as_ref().map(|_| REDACTED_PASSWORD),
+            )
+            .field("ca_file", &self.ca_file)
+            .finish()
+    }
+}
+
+impl RepositoryAuth {
+    /// Returns if credentials are configured.
+    pub fn has_credentials(&self) -> bool {
+        self.username.is_some()
+    }
+
+    /// Builds a HTTP client, which trusts the configured CA bundle.
+    pub fn http_client(&self) -> Result<reqwest::Client, AuthError> {
+        let mut builder = reqwest::Client::builder().user_agent("stackable-cockpit");
+
+        if let Some(path) = &self.ca_file {
+            let pem = std::fs::read(path).context(ReadCaFileSnafu { path })?;
+            let certificates =
+                reqwest::Certificate::from_pem_bundle(&pem).context(ParseCaFileSnafu { path })?;
+
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        builder.build().context(InitializeClientSnafu)
+    }
+
+    /// Adds the configured credentials (if any) to the `request` using basic
+    /// auth.
+    pub fn authenticate(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt::Debug;
+
+use serde_yaml::Mapping;
+use tracing::error;
+
+use crate::helm::{
+    ChartValues, HelmError, HelmErrorKind, HelmOperation, HelmOperationId, Release,
+    ReleaseRevision, RepositoryAuth,
+};
+
+/// Describes a Helm release which should be installed, upgraded or rendered.
+///
+/// The `chart_name` is the fully qualified chart name, e.g.
+/// `stackable-stable/airflow-operator` or the path of a local chart.
+#[derive(Clone, Debug)]
+pub struct ReleaseRequest {
+    pub release_name: String,
+    pub chart_name: String,
+    pub chart_version: String,
+    pub values_yaml: String,
+    pub namespace: String,
+
+    /// The digest the chart must have, see
+    /// [`Client::with_chart_digest`](crate::helm::Client::with_chart_digest).
+    pub chart_digest: Option<String>,
+}
+
+/// The low-level Helm operations used by the [`Client`](crate::helm::Client).
+///
+/// The default implementation is the [`FfiBackend`], which calls into the Go
+/// Helm library. The [`FakeBackend`](crate::helm::FakeBackend) simulates
+/// release state in-memory, which enables testing without a cluster.
+///
+/// Operations which modify releases receive a [`HelmOperation`], which limits
+/// their duration and allows cancelling them using
+/// [`HelmBackend::cancel_operation`].
+pub trait HelmBackend: Debug + Send + Sync {
+    fn install_release(
+        &self,
+        request: &ReleaseRequest,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError>;
+
+    /// Upgrades an existing release in-place. If `force` is set, conflicting
+    /// resources are deleted and re-created instead of being patched.
+    fn upgrade_release(
+        &self,
+        request: &ReleaseRequest,
+        force: bool,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError>;
+
+    /// Rolls back a release to the provided `revision`. If no revision is
+    /// provided, the release is rolled back to the previous revision.
+    fn rollback_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        revision: Option<u32>,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError>;
+
+    fn uninstall_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError>;
+
+    /// Cancels the running operation with the provided `id`. The cancelled
+    /// operation returns shortly after, without leaving the release in a
+    /// pending state.
+    fn cancel_operation(&self, id: HelmOperationId);
+
+    /// Renders the release without installing it and returns the resulting
+    /// manifests as a multi-document YAML string.
+    fn template_release(&self, request: &ReleaseRequest) -> Result<String, HelmError>;
+
+    fn release_exists(&self, release_name: &str, namespace: &str) -> Result<bool, HelmError>;
+
+    fn list_releases(&self, namespace: &str) -> Result<Vec<Release>, HelmError>;
+
+    /// Returns the revision history of a release, oldest revision first.
+    fn release_history(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<Vec<ReleaseRevision>, HelmError>;
+
+    /// Returns the user-supplied values of the deployed revision of a release.
+    fn release_values(&self, release_name: &str, namespace: &str) -> Result<Mapping, HelmError>;
+
+    /// Returns the default values and the values schema of a chart. The
+    /// `chart_name` is fully qualified, see [`ReleaseRequest`].
+    fn chart_values(&self, chart_name: &str, chart_version: &str)
+    -> Result<ChartValues, HelmError>;
+
+    fn add_repo(
+        &self,
+        repository_name: &str,
+        repository_url: &str,
+        auth: &RepositoryAuth,
+    ) -> Result<(), HelmError>;
+
+    /// Configures access to the OCI registry at `host`, e.g. the credentials
+    /// used for all subsequent operations.
+    fn configure_registry(
+        &self,
+        host: &str,
+        auth: &RepositoryAuth,
+        plain_http: bool,
+    ) -> Result<(), HelmError>;
+}
+
+/// Calls into the Go Helm library using the `helm-sys` FFI bindings.
+#[derive(Debug, Default)]
+pub struct FfiBackend;
+
+impl HelmBackend for FfiBackend {
+    fn install_release(
+        &self,
+        request: &ReleaseRequest,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        helm_sys::install_helm_release(
+            &request.release_name,
+            &request.chart_name,
+            &request.chart_version,
+            &request.values_yaml,
+            &request.namespace,
+            request.chart_digest.as_deref().unwrap_or_default(),
+            operation,
+        )
+        .inspect_err(|error| {
+            error!("Go wrapper function go_install_helm_release encountered an error: {error}")
+        })
+    }
+
+    fn upgrade_release(
+        &self,
+        request: &ReleaseRequest,
+        force: bool,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        helm_sys::upgrade_helm_release(
+            &request.release_name,
+            &request.chart_name,
+            &request.chart_version,
+            &request.values_yaml,
+            &request.namespace,
+            request.chart_digest.as_deref().unwrap_or_default(),
+            force,
+            operation,
+        )
+        .inspect_err(|error| {
+            error!("Go wrapper function go_upgrade_helm_release encountered an error: {error}")
+        })
+    }
+
+    fn rollback_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        revision: Option<u32>,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        // Helm uses revision 0 to refer to the previous revision
+        let revision = match revision {
+            Some(revision) => i32::try_from(revision).map_err(|_| HelmError {
+                kind: HelmErrorKind::Other,
+                message: format!("revision {revision} of release {release_name:?} is out of range"),
+                status: None,
+                resource: None,
+            })?,
+            None => 0,
+        };
+
+        helm_sys::rollback_helm_release(release_name, namespace, revision, operation).inspect_err(
+            |error| {
+                error!("Go wrapper function go_rollback_helm_release encountered an error: {error}")
+            },
+        )
+    }
+
+    fn uninstall_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        helm_sys::uninstall_helm_release(release_name, namespace, operation).inspect_err(|error| {
+            error!("Go wrapper function go_uninstall_helm_release encountered an error: {error}")
+        })
+    }
+
+    fn cancel_operation(&self, id: HelmOperationId) {
+        helm_sys::cancel_helm_operation(id)
+    }
+
+    fn template_release(&self, request: &ReleaseRequest) -> Result<String, HelmError> {
+        helm_sys::template_helm_release(
+            &request.release_name,
+            &request.chart_name,
+            &request.chart_version,
+            &request.values_yaml,
+            &request.namespace,
+        )
+        .inspect_err(|error| {
+            error!("Go wrapper function go_template_helm_release encountered an error: {error}")
+        })
+    }
+
+    fn release_exists(&self, release_name: &str, namespace: &str) -> Result<bool, HelmError> {
+        helm_sys::check_helm_release_exists(release_name, namespace).inspect_err(|error| {
+            error!("Go wrapper function go_helm_release_exists encountered an error: {error}")
+        })
+    }
+
+    fn list_releases(&self, namespace: &str) -> Result<Vec<Release>, HelmError> {
+        helm_sys::list_helm_releases(namespace).inspect_err(|error| {
+            error!("Go wrapper function go_helm_list_releases encountered an error: {error}")
+        })
+    }
+
+    fn release_history(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<Vec<ReleaseRevision>, HelmError> {
+        helm_sys::helm_release_history(release_name, namespace).inspect_err(|error| {
+            error!("Go wrapper function go_helm_release_history encountered an error: {error}")
+        })
+    }
+
+    fn release_values(&self, release_name: &str, namespace: &str) -> Result<Mapping, HelmError> {
+        helm_sys::helm_release_values(release_name, namespace).inspect_err(|error| {
+            error!("Go wrapper function go_helm_release_values encountered an error: {error}")
+        })
+    }
+
+    fn chart_values(
+        &self,
+        chart_name: &str,
+        chart_version: &str,
+    ) -> Result<ChartValues, HelmError> {
+        helm_sys::helm_chart_values(chart_name, chart_version).inspect_err(|error| {
+            error!("Go wrapper function go_helm_chart_values encountered an error: {error}")
+        })
+    }
+
+    fn add_repo(
+        &self,
+        repository_name: &str,
+        repository_url: &str,
+        auth: &RepositoryAuth,
+    ) -> Result<(), HelmError> {
+        let (username, password, ca_file) = ffi_auth(auth);
+
+        helm_sys::add_helm_repository(
+            repository_name,
+            repository_url,
+            username,
+            password,
+            &ca_file,
+        )
+        .inspect_err(|error| {
+            error!("Go wrapper function go_add_helm_repo encountered an error: {error}")
+        })
+    }
+
+    fn configure_registry(
+        &self,
+        host: &str,
+        auth: &RepositoryAuth,
+        plain_http: bool,
+    ) -> Result<(), HelmError> {
+        let (username, password, ca_file) = ffi_auth(auth);
+
+        helm_sys::configure_helm_registry(host, username, password, &ca_file, plain_http)
+            .inspect_err(|error| {
+                error!(
+                    "Go wrapper function go_configure_helm_registry encountered an error: {error}"
+                )
+            })
+    }
+}
+
+/// Returns the username, password and CA file path of `auth`, the Go wrapper
+/// uses empty strings for unset values.
+fn ffi_auth(auth: &RepositoryAuth) -> (&str, &str, String) {
+    (
+        auth.username.as_deref().unwrap_or_default(),
+        auth.password.as_deref().unwrap_or_default(),
+        auth.ca_file
+            .as_ref()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    )
+}
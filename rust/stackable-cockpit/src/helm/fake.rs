@@ -0,0 +1,558 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use serde_yaml::Mapping;
+
+use crate::helm::{
+    ChartValues, HelmBackend, HelmError, HelmErrorKind, HelmOperation, HelmOperationId, Release,
+    ReleaseRequest, ReleaseRevision, RepositoryAuth,
+};
+
+const STATUS_DEPLOYED: &str = "deployed";
+const STATUS_FAILED: &str = "failed";
+const STATUS_SUPERSEDED: &str = "superseded";
+
+/// A call recorded by the [`FakeBackend`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FakeCall {
+    InstallRelease {
+        release_name: String,
+        chart_name: String,
+        chart_version: String,
+        namespace: String,
+    },
+    UpgradeRelease {
+        release_name: String,
+        chart_name: String,
+        chart_version: String,
+        namespace: String,
+        force: bool,
+    },
+    RollbackRelease {
+        release_name: String,
+        namespace: String,
+        revision: Option<u32>,
+    },
+    UninstallRelease {
+        release_name: String,
+        namespace: String,
+    },
+    TemplateRelease {
+        release_name: String,
+        chart_name: String,
+        chart_version: String,
+        namespace: String,
+    },
+    ReleaseExists {
+        release_name: String,
+        namespace: String,
+    },
+    ListReleases {
+        namespace: String,
+    },
+    ReleaseHistory {
+        release_name: String,
+        namespace: String,
+    },
+    ReleaseValues {
+        release_name: String,
+        namespace: String,
+    },
+    ChartValues {
+        chart_name: String,
+        chart_version: String,
+    },
+    AddRepo {
+        repository_name: String,
+        repository_url: String,
+        auth: RepositoryAuth,
+    },
+    ConfigureRegistry {
+        host: String,
+        auth: RepositoryAuth,
+        plain_http: bool,
+    },
+    CancelOperation,
+}
+
+/// The operations of the [`HelmBackend`], used to inject errors into the
+/// [`FakeBackend`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FakeOperation {
+    InstallRelease,
+    UpgradeRelease,
+    RollbackRelease,
+    UninstallRelease,
+    TemplateRelease,
+    ReleaseExists,
+    ListReleases,
+    ReleaseHistory,
+    ReleaseValues,
+    ChartValues,
+    AddRepo,
+    ConfigureRegistry,
+}
+
+/// An in-memory [`HelmBackend`], which records all calls and simulates the
+/// state of Helm releases. It never talks to a cluster and is intended to be
+/// used in tests.
+///
+/// Clones share the same state, so a clone can be handed to a
+/// [`Client`](crate::helm::Client) while the original is used to inspect the
+/// recorded calls and releases.
+#[derive(Clone, Debug, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    calls: Vec<FakeCall>,
+    errors: Vec<(FakeOperation, HelmError)>,
+
+    /// Revisions of all releases, keyed by namespace and release name.
+    releases: BTreeMap<(String, String), Vec<ReleaseRevision>>,
+
+    /// Values of the latest successful install or upgrade of all releases,
+    /// keyed by namespace and release name.
+    values: BTreeMap<(String, String), Mapping>,
+
+    /// Manifests returned when rendering a chart, keyed by chart name.
+    manifests: BTreeMap<String, String>,
+
+    /// Default values and values schemas of charts, keyed by chart name.
+    chart_values: BTreeMap<String, ChartValues>,
+}
+
+impl FakeBackend {
+    /// Adds an already deployed release with the provided chart `version`.
+    pub fn with_release(self, namespace: &str, release_name: &str, version: &str) -> Self {
+        self.state().push_revision(
+            namespace,
+            release_name,
+            version,
+            STATUS_DEPLOYED,
+            "Install complete".to_owned(),
+        );
+        self
+    }
+
+    /// Sets the manifests which are returned when rendering the chart with
+    /// the fully qualified `chart_name`.
+    pub fn with_manifests(self, chart_name: &str, manifests: &str) -> Self {
+        self.state()
+            .manifests
+            .insert(chart_name.to_owned(), manifests.to_owned());
+        self
+    }
+
+    /// Sets the default values and values schema which are returned for the
+    /// chart with the fully qualified `chart_name`.
+    pub fn with_chart_values(self, chart_name: &str, chart_values: ChartValues) -> Self {
+        self.state()
+            .chart_values
+            .insert(chart_name.to_owned(), chart_values);
+        self
+    }
+
+    /// Lets the next call of `operation` fail with the provided `error`.
+    /// Multiple errors for the same operation are returned in the order they
+    /// were added.
+    pub fn fail_next(&self, operation: FakeOperation, error: HelmError) {
+        self.state().errors.push((operation, error));
+    }
+
+    /// Returns all calls recorded so far.
+    pub fn calls(&self) -> Vec<FakeCall> {
+        self.state().calls.clone()
+    }
+
+    /// Returns the chart version of the currently deployed revision of a
+    /// release, if it exists.
+    pub fn release_version(&self, namespace: &str, release_name: &str) -> Option<String> {
+        self.state()
+            .releases
+            .get(&(namespace.to_owned(), release_name.to_owned()))
+            .and_then(|revisions| revisions.last())
+            .map(|revision| revision.chart_version.clone())
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeState> {
+        // A poisoned lock means that another test thread panicked, so there is
+        // no point in continuing
+        self.state
+            .lock()
+            .expect("fake Helm backend state is poisoned")
+    }
+
+    /// Records the call and returns the locked state.
+    fn record(&self, call: FakeCall) -> MutexGuard<'_, FakeState> {
+        let mut state = self.state();
+        state.calls.push(call);
+        state
+    }
+}
+
+impl FakeState {
+    /// Returns the next queued error for `operation`, if any.
+    fn take_error(&mut self, operation: FakeOperation) -> Result<(), HelmError> {
+        match self.errors.iter().position(|(op, _)| *op == operation) {
+            Some(index) => Err(self.errors.remove(index).1),
+            None => Ok(()),
+        }
+    }
+
+    fn revisions(&self, namespace: &str, release_name: &str) -> Option<&Vec<ReleaseRevision>> {
+        self.releases
+            .get(&(namespace.to_owned(), release_name.to_owned()))
+    }
+
+    /// Stores the values of a successful install or upgrade. Values which
+    /// aren't a YAML mapping are stored as empty values.
+    fn set_values(&mut self, request: &ReleaseRequest) {
+        let values = serde_yaml::from_str(&request.values_yaml).unwrap_or_default();
+
+        self.values.insert(
+            (request.namespace.clone(), request.release_name.clone()),
+            values,
+        );
+    }
+
+    fn push_revision(
+        &mut self,
+        namespace: &str,
+        release_name: &str,
+        chart_version: &str,
+        status: &str,
+        description: String,
+    ) {
+        let revisions = self
+            .releases
+            .entry((namespace.to_owned(), release_name.to_owned()))
+            .or_default();
+
+        // Like Helm, only successful revisions supersede the deployed one
+        if status == STATUS_DEPLOYED {
+            for revision in revisions.iter_mut() {
+                if revision.status == STATUS_DEPLOYED {
+                    revision.status = STATUS_SUPERSEDED.to_owned();
+                }
+            }
+        }
+
+        revisions.push(ReleaseRevision {
+            revision: revisions.len() as u32 + 1,
+            chart_version: chart_version.to_owned(),
+            app_version: chart_version.to_owned(),
+            status: status.to_owned(),
+            updated: String::new(),
+            description,
+        });
+    }
+}
+
+impl HelmBackend for FakeBackend {
+    fn install_release(
+        &self,
+        request: &ReleaseRequest,
+        _operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        let mut state = self.record(FakeCall::InstallRelease {
+            release_name: request.release_name.clone(),
+            chart_name: request.chart_name.clone(),
+            chart_version: request.chart_version.clone(),
+            namespace: request.namespace.clone(),
+        });
+        state.take_error(FakeOperation::InstallRelease)?;
+
+        if state
+            .revisions(&request.namespace, &request.release_name)
+            .is_some()
+        {
+            return Err(fake_error(
+                HelmErrorKind::Conflict,
+                "cannot re-use a name that is still in use",
+            ));
+        }
+
+        state.push_revision(
+            &request.namespace,
+            &request.release_name,
+            &request.chart_version,
+            STATUS_DEPLOYED,
+            "Install complete".to_owned(),
+        );
+        state.set_values(request);
+
+        Ok(())
+    }
+
+    fn upgrade_release(
+        &self,
+        request: &ReleaseRequest,
+        force: bool,
+        _operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        let mut state = self.record(FakeCall::UpgradeRelease {
+            release_name: request.release_name.clone(),
+            chart_name: request.chart_name.clone(),
+            chart_version: request.chart_version.clone(),
+            namespace: request.namespace.clone(),
+            force,
+        });
+
+        if state
+            .revisions(&request.namespace, &request.release_name)
+            .is_none()
+        {
+            return Err(release_not_found());
+        }
+
+        // Like Helm, failed upgrades leave a failed revision behind
+        if let Err(error) = state.take_error(FakeOperation::UpgradeRelease) {
+            state.push_revision(
+                &request.namespace,
+                &request.release_name,
+                &request.chart_version,
+                STATUS_FAILED,
+                format!("Upgrade failed: {message}", message = error.message),
+            );
+
+            return Err(error);
+        }
+
+        state.push_revision(
+            &request.namespace,
+            &request.release_name,
+            &request.chart_version,
+            STATUS_DEPLOYED,
+            "Upgrade complete".to_owned(),
+        );
+        state.set_values(request);
+
+        Ok(())
+    }
+
+    fn rollback_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        revision: Option<u32>,
+        _operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        let mut state = self.record(FakeCall::RollbackRelease {
+            release_name: release_name.to_owned(),
+            namespace: namespace.to_owned(),
+            revision,
+        });
+        state.take_error(FakeOperation::RollbackRelease)?;
+
+        let revisions = state
+            .revisions(namespace, release_name)
+            .ok_or_else(release_not_found)?;
+
+        // Like Helm, no revision refers to the previous revision
+        let target = revision.unwrap_or(revisions.len() as u32 - 1);
+        let chart_version = revisions
+            .iter()
+            .find(|r| r.revision == target)
+            .map(|r| r.chart_version.clone())
+            .ok_or_else(|| {
+                fake_error(
+                    HelmErrorKind::Other,
+                    format!("release has no revision {target}"),
+                )
+            })?;
+
+        state.push_revision(
+            namespace,
+            release_name,
+            &chart_version,
+            STATUS_DEPLOYED,
+            format!("Rollback to {target}"),
+        );
+
+        Ok(())
+    }
+
+    fn uninstall_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        _operation: &HelmOperation,
+    ) -> Result<(), HelmError> {
+        let mut state = self.record(FakeCall::UninstallRelease {
+            release_name: release_name.to_owned(),
+            namespace: namespace.to_owned(),
+        });
+        state.take_error(FakeOperation::UninstallRelease)?;
+
+        state
+            .values
+            .remove(&(namespace.to_owned(), release_name.to_owned()));
+        state
+            .releases
+            .remove(&(namespace.to_owned(), release_name.to_owned()))
+            .map(|_| ())
+            .ok_or_else(release_not_found)
+    }
+
+    fn template_release(&self, request: &ReleaseRequest) -> Result<String, HelmError> {
+        let mut state = self.record(FakeCall::TemplateRelease {
+            release_name: request.release_name.clone(),
+            chart_name: request.chart_name.clone(),
+            chart_version: request.chart_version.clone(),
+            namespace: request.namespace.clone(),
+        });
+        state.take_error(FakeOperation::TemplateRelease)?;
+
+        Ok(state
+            .manifests
+            .get(&request.chart_name)
+            .cloned()
+            .unwrap_or_else(|| {
+                format!(
+                    "---\n# Source: {chart_name}\n",
+                    chart_name = request.chart_name
+                )
+            }))
+    }
+
+    fn release_exists(&self, release_name: &str, namespace: &str) -> Result<bool, HelmError> {
+        let mut state = self.record(FakeCall::ReleaseExists {
+            release_name: release_name.to_owned(),
+            namespace: namespace.to_owned(),
+        });
+        state.take_error(FakeOperation::ReleaseExists)?;
+
+        Ok(state.revisions(namespace, release_name).is_some())
+    }
+
+    fn list_releases(&self, namespace: &str) -> Result<Vec<Release>, HelmError> {
+        let mut state = self.record(FakeCall::ListReleases {
+            namespace: namespace.to_owned(),
+        });
+        state.take_error(FakeOperation::ListReleases)?;
+
+        let releases = state
+            .releases
+            .iter()
+            .filter(|((release_namespace, _), _)| release_namespace == namespace)
+            .filter_map(|((release_namespace, name), revisions)| {
+                revisions.last().map(|revision| Release {
+                    name: name.clone(),
+                    version: revision.chart_version.clone(),
+                    namespace: release_namespace.clone(),
+                    status: revision.status.clone(),
+                    last_updated: revision.updated.clone(),
+                })
+            })
+            .collect();
+
+        Ok(releases)
+    }
+
+    fn release_history(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<Vec<ReleaseRevision>, HelmError> {
+        let mut state = self.record(FakeCall::ReleaseHistory {
+            release_name: release_name.to_owned(),
+            namespace: namespace.to_owned(),
+        });
+        state.take_error(FakeOperation::ReleaseHistory)?;
+
+        state
+            .revisions(namespace, release_name)
+            .cloned()
+            .ok_or_else(release_not_found)
+    }
+
+    fn release_values(&self, release_name: &str, namespace: &str) -> Result<Mapping, HelmError> {
+        let mut state = self.record(FakeCall::ReleaseValues {
+            release_name: release_name.to_owned(),
+            namespace: namespace.to_owned(),
+        });
+        state.take_error(FakeOperation::ReleaseValues)?;
+
+        if state.revisions(namespace, release_name).is_none() {
+            return Err(release_not_found());
+        }
+
+        Ok(state
+            .values
+            .get(&(namespace.to_owned(), release_name.to_owned()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn chart_values(
+        &self,
+        chart_name: &str,
+        chart_version: &str,
+    ) -> Result<ChartValues, HelmError> {
+        let mut state = self.record(FakeCall::ChartValues {
+            chart_name: chart_name.to_owned(),
+            chart_version: chart_version.to_owned(),
+        });
+        state.take_error(FakeOperation::ChartValues)?;
+
+        Ok(state
+            .chart_values
+            .get(chart_name)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn add_repo(
+        &self,
+        repository_name: &str,
+        repository_url: &str,
+        auth: &RepositoryAuth,
+    ) -> Result<(), HelmError> {
+        self.record(FakeCall::AddRepo {
+            repository_name: repository_name.to_owned(),
+            repository_url: repository_url.to_owned(),
+            auth: auth.clone(),
+        })
+        .take_error(FakeOperation::AddRepo)
+    }
+
+    fn configure_registry(
+        &self,
+        host: &str,
+        auth: &RepositoryAuth,
+        plain_http: bool,
+    ) -> Result<(), HelmError> {
+        self.record(FakeCall::ConfigureRegistry {
+            host: host.to_owned(),
+            auth: auth.clone(),
+            plain_http,
+        })
+        .take_error(FakeOperation::ConfigureRegistry)
+    }
+
+    // Operations of the fake backend complete immediately, so there is
+    // nothing to cancel
+    fn cancel_operation(&self, _id: HelmOperationId) {
+        self.record(FakeCall::CancelOperation);
+    }
+}
+
+/// Creates a [`HelmError`] of the provided `kind`, e.g. to be used with
+/// [`FakeBackend::fail_next`].
+pub fn fake_error(kind: HelmErrorKind, message: impl Into<String>) -> HelmError {
+    HelmError {
+        kind,
+        message: message.into(),
+        status: None,
+        resource: None,
+    }
+}
+
+fn release_not_found() -> HelmError {
+    fake_error(HelmErrorKind::ReleaseNotFound, "release: not found")
+}
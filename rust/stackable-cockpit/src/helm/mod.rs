@@ -0,0 +1,1510 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
+use snafu::{ResultExt, Snafu};
+use tokio::task::{JoinError, spawn_blocking};
+use tokio_util::sync::CancellationToken;
+use tracing::{Span, debug, info, instrument, warn};
+use tracing_indicatif::span_ext::IndicatifSpanExt as _;
+use url::Url;
+
+use crate::{
+    constants::{
+        HELM_CHART_FILE, HELM_DEFAULT_CHART_VERSION, HELM_DEFAULT_TIMEOUT, HELM_REPO_INDEX_FILE,
+    },
+    utils::chartsource::ChartSourceMetadata,
+};
+
+mod auth;
+mod backend;
+mod fake;
+
+pub use auth::*;
+pub use backend::*;
+pub use fake::*;
+pub use helm_sys::{
+    Error as HelmError, ErrorKind as HelmErrorKind, Operation as HelmOperation,
+    OperationId as HelmOperationId,
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub name: String,
+    pub version: String,
+    pub namespace: String,
+    pub status: String,
+    pub last_updated: String,
+}
+
+/// A single revision of a Helm release, as returned by
+/// [`Client::release_history`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseRevision {
+    pub revision: u32,
+    pub chart_version: String,
+    pub app_version: String,
+    pub status: String,
+    pub updated: String,
+    pub description: String,
+}
+
+/// The default values and the values schema of a chart, as returned by
+/// [`Client::chart_values`].
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ChartValues {
+    #[serde(default)]
+    pub values: Mapping,
+
+    /// The raw `values.schema.json` of the chart, which is empty if the chart
+    /// doesn't provide one.
+    #[serde(default)]
+    pub schema: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chart {
+    pub release_name: String,
+    pub name: String,
+    pub repo: ChartRepo,
+    pub version: String,
+    pub options: serde_yaml::Value,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChartRepo {
+    pub name: String,
+    pub url: String,
+}
+
+/// The kind of source a chart repo URL refers to.
+///
+/// [Self::Oci] and [Self::Local] don't need special handling, but [Self::Repo]
+/// needs to call [`Client::add_repo`].
+///
+/// Charts from a [Self::Local] source need to be resolved using
+/// [`resolve_local_chart`] first.
+#[derive(Debug, PartialEq)]
+pub enum ChartSourceKind {
+    /// OCI registry (url starts with `oci://`)
+    Oci,
+
+    /// Traditional index.yaml-based repository (url starts with `http://` or `https://`)
+    Repo,
+
+    /// Local filesystem path, either a chart directory, a packaged chart
+    /// (`.tgz`) or a directory containing multiple charts.
+    ///
+    /// This is the fallback if not oci or http(s).
+    Local,
+}
+
+impl ChartRepo {
+    /// Determine the kind of chart source based on the URL scheme.
+    pub fn source_kind(&self) -> ChartSourceKind {
+        ChartSourceKind::from_url(&self.url)
+    }
+}
+
+impl ChartSourceKind {
+    /// Determine the kind of chart source based on the scheme of `url`.
+    pub fn from_url(url: &str) -> Self {
+        if url.starts_with("oci://") {
+            Self::Oci
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            Self::Repo
+        } else {
+            Self::Local
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("failed to parse URL"))]
+    UrlParse { source: url::ParseError },
+
+    #[snafu(display("failed to deserialize YAML data"))]
+    DeserializeYaml { source: serde_yaml::Error },
+
+    #[snafu(display("failed to retrieve remote content"))]
+    FetchRemoteContent { source: reqwest::Error },
+
+    #[snafu(display("failed to add Helm repo"))]
+    AddRepo { source: HelmError },
+
+    #[snafu(display("failed to configure Helm registry"))]
+    ConfigureRegistry { source: HelmError },
+
+    #[snafu(display("failed to set up repository authentication"))]
+    RepositoryAuth { source: AuthError },
+
+    #[snafu(display("failed to list Helm releases"))]
+    ListReleases { source: HelmError },
+
+    #[snafu(display("failed to check if Helm release exists"))]
+    CheckReleaseExists { source: HelmError },
+
+    #[snafu(display("failed to install Helm release"))]
+    InstallRelease { source: InstallReleaseError },
+
+    #[snafu(display("failed to upgrade/install Helm release"))]
+    UpgradeRelease { source: InstallReleaseError },
+
+    #[snafu(display("failed to uninstall Helm release"))]
+    UninstallRelease { source: HelmError },
+
+    #[snafu(display("failed to roll back Helm release"))]
+    RollbackRelease { source: HelmError },
+
+    #[snafu(display("failed to retrieve Helm release history"))]
+    ReleaseHistory { source: HelmError },
+
+    #[snafu(display("failed to retrieve Helm release values"))]
+    ReleaseValues { source: HelmError },
+
+    #[snafu(display("failed to find local Helm chart {chart_name:?} in {path:?}"))]
+    LocalChartNotFound { path: PathBuf, chart_name: String },
+
+    #[snafu(display("failed to template Helm release"))]
+    TemplateRelease { source: HelmError },
+
+    #[snafu(display("failed to retrieve values of Helm chart"))]
+    ChartValues { source: HelmError },
+
+    #[snafu(display("Helm {operation} was cancelled"))]
+    Cancelled { operation: &'static str },
+
+    #[snafu(display("Helm {operation} did not complete within {timeout:?}"))]
+    Timeout {
+        operation: &'static str,
+        timeout: Duration,
+    },
+
+    #[snafu(display("failed to run blocking Helm task"))]
+    BlockingTask { source: JoinError },
+}
+
+impl Error {
+    /// Returns the kind of the underlying Helm error, if this error was caused
+    /// by a failed Helm operation.
+    pub fn helm_error_kind(&self) -> Option<HelmErrorKind> {
+        find_helm_error(self).map(|error| error.kind)
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum InstallReleaseError {
+    /// This error indicates that the Helm release was not found, instead of
+    /// `check_release_exists` returning true.
+    #[snafu(display("failed to find release {name}"))]
+    NoSuchRelease { name: String },
+
+    /// This error indicates that the Helm release is already installed at a
+    /// different version than requested. Installation is skipped. Existing
+    /// releases should be uninstalled with 'stackablectl op un \<NAME\>'.
+    #[snafu(display(
+        "release {name} ({current_version}) already installed, skipping requested version {requested_version}"
+    ))]
+    ReleaseAlreadyInstalled {
+        name: String,
+        current_version: String,
+        requested_version: String,
+    },
+
+    /// This error indicates that there was an Helm error. The kind of the
+    /// error can be inspected using [`HelmError::kind`].
+    #[snafu(display("helm FFI library call failed"))]
+    HelmWrapper { source: HelmError },
+
+    /// This error indicates that upgrading the Helm release failed and the
+    /// subsequent rollback to the previous revision failed as well. The
+    /// release is most likely left in a failed state.
+    #[snafu(display(
+        "upgrade of release {name} failed ({upgrade_error}) and rolling back failed as well"
+    ))]
+    RollbackFailed {
+        name: String,
+        upgrade_error: HelmError,
+        source: HelmError,
+    },
+
+    /// This error indicates that upgrading the Helm release failed and the
+    /// release was rolled back to the previous revision.
+    #[snafu(display("upgrade of release {name} failed ({upgrade_error}) and was rolled back"))]
+    RolledBack { name: String, upgrade_error: String },
+}
+
+#[derive(Debug)]
+pub enum InstallReleaseStatus {
+    /// Indicates that a release is already installed with a different version
+    /// than requested.
+    ReleaseAlreadyInstalledWithVersion {
+        release_name: String,
+        current_version: String,
+        requested_version: String,
+    },
+
+    /// Indicates that a release is already installed, but no specific version
+    /// was requested.
+    ReleaseAlreadyInstalledUnspecified {
+        release_name: String,
+        current_version: String,
+    },
+
+    /// Indicates that the release was installed successfully.
+    Installed(String),
+
+    /// Indicates that an existing release was upgraded in-place.
+    Upgraded {
+        release_name: String,
+        from: String,
+        to: String,
+    },
+
+    /// Indicates that upgrading an existing release failed and the release
+    /// was rolled back to the previous revision.
+    RolledBack {
+        release_name: String,
+        from: String,
+        to: String,
+        error: String,
+    },
+}
+
+impl Display for InstallReleaseStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallReleaseStatus::ReleaseAlreadyInstalledWithVersion {
+                release_name,
+                current_version,
+                requested_version,
+            } => {
+                write!(
+                    f,
+                    "The release {release_name} ({current_version}) is already installed (requested {requested_version}), skipping."
+                )
+            }
+            InstallReleaseStatus::ReleaseAlreadyInstalledUnspecified {
+                release_name,
+                current_version,
+            } => {
+                write!(
+                    f,
+                    "The release {release_name} ({current_version}) is already installed and no specific version was requested, skipping."
+                )
+            }
+            InstallReleaseStatus::Installed(release_name) => {
+                write!(f, "The release {release_name} was successfully installed.")
+            }
+            InstallReleaseStatus::Upgraded {
+                release_name,
+                from,
+                to,
+            } => {
+                write!(
+                    f,
+                    "The release {release_name} was successfully upgraded from {from} to {to}."
+                )
+            }
+            InstallReleaseStatus::RolledBack {
+                release_name,
+                from,
+                to,
+                error,
+            } => {
+                write!(
+                    f,
+                    "The upgrade of release {release_name} from {from} to {to} failed ({error}), rolled back to {from}."
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UninstallReleaseStatus {
+    NotInstalled(String),
+    Uninstalled(String),
+}
+
+impl Display for UninstallReleaseStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UninstallReleaseStatus::NotInstalled(release_name) => {
+                write!(f, "The release {release_name} is not installed, skipping.")
+            }
+            UninstallReleaseStatus::Uninstalled(release_name) => {
+                write!(
+                    f,
+                    "The release {release_name} was successfully uninstalled."
+                )
+            }
+        }
+    }
+}
+
+/// Describes which chart (and version) should be used for a Helm release.
+///
+/// The chart is referenced as `<chart_source>/<chart_name>`. Local charts
+/// resolved by [`resolve_local_chart`] are referenced by path only, in which
+/// case `chart_source` is empty and `chart_name` contains the path.
+pub struct ChartVersion<'a> {
+    pub chart_source: &'a str,
+    pub chart_name: &'a str,
+    pub chart_version: Option<&'a str>,
+}
+
+/// A cheaply cloneable Helm client. All Helm operations are delegated to a
+/// [`HelmBackend`], which defaults to the [`FfiBackend`].
+///
+/// Helm operations are blocking, so they are run on the blocking thread pool
+/// of the Tokio runtime. Each operation is limited by a timeout and can be
+/// cancelled using a [`CancellationToken`]. Operations which modify releases
+/// (install, upgrade, rollback and uninstall) are cancelled in Helm as well
+/// and the client waits for them to stop, so that releases are not left in a
+/// pending state.
+#[derive(Clone, Debug)]
+pub struct Client {
+    backend: Arc<dyn HelmBackend>,
+    cancellation_token: CancellationToken,
+    timeout: Duration,
+
+    /// Digests keyed by chart reference and version, see
+    /// [`Client::with_chart_digest`].
+    chart_digests: Arc<HashMap<(String, String), String>>,
+
+    /// URLs of the Helm repositories keyed by repository name, which are used
+    /// to match charts referenced by repository name against pinned charts.
+    repo_urls: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new(FfiBackend)
+    }
+}
+
+impl Client {
+    /// Creates a new Helm client using the provided `backend`.
+    pub fn new(backend: impl HelmBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            cancellation_token: CancellationToken::new(),
+            timeout: HELM_DEFAULT_TIMEOUT,
+            chart_digests: Arc::default(),
+            repo_urls: Arc::default(),
+        }
+    }
+
+    /// Sets the maximum duration of a single Helm operation. Defaults to
+    /// [`HELM_DEFAULT_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the token used to cancel pending Helm operations, e.g. when the
+    /// user hits Ctrl-C.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Pins the chart referenced by `chart_reference` (see [`chart_reference`],
+    /// e.g. `oci://oci.stackable.tech/sdp-charts/airflow-operator`) in
+    /// `chart_version` to `digest`, e.g. from a lockfile. Installing or
+    /// upgrading a release using this chart fails if the pulled chart has a
+    /// different digest, e.g. because it was re-pushed under the same tag.
+    pub fn with_chart_digest(
+        mut self,
+        chart_reference: impl Into<String>,
+        chart_version: impl Into<String>,
+        digest: impl Into<String>,
+    ) -> Self {
+        Arc::make_mut(&mut self.chart_digests).insert(
+            (chart_reference.into(), chart_version.into()),
+            digest.into(),
+        );
+        self
+    }
+
+    /// Registers the URL of the Helm repository named `repo_name`, which was
+    /// added outside of this client. Repositories added using
+    /// [`Client::add_repo`] are registered automatically.
+    pub fn with_repo_url(self, repo_name: impl Into<String>, repo_url: impl Into<String>) -> Self {
+        self.repo_urls().insert(repo_name.into(), repo_url.into());
+        self
+    }
+
+    fn repo_urls(&self) -> MutexGuard<'_, HashMap<String, String>> {
+        // The map is only accessed briefly and never while panicking
+        self.repo_urls
+            .lock()
+            .expect("Helm repository URLs lock is poisoned")
+    }
+
+    /// Returns the digest the chart with the fully qualified `chart_name` in
+    /// `chart_version` is pinned to, if any. Charts referenced by repository
+    /// name (e.g. `stackable-stable/airflow-operator`) are matched using the
+    /// URL of the repository.
+    fn chart_digest(&self, chart_name: &str, chart_version: &str) -> Option<String> {
+        let chart_reference = match chart_name.split_once('/') {
+            Some((repo_name, name)) => match self.repo_urls().get(repo_name) {
+                Some(repo_url) => chart_reference(repo_url, name),
+                None => chart_name.to_owned(),
+            },
+            None => chart_name.to_owned(),
+        };
+
+        self.chart_digests
+            .get(&(chart_reference, chart_version.to_owned()))
+            .cloned()
+    }
+
+    /// Installs a Helm release from a repo or registry.
+    ///
+    /// This function expects the fully qualified Helm release name. In case of our
+    /// operators this is: `<PRODUCT_NAME>-operator`.
+    #[instrument(skip(self, values_yaml), fields(with_values = values_yaml.is_some(), indicatif.pb_show = true))]
+    pub async fn install_release_from_repo_or_registry(
+        &self,
+        release_name: &str,
+        ChartVersion {
+            chart_source,
+            chart_name,
+            chart_version,
+        }: ChartVersion,
+        values_yaml: Option<&str>,
+        namespace: &str,
+    ) -> Result<InstallReleaseStatus, Error> {
+        debug!("Install Helm release from repo");
+        Span::current().pb_set_message(format!("Installing {chart_name} Helm chart").as_str());
+
+        if self.check_release_exists(release_name, namespace).await? {
+            let release =
+                self.get_release(release_name, namespace)
+                    .await?
+                    .ok_or(Error::InstallRelease {
+                        source: InstallReleaseError::NoSuchRelease {
+                            name: release_name.to_owned(),
+                        },
+                    })?;
+
+            let current_version = release.version;
+
+            match chart_version {
+                Some(chart_version) => {
+                    if chart_version == current_version {
+                        return Ok(InstallReleaseStatus::ReleaseAlreadyInstalledWithVersion {
+                            requested_version: chart_version.to_string(),
+                            release_name: release_name.to_string(),
+                            current_version,
+                        });
+                    } else {
+                        return Err(Error::InstallRelease {
+                            source: InstallReleaseError::ReleaseAlreadyInstalled {
+                                requested_version: chart_version.into(),
+                                name: release_name.into(),
+                                current_version,
+                            },
+                        });
+                    }
+                }
+                None => {
+                    return Ok(InstallReleaseStatus::ReleaseAlreadyInstalledUnspecified {
+                        release_name: release_name.to_string(),
+                        current_version,
+                    });
+                }
+            }
+        }
+
+        let full_chart_name = full_chart_name(chart_source, chart_name);
+        let chart_version = chart_version.unwrap_or(HELM_DEFAULT_CHART_VERSION);
+
+        debug!(
+            release_name,
+            chart_version, full_chart_name, "Installing Helm release"
+        );
+
+        self.install_release(
+            release_name,
+            &full_chart_name,
+            chart_version,
+            values_yaml,
+            namespace,
+        )
+        .await?;
+
+        Ok(InstallReleaseStatus::Installed(release_name.to_string()))
+    }
+
+    /// Upgrades a Helm release from a repo or registry. If the release is not
+    /// installed yet, it is installed instead.
+    ///
+    /// Existing releases are upgraded in-place. If the upgrade fails, the release
+    /// is rolled back to the previous revision and
+    /// [`InstallReleaseStatus::RolledBack`] is returned. Releases which are
+    /// already installed with the requested version and values are left
+    /// untouched, except for local charts, whose content can change without a
+    /// version bump. If `force` is set, the release is always upgraded and
+    /// conflicting resources are replaced (deleted and re-created) instead of
+    /// patched.
+    ///
+    /// This function expects the fully qualified Helm release name. In case of our
+    /// operators this is: `<PRODUCT_NAME>-operator`.
+    #[instrument(skip(self, values_yaml), fields(with_values = values_yaml.is_some(), indicatif.pb_show = true))]
+    pub async fn upgrade_or_install_release_from_repo_or_registry(
+        &self,
+        release_name: &str,
+        ChartVersion {
+            chart_source,
+            chart_name,
+            chart_version,
+        }: ChartVersion,
+        values_yaml: Option<&str>,
+        namespace: &str,
+        force: bool,
+    ) -> Result<InstallReleaseStatus, Error> {
+        debug!("Install/Upgrade Helm release from repo");
+        Span::current()
+            .pb_set_message(format!("Installing/Upgrading {chart_name} Helm chart").as_str());
+
+        let full_chart_name = full_chart_name(chart_source, chart_name);
+
+        if self.check_release_exists(release_name, namespace).await? {
+            let release =
+                self.get_release(release_name, namespace)
+                    .await?
+                    .ok_or(Error::UpgradeRelease {
+                        source: InstallReleaseError::NoSuchRelease {
+                            name: release_name.to_owned(),
+                        },
+                    })?;
+
+            let current_version = release.version;
+
+            let chart_version = match chart_version {
+                Some(chart_version) => {
+                    // Local charts are referenced by path, see ChartVersion
+                    let is_local_chart = chart_source.is_empty();
+
+                    if chart_version == current_version
+                        && !is_local_chart
+                        && !force
+                        && !self
+                            .release_values_changed(release_name, namespace, values_yaml)
+                            .await?
+                    {
+                        return Ok(InstallReleaseStatus::ReleaseAlreadyInstalledWithVersion {
+                            requested_version: chart_version.to_string(),
+                            release_name: release_name.to_string(),
+                            current_version,
+                        });
+                    }
+
+                    chart_version
+                }
+                None => {
+                    return Ok(InstallReleaseStatus::ReleaseAlreadyInstalledUnspecified {
+                        release_name: release_name.to_string(),
+                        current_version,
+                    });
+                }
+            };
+
+            debug!(
+                release_name,
+                current_version, chart_version, full_chart_name, force, "Upgrading Helm release"
+            );
+
+            let request = ReleaseRequest {
+                release_name: release_name.to_owned(),
+                chart_digest: self.chart_digest(&full_chart_name, chart_version),
+                chart_name: full_chart_name,
+                chart_version: chart_version.to_owned(),
+                values_yaml: values_yaml.unwrap_or_default().to_owned(),
+                namespace: namespace.to_owned(),
+            };
+
+            // No further operations must be started once the client was
+            // cancelled, so a timed out or cancelled upgrade is not rolled back
+            // and the error is returned right away.
+            let upgrade_result = self
+                .run_operation("upgrade", move |backend, operation| {
+                    backend.upgrade_release(&request, force, operation)
+                })
+                .await?;
+
+            if let Err(upgrade_error) = upgrade_result {
+                warn!(
+                    release_name,
+                    current_version, "Upgrading Helm release failed, rolling back"
+                );
+
+                let (name, ns) = (release_name.to_owned(), namespace.to_owned());
+                let rollback_result = self
+                    .run_operation("rollback", move |backend, operation| {
+                        backend.rollback_release(&name, &ns, None, operation)
+                    })
+                    .await?;
+
+                if let Err(rollback_error) = rollback_result {
+                    return Err(Error::UpgradeRelease {
+                        source: InstallReleaseError::RollbackFailed {
+                            name: release_name.to_owned(),
+                            source: rollback_error,
+                            upgrade_error,
+                        },
+                    });
+                }
+
+                return Ok(InstallReleaseStatus::RolledBack {
+                    release_name: release_name.to_owned(),
+                    from: current_version,
+                    to: chart_version.to_owned(),
+                    error: upgrade_error.to_string(),
+                });
+            }
+
+            return Ok(InstallReleaseStatus::Upgraded {
+                release_name: release_name.to_owned(),
+                from: current_version,
+                to: chart_version.to_owned(),
+            });
+        }
+
+        let chart_version = chart_version.unwrap_or(HELM_DEFAULT_CHART_VERSION);
+
+        debug!(
+            release_name,
+            chart_version, full_chart_name, "Installing Helm release"
+        );
+
+        self.install_release(
+            release_name,
+            &full_chart_name,
+            chart_version,
+            values_yaml,
+            namespace,
+        )
+        .await?;
+
+        Ok(InstallReleaseStatus::Installed(release_name.to_string()))
+    }
+
+    /// Installs a Helm release.
+    ///
+    /// This function expects the fully qualified Helm release name. In case of our
+    /// operators this is: `<PRODUCT_NAME>-operator`.
+    #[instrument(skip(self), fields(with_values = values_yaml.is_some()))]
+    async fn install_release(
+        &self,
+        release_name: &str,
+        chart_name: &str,
+        chart_version: &str,
+        values_yaml: Option<&str>,
+        namespace: &str,
+    ) -> Result<(), Error> {
+        let request = ReleaseRequest {
+            release_name: release_name.to_owned(),
+            chart_name: chart_name.to_owned(),
+            chart_version: chart_version.to_owned(),
+            values_yaml: values_yaml.unwrap_or_default().to_owned(),
+            namespace: namespace.to_owned(),
+            chart_digest: self.chart_digest(chart_name, chart_version),
+        };
+
+        self.run_operation("install", move |backend, operation| {
+            backend.install_release(&request, operation)
+        })
+        .await?
+        .map_err(|error| Error::InstallRelease {
+            source: InstallReleaseError::HelmWrapper { source: error },
+        })
+    }
+
+    /// Rolls back a Helm release to the provided `revision`. If no revision is
+    /// provided, the release is rolled back to the previous revision.
+    ///
+    /// This function expects the fully qualified Helm release name. In case of our
+    /// operators this is: `<PRODUCT_NAME>-operator`.
+    #[instrument(skip(self))]
+    pub async fn rollback_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        revision: Option<u32>,
+    ) -> Result<(), Error> {
+        debug!("Roll back Helm release");
+
+        let (release_name, namespace) = (release_name.to_owned(), namespace.to_owned());
+        self.run_operation("rollback", move |backend, operation| {
+            backend.rollback_release(&release_name, &namespace, revision, operation)
+        })
+        .await?
+        .context(RollbackReleaseSnafu)
+    }
+
+    /// Returns the revision history of a Helm release, oldest revision first.
+    #[instrument(skip(self))]
+    pub async fn release_history(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<Vec<ReleaseRevision>, Error> {
+        debug!("Get Helm release history");
+
+        let (release_name, namespace) = (release_name.to_owned(), namespace.to_owned());
+        self.run("history", move |backend| {
+            backend.release_history(&release_name, &namespace)
+        })
+        .await?
+        .context(ReleaseHistorySnafu)
+    }
+
+    /// Returns the user-supplied values of the deployed revision of a Helm
+    /// release.
+    #[instrument(skip(self))]
+    pub async fn release_values(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<Mapping, Error> {
+        debug!("Get Helm release values");
+
+        let (release_name, namespace) = (release_name.to_owned(), namespace.to_owned());
+        self.run("get values", move |backend| {
+            backend.release_values(&release_name, &namespace)
+        })
+        .await?
+        .context(ReleaseValuesSnafu)
+    }
+
+    /// Returns if `values_yaml` differs from the user-supplied values of the
+    /// deployed revision of a Helm release. Values which can't be parsed are
+    /// considered changed, so that Helm reports the error during the upgrade.
+    async fn release_values_changed(
+        &self,
+        release_name: &str,
+        namespace: &str,
+        values_yaml: Option<&str>,
+    ) -> Result<bool, Error> {
+        let values = match values_yaml.map(str::trim).unwrap_or_default() {
+            "" => Mapping::new(),
+            values_yaml => match serde_yaml::from_str(values_yaml) {
+                Ok(values) => values,
+                Err(_) => return Ok(true),
+            },
+        };
+
+        let current_values = self.release_values(release_name, namespace).await?;
+        Ok(values != current_values)
+    }
+
+    /// Renders a Helm release from a repo or registry without installing it.
+    ///
+    /// The returned string contains all Kubernetes manifests (including CRDs) the
+    /// chart would create, as a multi-document YAML string. Charts from repos
+    /// require the repo to be added via [`Client::add_repo`] beforehand.
+    #[instrument(skip(self, values_yaml), fields(with_values = values_yaml.is_some()))]
+    pub async fn template_release(
+        &self,
+        release_name: &str,
+        ChartVersion {
+            chart_source,
+            chart_name,
+            chart_version,
+        }: ChartVersion,
+        values_yaml: Option<&str>,
+        namespace: &str,
+    ) -> Result<String, Error> {
+        debug!("Template Helm release");
+
+        let request = ReleaseRequest {
+            release_name: release_name.to_owned(),
+            chart_name: full_chart_name(chart_source, chart_name),
+            chart_version: chart_version
+                .unwrap_or(HELM_DEFAULT_CHART_VERSION)
+                .to_owned(),
+            values_yaml: values_yaml.unwrap_or_default().to_owned(),
+            namespace: namespace.to_owned(),
+            // Rendering doesn't install anything, so the chart isn't pinned
+            chart_digest: None,
+        };
+
+        self.run("template", move |backend| {
+            backend.template_release(&request)
+        })
+        .await?
+        .context(TemplateReleaseSnafu)
+    }
+
+    /// Returns the default values and the values schema of a chart from a repo
+    /// or registry. Charts from repos require the repo to be added via
+    /// [`Client::add_repo`] beforehand.
+    #[instrument(skip(self))]
+    pub async fn chart_values(
+        &self,
+        ChartVersion {
+            chart_source,
+            chart_name,
+            chart_version,
+        }: ChartVersion<'_>,
+    ) -> Result<ChartValues, Error> {
+        debug!("Get Helm chart values");
+
+        let chart_name = full_chart_name(chart_source, chart_name);
+        let chart_version = chart_version
+            .unwrap_or(HELM_DEFAULT_CHART_VERSION)
+            .to_owned();
+
+        self.run("show values", move |backend| {
+            backend.chart_values(&chart_name, &chart_version)
+        })
+        .await?
+        .context(ChartValuesSnafu)
+    }
+
+    /// Uninstall a Helm release.
+    ///
+    /// This function expects the fully qualified Helm release name. In case of our
+    /// operators this is: `<PRODUCT_NAME>-operator`.
+    #[instrument(skip(self), fields(indicatif.pb_show = true))]
+    pub async fn uninstall_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<UninstallReleaseStatus, Error> {
+        debug!("Uninstall Helm release");
+        Span::current().pb_set_message(format!("Uninstalling {release_name}-operator").as_str());
+
+        if self.check_release_exists(release_name, namespace).await? {
+            let (name, ns) = (release_name.to_owned(), namespace.to_owned());
+            self.run_operation("uninstall", move |backend, operation| {
+                backend.uninstall_release(&name, &ns, operation)
+            })
+            .await?
+            .context(UninstallReleaseSnafu)?;
+
+            return Ok(UninstallReleaseStatus::Uninstalled(
+                release_name.to_string(),
+            ));
+        }
+
+        info!("The Helm release {release_name} is not installed, skipping.");
+
+        Ok(UninstallReleaseStatus::NotInstalled(
+            release_name.to_string(),
+        ))
+    }
+
+    /// Returns if a Helm release exists
+    #[instrument(skip(self))]
+    pub async fn check_release_exists(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<bool, Error> {
+        debug!("Check if Helm release exists");
+
+        let (release_name, namespace) = (release_name.to_owned(), namespace.to_owned());
+        self.run("status", move |backend| {
+            backend.release_exists(&release_name, &namespace)
+        })
+        .await?
+        .context(CheckReleaseExistsSnafu)
+    }
+
+    /// Returns a list of Helm releases
+    #[instrument(skip(self))]
+    pub async fn list_releases(&self, namespace: &str) -> Result<Vec<Release>, Error> {
+        debug!("List Helm releases");
+
+        let namespace = namespace.to_owned();
+        self.run("list", move |backend| backend.list_releases(&namespace))
+            .await?
+            .context(ListReleasesSnafu)
+    }
+
+    /// Returns a single Helm release by `release_name`.
+    #[instrument(skip(self))]
+    pub async fn get_release(
+        &self,
+        release_name: &str,
+        namespace: &str,
+    ) -> Result<Option<Release>, Error> {
+        debug!("Get Helm release");
+
+        Ok(self
+            .list_releases(namespace)
+            .await?
+            .into_iter()
+            .find(|r| r.name == release_name))
+    }
+
+    /// Adds a Helm repo with `repo_name` and `repo_url`, which is accessed
+    /// using `auth`.
+    #[instrument(skip(self))]
+    pub async fn add_repo(
+        &self,
+        repository_name: &str,
+        repository_url: &str,
+        auth: &RepositoryAuth,
+    ) -> Result<(), Error> {
+        debug!("Add Helm repo");
+
+        let (name, url, auth) = (
+            repository_name.to_owned(),
+            repository_url.to_owned(),
+            auth.clone(),
+        );
+        self.run("repo add", move |backend| {
+            backend.add_repo(&name, &url, &auth)
+        })
+        .await?
+        .context(AddRepoSnafu)?;
+
+        self.repo_urls()
+            .insert(repository_name.to_owned(), repository_url.to_owned());
+
+        Ok(())
+    }
+
+    /// Configures access to the OCI registry at `host` (e.g.
+    /// `oci.stackable.tech`) using `auth`, which is used for all charts pulled
+    /// from this registry afterwards. If `plain_http` is set, the registry is
+    /// accessed using plain HTTP instead of HTTPS.
+    #[instrument(skip(self))]
+    pub async fn configure_registry(
+        &self,
+        host: &str,
+        auth: &RepositoryAuth,
+        plain_http: bool,
+    ) -> Result<(), Error> {
+        debug!("Configure Helm registry");
+
+        let (host, auth) = (host.to_owned(), auth.clone());
+        self.run("registry configure", move |backend| {
+            backend.configure_registry(&host, &auth, plain_http)
+        })
+        .await?
+        .context(ConfigureRegistrySnafu)
+    }
+
+    /// Runs the blocking Helm `operation` on the blocking thread pool.
+    ///
+    /// The outer result contains errors caused by running the operation, i.e.
+    /// timeouts and cancellation, the inner result contains the error returned
+    /// by Helm itself.
+    ///
+    /// This is only used for operations which don't modify releases, which is
+    /// why a timed out or cancelled operation is left running in the
+    /// background. Use [`Client::run_operation`] for all other operations.
+    async fn run<T, F>(&self, operation: &'static str, f: F) -> Result<Result<T, HelmError>, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn HelmBackend) -> Result<T, HelmError> + Send + 'static,
+    {
+        // Don't start new operations if the client was already cancelled
+        if self.cancellation_token.is_cancelled() {
+            return CancelledSnafu { operation }.fail();
+        }
+
+        let backend = self.backend.clone();
+        let task = spawn_blocking(move || f(backend.as_ref()));
+
+        tokio::select! {
+            _ = self.cancellation_token.cancelled() => CancelledSnafu { operation }.fail(),
+            result = tokio::time::timeout(self.timeout, task) => match result {
+                Ok(result) => result.context(BlockingTaskSnafu),
+                Err(_) => TimeoutSnafu {
+                    operation,
+                    timeout: self.timeout,
+                }
+                .fail(),
+            },
+        }
+    }
+
+    /// Runs the blocking Helm `operation`, which modifies a release, on the
+    /// blocking thread pool. See [`Client::run`] for the returned results.
+    ///
+    /// Helm aborts the operation once the timeout expires. If the client is
+    /// cancelled, the operation is cancelled in Helm as well. In both cases,
+    /// this waits for the operation to stop, so that the release is not left
+    /// in a pending state, e.g. because the process exits afterwards.
+    async fn run_operation<T, F>(
+        &self,
+        operation: &'static str,
+        f: F,
+    ) -> Result<Result<T, HelmError>, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn HelmBackend, &HelmOperation) -> Result<T, HelmError> + Send + 'static,
+    {
+        // Don't start new operations if the client was already cancelled
+        if self.cancellation_token.is_cancelled() {
+            return CancelledSnafu { operation }.fail();
+        }
+
+        let backend = self.backend.clone();
+        let helm_operation = HelmOperation::new(self.timeout);
+        let mut task = spawn_blocking({
+            let backend = backend.clone();
+            move || f(backend.as_ref(), &helm_operation)
+        });
+
+        // Helm enforces the timeout itself, the timeout here only guards
+        // against operations which don't stop in time
+        let result = tokio::select! {
+            _ = self.cancellation_token.cancelled() => CancelledSnafu { operation }.fail(),
+            result = tokio::time::timeout(self.timeout, &mut task) => match result {
+                Ok(result) => return result.context(BlockingTaskSnafu),
+                Err(_) => TimeoutSnafu {
+                    operation,
+                    timeout: self.timeout,
+                }
+                .fail(),
+            },
+        };
+
+        debug!(
+            operation,
+            "Cancelling Helm operation and waiting for it to stop"
+        );
+        backend.cancel_operation(helm_operation.id);
+
+        // The result of the cancelled operation doesn't matter anymore
+        let _ = task.await;
+
+        result
+    }
+}
+
+/// Resolves a chart from a local chart source. The `source` path is checked in
+/// the following order:
+///
+/// - It points to a packaged chart (`.tgz`).
+/// - It points to a chart directory (containing a `Chart.yaml` file).
+/// - It contains a chart directory named `chart_name`.
+/// - It contains a packaged chart named `<chart_name>-<chart_version>.tgz`.
+pub fn resolve_local_chart(
+    source: &Path,
+    chart_name: &str,
+    chart_version: Option<&str>,
+) -> Result<PathBuf, Error> {
+    if source.is_file() && source.extension().is_some_and(|ext| ext == "tgz") {
+        return Ok(source.to_path_buf());
+    }
+
+    if source.join(HELM_CHART_FILE).is_file() {
+        return Ok(source.to_path_buf());
+    }
+
+    let chart_dir = source.join(chart_name);
+    if chart_dir.join(HELM_CHART_FILE).is_file() {
+        return Ok(chart_dir);
+    }
+
+    if let Some(chart_version) = chart_version {
+        let archive = source.join(format!("{chart_name}-{chart_version}.tgz"));
+        if archive.is_file() {
+            return Ok(archive);
+        }
+    }
+
+    LocalChartNotFoundSnafu {
+        path: source,
+        chart_name,
+    }
+    .fail()
+}
+
+/// Returns the fully qualified reference of the chart `chart_name` in the
+/// repository or registry at `repo_url`, e.g.
+/// `oci://oci.stackable.tech/sdp-charts/airflow-operator`. Pinned charts are
+/// keyed by this reference, see [`Client::with_chart_digest`].
+pub fn chart_reference(repo_url: &str, chart_name: &str) -> String {
+    format!(
+        "{repo_url}/{chart_name}",
+        repo_url = repo_url.trim_end_matches('/')
+    )
+}
+
+/// Returns the fully qualified chart name passed to Helm. Local charts are
+/// referenced by their path only.
+fn full_chart_name(chart_source: &str, chart_name: &str) -> String {
+    if chart_source.is_empty() {
+        chart_name.to_owned()
+    } else {
+        format!("{chart_source}/{chart_name}")
+    }
+}
+
+/// Returns the first Helm error in the chain of error sources, if any. This can
+/// be used to react to specific [`HelmErrorKind`]s, e.g. to print targeted
+/// hints.
+pub fn find_helm_error<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a HelmError> {
+    let mut current = Some(error);
+
+    while let Some(error) = current {
+        if let Some(helm_error) = error.downcast_ref::<HelmError>() {
+            return Some(helm_error);
+        }
+
+        current = error.source();
+    }
+
+    None
+}
+
+/// Retrieves the Helm index file from the repository URL, which is accessed
+/// using `auth`.
+#[instrument(skip_all, fields(%repo_url))]
+pub async fn get_helm_index<T>(
+    repo_url: T,
+    auth: &RepositoryAuth,
+) -> Result<ChartSourceMetadata, Error>
+where
+    T: AsRef<str> + std::fmt::Display + std::fmt::Debug,
+{
+    let index_file_content = get_helm_index_content(repo_url.as_ref(), auth).await?;
+    serde_yaml::from_str(&index_file_content).context(DeserializeYamlSnafu)
+}
+
+/// Returns the URL of the Helm index file of the repository at `repo_url`.
+pub fn helm_index_url(repo_url: &str) -> Result<Url, Error> {
+    let url = Url::parse(repo_url).context(UrlParseSnafu)?;
+    url.join(HELM_REPO_INDEX_FILE).context(UrlParseSnafu)
+}
+
+/// Retrieves the raw content of the Helm index file from the repository URL,
+/// which is accessed using `auth`.
+pub async fn get_helm_index_content(
+    repo_url: &str,
+    auth: &RepositoryAuth,
+) -> Result<String, Error> {
+    debug!("Get Helm repo index file");
+
+    let url = helm_index_url(repo_url)?;
+
+    debug!("Using {url} to retrieve Helm index file");
+
+    // TODO (Techassi): Use the FileTransferClient for that
+    let client = auth.http_client().context(RepositoryAuthSnafu)?;
+    let index_file_content = auth
+        .authenticate(client.get(url))
+        .send()
+        .await
+        .context(FetchRemoteContentSnafu)?
+        // Make sure authentication failures are reported as such
+        .error_for_status()
+        .context(FetchRemoteContentSnafu)?
+        .text()
+        .await
+        .context(FetchRemoteContentSnafu)?;
+
+    Ok(index_file_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[rstest]
+    #[case("oci://oci.stackable.tech/sdp-charts", ChartSourceKind::Oci)]
+    #[case(
+        "https://repo.stackable.tech/repository/helm-stable",
+        ChartSourceKind::Repo
+    )]
+    #[case("http://example.com/charts", ChartSourceKind::Repo)]
+    #[case("./charts/my-chart", ChartSourceKind::Local)]
+    #[case("/absolute/path/to/chart", ChartSourceKind::Local)]
+    fn source_kind(#[case] url: &str, #[case] expected: ChartSourceKind) {
+        let repo = ChartRepo {
+            name: "test".to_owned(),
+            url: url.to_owned(),
+        };
+        assert_eq!(repo.source_kind(), expected);
+    }
+
+    #[test]
+    fn resolve_local_chart_in_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path();
+        let chart_dir = source.join("airflow-operator");
+
+        std::fs::create_dir_all(&chart_dir).unwrap();
+        std::fs::write(chart_dir.join(HELM_CHART_FILE), "name: airflow-operator").unwrap();
+        std::fs::write(source.join("zookeeper-operator-25.3.0.tgz"), "").unwrap();
+
+        assert_eq!(
+            resolve_local_chart(source, "airflow-operator", None).unwrap(),
+            chart_dir
+        );
+        assert_eq!(
+            resolve_local_chart(&chart_dir, "ignored", None).unwrap(),
+            chart_dir
+        );
+        assert_eq!(
+            resolve_local_chart(source, "zookeeper-operator", Some("25.3.0")).unwrap(),
+            source.join("zookeeper-operator-25.3.0.tgz")
+        );
+        assert!(resolve_local_chart(source, "zookeeper-operator", None).is_err());
+    }
+
+    #[test]
+    fn chart_digest_of_pinned_chart() {
+        let client = Client::new(FakeBackend::default())
+            .with_repo_url(
+                "stackable-stable",
+                "https://repo.stackable.tech/repository/helm-stable/",
+            )
+            .with_chart_digest(
+                "oci://oci.stackable.tech/sdp-charts/airflow-operator",
+                "25.11.0",
+                "sha256:aaaa",
+            )
+            .with_chart_digest(
+                "https://repo.stackable.tech/repository/helm-stable/airflow-operator",
+                "25.11.0",
+                "sha256:bbbb",
+            );
+
+        assert_eq!(
+            client
+                .chart_digest(
+                    "oci://oci.stackable.tech/sdp-charts/airflow-operator",
+                    "25.11.0"
+                )
+                .as_deref(),
+            Some("sha256:aaaa")
+        );
+        assert_eq!(
+            client
+                .chart_digest("stackable-stable/airflow-operator", "25.11.0")
+                .as_deref(),
+            Some("sha256:bbbb")
+        );
+        assert_eq!(
+            client.chart_digest("stackable-stable/airflow-operator", "25.7.0"),
+            None
+        );
+
+        // Charts with the same name from other sources are not pinned
+        assert_eq!(
+            client.chart_digest("oci://ghcr.io/acme/airflow-operator", "25.11.0"),
+            None
+        );
+        assert_eq!(
+            client.chart_digest("acme/airflow-operator", "25.11.0"),
+            None
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn upgrade_or_install_release_upgrades_in_place() {
+        let backend = FakeBackend::default().with_release("default", "trino", "1.0.0");
+        let client = Client::new(backend.clone());
+
+        let status = client
+            .upgrade_or_install_release_from_repo_or_registry(
+                "trino",
+                ChartVersion {
+                    chart_source: "stackable",
+                    chart_name: "trino",
+                    chart_version: Some("2.0.0"),
+                },
+                None,
+                "default",
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(status, InstallReleaseStatus::Upgraded { .. }));
+        assert_eq!(
+            backend.release_version("default", "trino").as_deref(),
+            Some("2.0.0")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn upgrade_or_install_release_forwards_force() {
+        let backend = FakeBackend::default().with_release("default", "trino", "1.0.0");
+        let client = Client::new(backend.clone());
+
+        client
+            .upgrade_or_install_release_from_repo_or_registry(
+                "trino",
+                ChartVersion {
+                    chart_source: "stackable",
+                    chart_name: "trino",
+                    chart_version: Some("2.0.0"),
+                },
+                None,
+                "default",
+                true,
+            )
+            .await
+            .unwrap();
+
+        assert!(backend.calls().contains(&FakeCall::UpgradeRelease {
+            release_name: "trino".to_owned(),
+            chart_name: "stackable/trino".to_owned(),
+            chart_version: "2.0.0".to_owned(),
+            namespace: "default".to_owned(),
+            force: true,
+        }));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn upgrade_or_install_release_skips_same_version_and_values() {
+        let backend = FakeBackend::default().with_release("default", "trino", "1.0.0");
+        let client = Client::new(backend.clone());
+
+        let status = client
+            .upgrade_or_install_release_from_repo_or_registry(
+                "trino",
+                ChartVersion {
+                    chart_source: "stackable",
+                    chart_name: "trino",
+                    chart_version: Some("1.0.0"),
+                },
+                None,
+                "default",
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            status,
+            InstallReleaseStatus::ReleaseAlreadyInstalledWithVersion { .. }
+        ));
+        assert!(
+            !backend
+                .calls()
+                .iter()
+                .any(|call| matches!(call, FakeCall::UpgradeRelease { .. }))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn upgrade_or_install_release_upgrades_same_version_with_new_values() {
+        let backend = FakeBackend::default().with_release("default", "trino", "1.0.0");
+        let client = Client::new(backend.clone());
+
+        let status = client
+            .upgrade_or_install_release_from_repo_or_registry(
+                "trino",
+                ChartVersion {
+                    chart_source: "stackable",
+                    chart_name: "trino",
+                    chart_version: Some("1.0.0"),
+                },
+                Some("replicas: 2"),
+                "default",
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(status, InstallReleaseStatus::Upgraded { .. }));
+        assert!(backend.calls().contains(&FakeCall::UpgradeRelease {
+            release_name: "trino".to_owned(),
+            chart_name: "stackable/trino".to_owned(),
+            chart_version: "1.0.0".to_owned(),
+            namespace: "default".to_owned(),
+            force: false,
+        }));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn upgrade_or_install_release_upgrades_local_chart_with_same_version() {
+        let backend = FakeBackend::default().with_release("default", "trino", "1.0.0");
+        let client = Client::new(backend.clone());
+
+        let status = client
+            .upgrade_or_install_release_from_repo_or_registry(
+                "trino",
+                ChartVersion {
+                    chart_source: "",
+                    chart_name: "charts/trino-1.0.0.tgz",
+                    chart_version: Some("1.0.0"),
+                },
+                None,
+                "default",
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(status, InstallReleaseStatus::Upgraded { .. }));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn upgrade_or_install_release_rolls_back_failed_upgrade() {
+        let backend = FakeBackend::default().with_release("default", "trino", "1.0.0");
+        let client = Client::new(backend.clone());
+
+        backend.fail_next(
+            FakeOperation::UpgradeRelease,
+            fake_error(HelmErrorKind::Timeout, "timed out waiting"),
+        );
+
+        let status = client
+            .upgrade_or_install_release_from_repo_or_registry(
+                "trino",
+                ChartVersion {
+                    chart_source: "stackable",
+                    chart_name: "trino",
+                    chart_version: Some("2.0.0"),
+                },
+                None,
+                "default",
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(status, InstallReleaseStatus::RolledBack { .. }));
+        assert_eq!(
+            backend.release_version("default", "trino").as_deref(),
+            Some("1.0.0")
+        );
+        assert!(backend.calls().contains(&FakeCall::RollbackRelease {
+            release_name: "trino".to_owned(),
+            namespace: "default".to_owned(),
+            revision: None,
+        }));
+    }
+
+    #[tokio::test]
+    async fn cancelled_client_does_not_run_operations() {
+        let backend = FakeBackend::default();
+        let cancellation_token = CancellationToken::new();
+        let client =
+            Client::new(backend.clone()).with_cancellation_token(cancellation_token.clone());
+
+        cancellation_token.cancel();
+
+        let error = client.list_releases("default").await.unwrap_err();
+        assert!(matches!(error, Error::Cancelled { .. }));
+        assert!(backend.calls().is_empty());
+    }
+}
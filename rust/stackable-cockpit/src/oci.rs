@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use reqwest::{
+    Response, StatusCode,
+    header::{ACCEPT, LINK, WWW_AUTHENTICATE},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use snafu::{OptionExt, ResultExt, Snafu};
 use tracing::{debug, instrument};
 use url::Url;
@@ -8,9 +13,11 @@ use urlencoding::encode;
 
 use crate::{
     constants::{
-        HELM_OCI_BASE, HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST,
-        OCI_INDEX_PAGE_SIZE,
+        HELM_OCI_BASE, HELM_OCI_PROJECT, HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE,
+        HELM_REPO_NAME_TEST, OCI_CATALOG_PAGE_SIZE, OCI_INDEX_PAGE_SIZE, OCI_TAGS_PAGE_SIZE,
     },
+    helm::{AuthError, RepositoryAuth},
+    platform::operator::VALID_OPERATORS,
     utils::chartsource::{ChartSourceEntry, ChartSourceMetadata},
 };
 
@@ -36,6 +43,176 @@ pub enum Error {
 
     #[snafu(display("failed to parse URL"))]
     UrlParse { source: url::ParseError },
+
+    #[snafu(display("failed to set up registry authentication"))]
+    RegistryAuth { source: AuthError },
+
+    #[snafu(display("cannot get tags of OCI repository {repository:?}"))]
+    GetTags {
+        source: reqwest::Error,
+        repository: String,
+    },
+
+    #[snafu(display("cannot parse tags of OCI repository {repository:?}"))]
+    ParseTags {
+        source: reqwest::Error,
+        repository: String,
+    },
+
+    #[snafu(display("cannot get registry token from {realm:?}"))]
+    GetToken {
+        source: reqwest::Error,
+        realm: String,
+    },
+
+    #[snafu(display("cannot parse registry token received from {realm:?}"))]
+    ParseToken {
+        source: reqwest::Error,
+        realm: String,
+    },
+
+    #[snafu(display("invalid registry token challenge {challenge:?}"))]
+    InvalidTokenChallenge { challenge: String },
+
+    #[snafu(display("cannot get manifest of {reference:?}"))]
+    GetManifest {
+        source: reqwest::Error,
+        reference: String,
+    },
+
+    #[snafu(display("cannot parse repository catalog"))]
+    ParseCatalog { source: serde_json::Error },
+
+    #[snafu(display("invalid OCI registry reference {reference:?}, expected <HOST>/<PROJECT>"))]
+    InvalidRegistryReference { reference: String },
+}
+
+/// The API used to list the charts and their versions in an OCI registry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistryApi {
+    /// Harbor-specific repository and artifact API, which is used by the
+    /// Stackable registry.
+    #[default]
+    Harbor,
+
+    /// Registry-agnostic OCI distribution API, which is supported by all
+    /// registries, e.g. `registry:2`, Zot, GHCR or Artifactory.
+    Distribution,
+}
+
+/// An OCI registry which hosts the operator charts below a project (or
+/// namespace), e.g. `oci.stackable.tech/sdp-charts`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OciRegistry {
+    /// Host (and optional port) of the registry.
+    pub host: String,
+
+    /// Project (or namespace) the operator charts are located in.
+    pub project: String,
+
+    pub api: RegistryApi,
+
+    /// Use plain HTTP instead of HTTPS, e.g. for local registries.
+    pub plain_http: bool,
+}
+
+impl Default for OciRegistry {
+    fn default() -> Self {
+        Self {
+            host: HELM_OCI_BASE.to_owned(),
+            project: HELM_OCI_PROJECT.to_owned(),
+            api: RegistryApi::Harbor,
+            plain_http: false,
+        }
+    }
+}
+
+impl OciRegistry {
+    /// Parses a registry `reference` in the form `[oci://]<HOST>/<PROJECT>`.
+    /// If no `api` is provided, the Harbor API is used for the Stackable
+    /// registry and the OCI distribution API for every other registry.
+    pub fn parse(reference: &str, api: Option<RegistryApi>) -> Result<Self, Error> {
+        let (host, project) = reference
+            .trim_start_matches("oci://")
+            .trim_end_matches('/')
+            .split_once('/')
+            .filter(|(host, project)| !host.is_empty() && !project.is_empty())
+            .context(InvalidRegistryReferenceSnafu { reference })?;
+
+        let api = api.unwrap_or(if host == HELM_OCI_BASE {
+            RegistryApi::Harbor
+        } else {
+            RegistryApi::Distribution
+        });
+
+        Ok(Self {
+            host: host.to_owned(),
+            project: project.to_owned(),
+            api,
+            plain_http: false,
+        })
+    }
+
+    /// Returns the chart repository used by Helm, e.g.
+    /// `oci://oci.stackable.tech/sdp-charts`.
+    pub fn chart_repository(&self) -> String {
+        format!(
+            "oci://{host}/{project}",
+            host = self.host,
+            project = self.project
+        )
+    }
+
+    fn base_url(&self) -> String {
+        let scheme = if self.plain_http { "http" } else { "https" };
+        format!("{scheme}://{host}", host = self.host)
+    }
+
+    /// Returns the URL listing the repositories of the registry. The Harbor
+    /// API only lists the repositories of the project, the OCI distribution
+    /// catalog lists all repositories of the registry.
+    pub fn catalog_url(&self) -> Result<Url, Error> {
+        let url = match self.api {
+            RegistryApi::Harbor => format!(
+                "{base_url}/api/v2.0/repositories?page_size=100&q=name=~{project}/",
+                base_url = self.base_url(),
+                project = self.project
+            ),
+            RegistryApi::Distribution => format!(
+                "{base_url}/v2/_catalog?n={OCI_CATALOG_PAGE_SIZE}",
+                base_url = self.base_url()
+            ),
+        };
+
+        Url::parse(&url).context(UrlParseSnafu)
+    }
+
+    /// Returns the names of the operators with a chart in the project of this
+    /// registry, based on the `catalog` retrieved from [`Self::catalog_url`].
+    pub fn operators_from_catalog(&self, catalog: &str) -> Result<Vec<String>, Error> {
+        let repositories: Vec<String> = match self.api {
+            RegistryApi::Harbor => serde_json::from_str::<Vec<OciRepository>>(catalog)
+                .context(ParseCatalogSnafu)?
+                .into_iter()
+                .map(|repository| repository.name)
+                .collect(),
+            RegistryApi::Distribution => {
+                serde_json::from_str::<Catalog>(catalog)
+                    .context(ParseCatalogSnafu)?
+                    .repositories
+            }
+        };
+
+        let prefix = format!("{project}/", project = self.project);
+
+        Ok(repositories
+            .iter()
+            .filter_map(|repository| repository.strip_prefix(&prefix))
+            .filter_map(|chart_name| chart_name.strip_suffix("-operator"))
+            .map(ToOwned::to_owned)
+            .collect())
+    }
 }
 
 /// Identifies an operator-specific root folder in the repository e.g.
@@ -49,6 +226,19 @@ struct OciRepository {
     pub name: String,
 }
 
+/// Lists the repositories of a registry, as returned by the OCI distribution
+/// catalog API e.g.
+/// ```json
+/// {
+///   repositories: ["sdp-charts/airflow-operator"]
+/// }
+/// ```
+#[derive(Deserialize, Debug)]
+struct Catalog {
+    #[serde(default)]
+    pub repositories: Vec<String>,
+}
+
 /// Identifies an image tag e.g.
 /// ```json
 /// {
@@ -118,8 +308,13 @@ impl OciUrlExt for Url {
 }
 
 // TODO (@NickLarsenNZ): Look into why a HashMap is used here when the key is inside each entry in the value
+/// Lists all operator charts and their versions in the OCI `registry`, which
+/// is accessed using `auth`.
 #[instrument]
-pub async fn get_oci_index<'a>() -> Result<HashMap<&'a str, ChartSourceMetadata>, Error> {
+pub async fn get_oci_index<'a>(
+    registry: &OciRegistry,
+    auth: &RepositoryAuth,
+) -> Result<HashMap<&'a str, ChartSourceMetadata>, Error> {
     let mut source_index_files: HashMap<&str, ChartSourceMetadata> = HashMap::new();
 
     // initialize map
@@ -135,22 +330,64 @@ pub async fn get_oci_index<'a>() -> Result<HashMap<&'a str, ChartSourceMetadata>
             },
         );
     }
-    let base_url = format!("https://{HELM_OCI_BASE}/api/v2.0");
+
+    // reuse connections
+    let client = auth.http_client().context(RegistryAuthSnafu)?;
+
+    let tags = match registry.api {
+        RegistryApi::Harbor => get_harbor_tags(registry, &client, auth).await?,
+        RegistryApi::Distribution => get_distribution_tags(registry, &client, auth).await?,
+    };
+
+    for (repository_name, tag) in tags {
+        let release_version = tag.replace("-arm64", "").replace("-amd64", "");
+
+        tracing::trace!(repository_name, release_version, "OCI resolved artifact");
+
+        let repo_name = match release_version.as_str() {
+            "0.0.0-dev" => HELM_REPO_NAME_DEV,
+            version if version.contains("-pr") => HELM_REPO_NAME_TEST,
+            _ => HELM_REPO_NAME_STABLE,
+        };
+
+        if let Some(repo) = source_index_files.get_mut(repo_name) {
+            repo.entries
+                .entry(repository_name.clone())
+                .or_default()
+                .push(ChartSourceEntry {
+                    name: repository_name,
+                    version: release_version,
+                    digest: None,
+                })
+        }
+    }
+
+    Ok(source_index_files)
+}
+
+/// Returns the chart names and their tags using the Harbor-specific API. Only
+/// the first tag of each artifact is returned.
+async fn get_harbor_tags(
+    registry: &OciRegistry,
+    client: &reqwest::Client,
+    auth: &RepositoryAuth,
+) -> Result<Vec<(String, String)>, Error> {
+    let base_url = format!("{base_url}/api/v2.0", base_url = registry.base_url());
 
     // fetch all operators
     let url = format!(
-        "{base_url}/repositories?page_size={page_size}&q=name=~sdp-charts/",
-        page_size = 100
+        "{base_url}/repositories?page_size={page_size}&q=name=~{project}/",
+        page_size = 100,
+        project = registry.project
     );
 
-    // reuse connections
-    let client = reqwest::Client::new();
-
-    let repositories: Vec<OciRepository> = client
-        .get(&url)
+    let repositories: Vec<OciRepository> = auth
+        .authenticate(client.get(&url))
         .send()
         .await
         .context(GetRepositoriesSnafu)?
+        .error_for_status()
+        .context(GetRepositoriesSnafu)?
         .json()
         .await
         .context(ParseRepositoriesSnafu)?;
@@ -160,6 +397,8 @@ pub async fn get_oci_index<'a>() -> Result<HashMap<&'a str, ChartSourceMetadata>
         "Received response for OCI repositories"
     );
 
+    let mut tags = Vec::new();
+
     for repository in &repositories {
         // fetch all artifacts pro operator
         // NOTE (@NickLarsenNZ): I think repository_name should be helm_chart_name.
@@ -177,11 +416,13 @@ pub async fn get_oci_index<'a>() -> Result<HashMap<&'a str, ChartSourceMetadata>
             let root = Url::parse(base_url.as_str()).context(UrlParseSnafu)?;
             let url =
                 root.oci_artifacts_page(project_name, repository_name, OCI_INDEX_PAGE_SIZE, page)?;
-            let artifacts_page = client
-                .get(url)
+            let artifacts_page = auth
+                .authenticate(client.get(url))
                 .send()
                 .await
                 .context(GetArtifactsSnafu)?
+                .error_for_status()
+                .context(GetArtifactsSnafu)?
                 .json::<Vec<Artifact>>()
                 .await
                 .context(ParseArtifactsSnafu)?;
@@ -193,50 +434,434 @@ pub async fn get_oci_index<'a>() -> Result<HashMap<&'a str, ChartSourceMetadata>
             page += 1;
         }
 
-        for artifact in &artifacts {
-            if let Some(release_artifact) =
-                artifact.tags.as_ref().and_then(|tags| tags.iter().next())
-            {
-                let release_version = release_artifact
-                    .name
-                    .replace("-arm64", "")
-                    .replace("-amd64", "");
-
-                tracing::trace!(repository_name, release_version, "OCI resolved artifact");
-
-                let entry = ChartSourceEntry {
-                    name: repository_name.to_string(),
-                    version: release_version.to_string(),
-                };
-
-                match release_version.as_str() {
-                    "0.0.0-dev" => {
-                        if let Some(repo) = source_index_files.get_mut(HELM_REPO_NAME_DEV) {
-                            repo.entries
-                                .entry(repository_name.to_string())
-                                .or_default()
-                                .push(entry)
-                        }
-                    }
-                    version if version.contains("-pr") => {
-                        if let Some(repo) = source_index_files.get_mut(HELM_REPO_NAME_TEST) {
-                            repo.entries
-                                .entry(repository_name.to_string())
-                                .or_default()
-                                .push(entry)
-                        }
-                    }
-                    _ => {
-                        if let Some(repo) = source_index_files.get_mut(HELM_REPO_NAME_STABLE) {
-                            repo.entries
-                                .entry(repository_name.to_string())
-                                .or_default()
-                                .push(entry)
-                        }
-                    }
-                }
+        tags.extend(artifacts.into_iter().filter_map(|artifact| {
+            artifact
+                .tags
+                .and_then(|tags| tags.into_iter().next())
+                .map(|tag| (repository_name.to_owned(), tag.name))
+        }));
+    }
+
+    Ok(tags)
+}
+
+/// Returns the chart names and their tags using the OCI distribution API. The
+/// operator charts are looked up using the catalog of the registry. Many
+/// registries restrict access to the catalog, in which case the charts of the
+/// built-in operators are looked up instead. Charts which don't exist in the
+/// registry, e.g. because they are not mirrored, are skipped.
+async fn get_distribution_tags(
+    registry: &OciRegistry,
+    client: &reqwest::Client,
+    auth: &RepositoryAuth,
+) -> Result<Vec<(String, String)>, Error> {
+    let base_url = Url::parse(&registry.base_url()).context(UrlParseSnafu)?;
+    let mut tags = Vec::new();
+
+    let operators = match get_distribution_operators(registry, client, auth).await {
+        Ok(operators) if !operators.is_empty() => operators,
+        result => {
+            debug!(
+                error = result.err().map(tracing::field::display),
+                "OCI catalog unavailable, using built-in operators"
+            );
+            VALID_OPERATORS.iter().map(ToString::to_string).collect()
+        }
+    };
+
+    for operator in operators {
+        let repository_name = format!("{operator}-operator");
+        let repository = format!("{project}/{repository_name}", project = registry.project);
+
+        let mut distribution_client = DistributionClient::new(client, auth);
+        let mut next_url = Some(
+            base_url
+                .join(&format!(
+                    "/v2/{repository}/tags/list?n={OCI_TAGS_PAGE_SIZE}"
+                ))
+                .context(UrlParseSnafu)?,
+        );
+
+        while let Some(url) = next_url.take() {
+            let response = distribution_client
+                .get(url.clone())
+                .await?
+                .context(GetTagsSnafu {
+                    repository: &repository,
+                })?;
+
+            if response.status() == StatusCode::NOT_FOUND {
+                debug!(repository, "OCI repository not found, skipping");
+                break;
+            }
+
+            let response = response.error_for_status().context(GetTagsSnafu {
+                repository: &repository,
+            })?;
+
+            // Registries paginate the tag list using RFC 5988 Link headers,
+            // the URL of the next page is relative to the registry
+            next_url = next_page_url(&response)
+                .map(|next| url.join(&next))
+                .transpose()
+                .context(UrlParseSnafu)?;
+
+            let tag_list: TagList = response.json().await.context(ParseTagsSnafu {
+                repository: &repository,
+            })?;
+
+            tags.extend(
+                tag_list
+                    .tags
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|tag| (repository_name.clone(), tag)),
+            );
+        }
+    }
+
+    debug!(count = tags.len(), "Received OCI distribution tags");
+
+    Ok(tags)
+}
+
+/// Returns the names of the operators with a chart in the OCI `registry`, which
+/// is accessed using `auth`.
+#[instrument(skip(auth))]
+pub async fn get_operators(
+    registry: &OciRegistry,
+    auth: &RepositoryAuth,
+) -> Result<Vec<String>, Error> {
+    let client = auth.http_client().context(RegistryAuthSnafu)?;
+
+    match registry.api {
+        RegistryApi::Harbor => {
+            let catalog = auth
+                .authenticate(client.get(registry.catalog_url()?))
+                .send()
+                .await
+                .context(GetRepositoriesSnafu)?
+                .error_for_status()
+                .context(GetRepositoriesSnafu)?
+                .text()
+                .await
+                .context(GetRepositoriesSnafu)?;
+
+            registry.operators_from_catalog(&catalog)
+        }
+        RegistryApi::Distribution => get_distribution_operators(registry, &client, auth).await,
+    }
+}
+
+/// Returns the names of the operators listed in the catalog of the OCI
+/// distribution `registry`. All pages of the catalog are retrieved.
+async fn get_distribution_operators(
+    registry: &OciRegistry,
+    client: &reqwest::Client,
+    auth: &RepositoryAuth,
+) -> Result<Vec<String>, Error> {
+    let mut distribution_client = DistributionClient::new(client, auth);
+    let mut next_url = Some(registry.catalog_url()?);
+    let mut operators = Vec::new();
+
+    while let Some(url) = next_url.take() {
+        let response = distribution_client
+            .get(url.clone())
+            .await?
+            .context(GetRepositoriesSnafu)?
+            .error_for_status()
+            .context(GetRepositoriesSnafu)?;
+
+        // The catalog is paginated like the tag list, see get_distribution_tags
+        next_url = next_page_url(&response)
+            .map(|next| url.join(&next))
+            .transpose()
+            .context(UrlParseSnafu)?;
+
+        let catalog = response.text().await.context(GetRepositoriesSnafu)?;
+        operators.extend(registry.operators_from_catalog(&catalog)?);
+    }
+
+    Ok(operators)
+}
+
+/// Returns the digest of the manifest of `chart_name` tagged with `tag` in the
+/// OCI `registry`, e.g. `sha256:e80a4b1e...`. The digest identifies the exact
+/// chart content, which changes when a chart is re-pushed under the same tag.
+#[instrument(skip(auth))]
+pub async fn get_manifest_digest(
+    registry: &OciRegistry,
+    chart_name: &str,
+    tag: &str,
+    auth: &RepositoryAuth,
+) -> Result<String, Error> {
+    let reference = format!(
+        "{repository}/{chart_name}:{tag}",
+        repository = registry.chart_repository()
+    );
+
+    let client = auth.http_client().context(RegistryAuthSnafu)?;
+    let url = Url::parse(&registry.base_url())
+        .and_then(|base_url| {
+            base_url.join(&format!(
+                "/v2/{project}/{chart_name}/manifests/{tag}",
+                project = registry.project
+            ))
+        })
+        .context(UrlParseSnafu)?;
+
+    let response = DistributionClient::new(&client, auth)
+        .get_with_accept(url, MANIFEST_MEDIA_TYPES)
+        .await?
+        .and_then(Response::error_for_status)
+        .context(GetManifestSnafu {
+            reference: &reference,
+        })?;
+
+    // Registries return the digest in a header, but it is optional, in which
+    // case it is computed from the (canonical) manifest content
+    let digest_header = response
+        .headers()
+        .get(DOCKER_CONTENT_DIGEST)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    match digest_header {
+        Some(digest) => Ok(digest),
+        None => {
+            let manifest = response.bytes().await.context(GetManifestSnafu {
+                reference: &reference,
+            })?;
+
+            Ok(format!("sha256:{:x}", Sha256::digest(&manifest)))
+        }
+    }
+}
+
+/// Header containing the digest of a manifest returned by the registry.
+const DOCKER_CONTENT_DIGEST: &str = "Docker-Content-Digest";
+
+/// Helm charts are pushed as OCI image manifests, older registries might still
+/// return Docker manifests.
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+/// Tags of a repository returned by the OCI distribution API, e.g.
+/// ```json
+/// {
+///   name: "sdp-charts/airflow-operator",
+///   tags: ["24.11.1", "25.3.0"]
+/// }
+/// ```
+#[derive(Deserialize, Debug)]
+struct TagList {
+    tags: Option<Vec<String>>,
+}
+
+/// Token returned by the token endpoint of a registry. Depending on the
+/// registry, it is either returned as `token` or `access_token`.
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+/// Sends requests to the OCI distribution API of a single repository.
+///
+/// Most registries (e.g. GHCR or Artifactory) require a bearer token, even
+/// for anonymous access. If the registry responds with a bearer challenge,
+/// a token is requested from the token endpoint (using the configured
+/// credentials) and used for all subsequent requests.
+struct DistributionClient<'a> {
+    client: &'a reqwest::Client,
+    auth: &'a RepositoryAuth,
+    token: Option<String>,
+}
+
+impl<'a> DistributionClient<'a> {
+    fn new(client: &'a reqwest::Client, auth: &'a RepositoryAuth) -> Self {
+        Self {
+            client,
+            auth,
+            token: None,
+        }
+    }
+
+    /// Sends a GET request to `url`. Request errors are returned in the
+    /// inner result, so that callers can add their own context.
+    async fn get(&mut self, url: Url) -> Result<Result<Response, reqwest::Error>, Error> {
+        self.get_with_accept(url, "application/json").await
+    }
+
+    /// Like [`DistributionClient::get`], but with a custom `Accept` header.
+    async fn get_with_accept(
+        &mut self,
+        url: Url,
+        accept: &str,
+    ) -> Result<Result<Response, reqwest::Error>, Error> {
+        let response = match self.send(url.clone(), accept).await {
+            Ok(response) => response,
+            Err(err) => return Ok(Err(err)),
+        };
+
+        if response.status() != StatusCode::UNAUTHORIZED || self.token.is_some() {
+            return Ok(Ok(response));
+        }
+
+        let Some(challenge) = response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return Ok(Ok(response));
+        };
+
+        self.token = Some(self.request_token(challenge).await?);
+        Ok(self.send(url, accept).await)
+    }
+
+    async fn send(&self, url: Url, accept: &str) -> Result<Response, reqwest::Error> {
+        let request = self.client.get(url).header(ACCEPT, accept);
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => self.auth.authenticate(request),
+        };
+
+        request.send().await
+    }
+
+    /// Requests a token as described by the bearer `challenge`, e.g.
+    /// `realm="https://ghcr.io/token",service="ghcr.io",scope="repository:foo/bar:pull"`.
+    async fn request_token(&self, challenge: &str) -> Result<String, Error> {
+        let params = parse_challenge(challenge);
+        let realm = params
+            .get("realm")
+            .copied()
+            .context(InvalidTokenChallengeSnafu { challenge })?;
+
+        let mut url = Url::parse(realm).context(UrlParseSnafu)?;
+        for key in ["service", "scope"] {
+            if let Some(value) = params.get(key) {
+                url.query_pairs_mut().append_pair(key, value);
             }
         }
+
+        let response: TokenResponse = self
+            .auth
+            .authenticate(self.client.get(url))
+            .send()
+            .await
+            .context(GetTokenSnafu { realm })?
+            .error_for_status()
+            .context(GetTokenSnafu { realm })?
+            .json()
+            .await
+            .context(ParseTokenSnafu { realm })?;
+
+        response
+            .token
+            .or(response.access_token)
+            .context(InvalidTokenChallengeSnafu { challenge })
+    }
+}
+
+/// Parses the comma-separated `key="value"` parameters of a bearer challenge.
+/// Commas within quoted values, e.g. `scope="repository:foo:pull,push"`, don't
+/// separate parameters.
+fn parse_challenge(challenge: &str) -> HashMap<&str, &str> {
+    let mut in_quotes = false;
+
+    challenge
+        .split(|c: char| {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            }
+
+            c == ',' && !in_quotes
+        })
+        .filter_map(|param| param.trim().split_once('='))
+        .map(|(key, value)| (key, value.trim_matches('"')))
+        .collect()
+}
+
+/// Returns the (possibly relative) URL of the next page from the `Link`
+/// header of the `response`, e.g. `</v2/foo/tags/list?n=100&last=1.0>; rel="next"`.
+fn next_page_url(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find(|link| link.contains("rel=\"next\""))
+        .and_then(|link| {
+            let (url, _) = link.split_once(';')?;
+            Some(
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_owned(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(
+        "oci.stackable.tech/sdp-charts",
+        "oci.stackable.tech",
+        "sdp-charts",
+        RegistryApi::Harbor
+    )]
+    #[case(
+        "oci://ghcr.io/acme/charts/",
+        "ghcr.io",
+        "acme/charts",
+        RegistryApi::Distribution
+    )]
+    #[case(
+        "localhost:5000/sdp-charts",
+        "localhost:5000",
+        "sdp-charts",
+        RegistryApi::Distribution
+    )]
+    fn parse_registry(
+        #[case] reference: &str,
+        #[case] host: &str,
+        #[case] project: &str,
+        #[case] api: RegistryApi,
+    ) {
+        let registry = OciRegistry::parse(reference, None).unwrap();
+
+        assert_eq!(registry.host, host);
+        assert_eq!(registry.project, project);
+        assert_eq!(registry.api, api);
+    }
+
+    #[test]
+    fn parse_bearer_challenge() {
+        let params = parse_challenge(
+            r#"realm="https://ghcr.io/token",service="ghcr.io",scope="repository:acme/airflow-operator:pull""#,
+        );
+
+        assert_eq!(params.get("realm"), Some(&"https://ghcr.io/token"));
+        assert_eq!(params.get("service"), Some(&"ghcr.io"));
+        assert_eq!(
+            params.get("scope"),
+            Some(&"repository:acme/airflow-operator:pull")
+        );
+
+        // Commas within quoted values don't separate parameters
+        let params = parse_challenge(
+            r#"realm="https://ghcr.io/token",scope="repository:x/y:pull,push",service="ghcr.io""#,
+        );
+
+        assert_eq!(params.get("scope"), Some(&"repository:x/y:pull,push"));
+        assert_eq!(params.get("service"), Some(&"ghcr.io"));
     }
-    Ok(source_index_files)
 }
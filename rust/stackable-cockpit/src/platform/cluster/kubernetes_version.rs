@@ -0,0 +1,59 @@
+use semver::{Version, VersionReq};
+use snafu::{ResultExt, Snafu};
+use tracing::{debug, warn};
+
+use crate::utils::k8s::{Client, Error};
+
+type Result<T, E = KubernetesVersionError> = std::result::Result<T, E>;
+
+/// This error indicates that the Kubernetes version of the cluster couldn't be
+/// retrieved or isn't supported by a release, stack or demo.
+#[derive(Debug, Snafu)]
+pub enum KubernetesVersionError {
+    #[snafu(display("failed to retrieve Kubernetes version"))]
+    KubernetesVersion { source: Error },
+
+    #[snafu(display(
+        "the {object_name} only supports Kubernetes versions {supported}, but the cluster runs Kubernetes {version}"
+    ))]
+    UnsupportedKubernetesVersion {
+        object_name: String,
+        version: Version,
+        supported: VersionReq,
+    },
+}
+
+/// Checks if the Kubernetes version of the current cluster matches the
+/// `supported` version requirement. `object_name` should describe what is
+/// installed, e.g. `release 24.11` or `stack trino-iceberg`. If `skip` is set,
+/// an unsupported version is only logged as a warning.
+pub async fn check_kubernetes_version(
+    client: &Client,
+    supported: &VersionReq,
+    object_name: &str,
+    skip: bool,
+) -> Result<()> {
+    let version = client
+        .get_kubernetes_version()
+        .await
+        .context(KubernetesVersionSnafu)?;
+
+    debug!(%version, %supported, object_name, "Checking Kubernetes version");
+
+    if supported.matches(&version) {
+        return Ok(());
+    }
+
+    let err = KubernetesVersionError::UnsupportedKubernetesVersion {
+        object_name: object_name.to_owned(),
+        version,
+        supported: supported.clone(),
+    };
+
+    if skip {
+        warn!("{err}");
+        return Ok(());
+    }
+
+    Err(err)
+}
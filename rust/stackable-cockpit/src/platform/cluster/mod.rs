@@ -1,11 +1,16 @@
+use semver::Version;
 use snafu::{ResultExt, Snafu};
 use stackable_operator::{
-    cpu::CpuQuantity, k8s_openapi::api::core::v1::Node, kube::core::ObjectList,
+    cpu::CpuQuantity,
+    k8s_openapi::{api::core::v1::Node, apimachinery::pkg::version::Info},
+    kube::core::ObjectList,
     memory::MemoryQuantity,
 };
 
+mod kubernetes_version;
 mod resource_request;
 
+pub use kubernetes_version::*;
 pub use resource_request::*;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -21,12 +26,22 @@ pub enum Error {
     ParseNodeMemoryQuantity {
         source: stackable_operator::memory::Error,
     },
+
+    #[snafu(display("failed to parse Kubernetes version {version:?}"))]
+    ParseKubernetesVersion {
+        source: semver::Error,
+        version: String,
+    },
 }
 
 /// [`ClusterInfo`] contains information about the Kubernetes cluster, such as
-/// the number of nodes and allocatable resources.
+/// the Kubernetes version, the number of nodes and allocatable resources.
 #[derive(Debug)]
 pub struct ClusterInfo {
+    /// Version of the Kubernetes API server without any pre-release or build
+    /// metadata, e.g. `1.31.2` for `v1.31.2-gke.1059000`.
+    pub kubernetes_version: Version,
+
     /// All nodes of the cluster regardless of their type
     pub node_count: usize,
 
@@ -47,7 +62,9 @@ pub struct ClusterInfo {
 }
 
 impl ClusterInfo {
-    pub fn from_nodes(nodes: ObjectList<Node>) -> Result<Self> {
+    pub fn new(nodes: ObjectList<Node>, version_info: &Info) -> Result<Self> {
+        let kubernetes_version = parse_kubernetes_version(version_info)?;
+
         // FIXME (Techassi): Also retrieve number of control plane nodes
         let node_count = nodes.items.len();
 
@@ -80,6 +97,7 @@ impl ClusterInfo {
         }
 
         Ok(ClusterInfo {
+            kubernetes_version,
             node_count,
             untainted_node_count,
             untainted_allocatable_cpu,
@@ -87,3 +105,38 @@ impl ClusterInfo {
         })
     }
 }
+
+/// Parses the version reported by the Kubernetes API server. Pre-release and
+/// build metadata added by vendors (like `-eks-f69f56f` or `+k3s1`) is dropped,
+/// as such versions would never match plain version requirements.
+pub fn parse_kubernetes_version(version_info: &Info) -> Result<Version> {
+    let version = Version::parse(version_info.git_version.trim_start_matches('v')).context(
+        ParseKubernetesVersionSnafu {
+            version: version_info.git_version.clone(),
+        },
+    )?;
+
+    Ok(Version::new(version.major, version.minor, version.patch))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_vendor_kubernetes_versions() {
+        for (git_version, expected) in [
+            ("v1.31.2", Version::new(1, 31, 2)),
+            ("v1.30.5-gke.1014001", Version::new(1, 30, 5)),
+            ("v1.29.8-eks-a737599", Version::new(1, 29, 8)),
+            ("v1.28.3+k3s1", Version::new(1, 28, 3)),
+        ] {
+            let version_info = Info {
+                git_version: git_version.to_owned(),
+                ..Default::default()
+            };
+
+            assert_eq!(parse_kubernetes_version(&version_info).unwrap(), expected);
+        }
+    }
+}
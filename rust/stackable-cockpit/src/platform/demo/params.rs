@@ -17,6 +17,10 @@ pub struct DemoInstallParameters {
     pub parameters: Vec<String>,
     pub skip_release: bool,
 
+    /// Only warn instead of failing if the demo, stack or release doesn't
+    /// support the Kubernetes version of the cluster.
+    pub skip_kubernetes_version_check: bool,
+
     pub stack_labels: Labels,
     pub labels: Labels,
     pub chart_source: ChartSourceType,
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use semver::VersionReq;
 use serde::{Deserialize, Serialize};
 use snafu::{OptionExt, ResultExt, Snafu};
 use stackable_operator::kvp::{Label, LabelError};
@@ -8,10 +11,12 @@ use utoipa::ToSchema;
 
 use crate::{
     common::manifest::ManifestSpec,
+    helm,
     platform::{
-        cluster::{ResourceRequests, ResourceRequestsError},
+        cluster::{self, KubernetesVersionError, ResourceRequests, ResourceRequestsError},
         demo::{DemoInstallParameters, DemoUninstallParameters},
         manifests::{self, InstallManifestsExt},
+        plan::{self, InstallPlan},
         release::ReleaseList,
         stack::{self, StackInstallParameters, StackList},
     },
@@ -36,6 +41,9 @@ pub enum Error {
     #[snafu(display("demo resource requests error"), context(false))]
     DemoResourceRequests { source: ResourceRequestsError },
 
+    #[snafu(display("demo Kubernetes version error"), context(false))]
+    DemoKubernetesVersion { source: KubernetesVersionError },
+
     #[snafu(display("cannot install demo in namespace {requested:?}, only {supported:?} supported", supported = supported.join(", ")))]
     UnsupportedNamespace {
         requested: String,
@@ -48,6 +56,9 @@ pub enum Error {
     #[snafu(display("failed to install stack"))]
     InstallStack { source: stack::Error },
 
+    #[snafu(display("failed to build install plan for stack"))]
+    PlanStack { source: stack::Error },
+
     /// This error indicates that the release failed to uninstall.
     #[snafu(display("failed to uninstall release"))]
     UninstallRelease { source: stack::Error },
@@ -55,6 +66,12 @@ pub enum Error {
     #[snafu(display("failed to install stack manifests"))]
     InstallManifests { source: manifests::Error },
 
+    #[snafu(display("failed to render demo manifests"))]
+    RenderManifests { source: manifests::Error },
+
+    #[snafu(display("failed to build install plan"))]
+    BuildPlan { source: plan::Error },
+
     #[snafu(display("failed to uninstall Helm manifests"))]
     UninstallHelmManifests { source: manifests::Error },
 
@@ -99,6 +116,12 @@ pub struct DemoSpec {
     /// The resource requests the demo imposes on a Kubernetes cluster
     pub resource_requests: Option<ResourceRequests>,
 
+    /// Kubernetes versions this demo supports, e.g. `>=1.29`. If not set, the
+    /// demo is assumed to support every Kubernetes version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub supported_kubernetes_versions: Option<VersionReq>,
+
     /// A variable number of supported parameters
     #[serde(default)]
     pub parameters: Vec<Parameter>,
@@ -110,12 +133,18 @@ impl DemoSpec {
     ///
     /// - Does the demo support to be installed in the requested namespace?
     /// - Does the cluster have enough resources available to run this demo?
+    /// - Does the demo support the Kubernetes version of the cluster?
     #[instrument(skip_all)]
-    pub async fn check_prerequisites(&self, client: &Client, namespace: &str) -> Result<(), Error> {
+    pub async fn check_prerequisites(
+        &self,
+        client: &Client,
+        install_parameters: &DemoInstallParameters,
+    ) -> Result<(), Error> {
         debug!("Checking prerequisites before installing demo");
 
         // Returns an error if the demo doesn't support to be installed in the
         // requested namespace
+        let namespace = &install_parameters.demo_namespace;
         if !self.supports_namespace(namespace) {
             return Err(Error::UnsupportedNamespace {
                 requested: namespace.to_owned(),
@@ -123,6 +152,16 @@ impl DemoSpec {
             });
         }
 
+        if let Some(supported_versions) = &self.supported_kubernetes_versions {
+            cluster::check_kubernetes_version(
+                client,
+                supported_versions,
+                &format!("demo {demo_name}", demo_name = install_parameters.demo_name),
+                install_parameters.skip_kubernetes_version_check,
+            )
+            .await?;
+        }
+
         // Checks if the available cluster resources are sufficient to deploy
         // the demo.
         if let Some(resource_requests) = &self.resource_requests {
@@ -155,6 +194,7 @@ impl DemoSpec {
         release_list: ReleaseList,
         install_parameters: DemoInstallParameters,
         client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         // Get the stack spec based on the name defined in the demo spec
@@ -163,33 +203,24 @@ impl DemoSpec {
         })?;
 
         // Check demo prerequisites
-        self.check_prerequisites(client, &install_parameters.demo_namespace)
+        self.check_prerequisites(client, &install_parameters)
             .await?;
 
-        let stack_install_parameters = StackInstallParameters {
-            stack_name: self.stack.clone(),
-            demo_name: Some(install_parameters.demo_name.clone()),
-            operator_namespace: install_parameters.operator_namespace.clone(),
-            stack_namespace: install_parameters.demo_namespace.clone(),
-            parameters: install_parameters.stack_parameters.clone(),
-            labels: install_parameters.stack_labels.clone(),
-            skip_release: install_parameters.skip_release,
-            chart_source: install_parameters.chart_source.clone(),
-            operator_values: install_parameters.operator_values.clone(),
-        };
+        let stack_install_parameters = self.stack_install_parameters(&install_parameters);
 
         stack
             .install(
                 release_list,
                 stack_install_parameters,
                 client,
+                helm_client,
                 transfer_client,
             )
             .await
             .context(InstallStackSnafu)?;
 
         // Install demo manifests
-        self.prepare_manifests(install_parameters, client, transfer_client)
+        self.prepare_manifests(install_parameters, client, helm_client, transfer_client)
             .await
     }
 
@@ -204,6 +235,7 @@ impl DemoSpec {
         release_list: ReleaseList,
         uninstall_parameters: DemoUninstallParameters,
         client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         // Get the stack spec based on the name defined in the demo spec
@@ -224,6 +256,7 @@ impl DemoSpec {
             &self.manifests,
             parameters,
             &uninstall_parameters.demo_namespace.to_owned(),
+            helm_client,
             transfer_client,
         )
         .await
@@ -241,6 +274,7 @@ impl DemoSpec {
             &stack.manifests,
             stack_parameters,
             &uninstall_parameters.demo_namespace.to_owned(),
+            helm_client,
             transfer_client,
         )
         .await
@@ -265,7 +299,11 @@ impl DemoSpec {
         // Delete operators and the operator namespace
         if !uninstall_parameters.skip_operators {
             stack
-                .uninstall_release(release_list, &uninstall_parameters.operator_namespace)
+                .uninstall_release(
+                    release_list,
+                    &uninstall_parameters.operator_namespace,
+                    helm_client,
+                )
                 .await
                 .context(UninstallReleaseSnafu)?;
 
@@ -296,20 +334,13 @@ impl DemoSpec {
         &self,
         install_parameters: DemoInstallParameters,
         client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         info!("Installing demo manifests");
         Span::current().pb_set_message("Installing manifests");
 
-        let mut parameters = install_parameters
-            .parameters
-            .to_owned()
-            .into_params(&self.parameters)
-            .context(ParseParametersSnafu)?;
-
-        // We add the STACK and DEMO parameter, so that demos can use that to render e.g. the demo label
-        parameters.insert("STACK".to_owned(), install_parameters.stack_name);
-        parameters.insert("DEMO".to_owned(), install_parameters.demo_name);
+        let parameters = self.manifest_parameters(&install_parameters)?;
 
         Self::install_manifests(
             &self.manifests,
@@ -317,12 +348,101 @@ impl DemoSpec {
             &install_parameters.demo_namespace,
             install_parameters.labels,
             client,
+            helm_client,
             transfer_client,
         )
         .await
         .context(InstallManifestsSnafu)
     }
 
+    /// Builds an [`InstallPlan`] which describes what [`DemoSpec::install`]
+    /// would do with the same parameters, including the plan of the
+    /// underlying stack. This doesn't access the cluster.
+    #[instrument(skip_all, fields(
+        stack_name = %self.stack,
+        operator_namespace = %install_parameters.operator_namespace,
+        demo_namespace = %install_parameters.demo_namespace,
+    ))]
+    pub async fn plan(
+        &self,
+        stack_list: &StackList,
+        release_list: &ReleaseList,
+        install_parameters: &DemoInstallParameters,
+        transfer_client: &xfer::Client,
+    ) -> Result<InstallPlan, Error> {
+        debug!("Building install plan for demo");
+
+        // Get the stack spec based on the name defined in the demo spec
+        let stack = stack_list.get(&self.stack).context(NoSuchStackSnafu {
+            name: self.stack.clone(),
+        })?;
+
+        if !self.supports_namespace(&install_parameters.demo_namespace) {
+            return Err(Error::UnsupportedNamespace {
+                requested: install_parameters.demo_namespace.clone(),
+                supported: self.supported_namespaces.clone(),
+            });
+        }
+
+        let stack_install_parameters = self.stack_install_parameters(install_parameters);
+        let mut plan = stack
+            .plan(release_list, &stack_install_parameters, transfer_client)
+            .await
+            .context(PlanStackSnafu)?;
+
+        let parameters = self.manifest_parameters(install_parameters)?;
+        let manifests = Self::render_manifests(
+            &self.manifests,
+            &parameters,
+            &install_parameters.demo_namespace,
+            transfer_client,
+        )
+        .await
+        .context(RenderManifestsSnafu)?;
+
+        plan.add_manifests(&manifests, &install_parameters.demo_namespace)
+            .context(BuildPlanSnafu)?;
+
+        Ok(plan)
+    }
+
+    /// Returns the parameters used to install the underlying stack.
+    fn stack_install_parameters(
+        &self,
+        install_parameters: &DemoInstallParameters,
+    ) -> StackInstallParameters {
+        StackInstallParameters {
+            stack_name: self.stack.clone(),
+            demo_name: Some(install_parameters.demo_name.clone()),
+            operator_namespace: install_parameters.operator_namespace.clone(),
+            stack_namespace: install_parameters.demo_namespace.clone(),
+            parameters: install_parameters.stack_parameters.clone(),
+            labels: install_parameters.stack_labels.clone(),
+            skip_release: install_parameters.skip_release,
+            skip_kubernetes_version_check: install_parameters.skip_kubernetes_version_check,
+            chart_source: install_parameters.chart_source.clone(),
+            operator_values: install_parameters.operator_values.clone(),
+        }
+    }
+
+    /// Returns the parameters used to render the demo manifests.
+    fn manifest_parameters(
+        &self,
+        install_parameters: &DemoInstallParameters,
+    ) -> Result<HashMap<String, String>, Error> {
+        let mut parameters = install_parameters
+            .parameters
+            .to_owned()
+            .into_params(&self.parameters)
+            .context(ParseParametersSnafu)?;
+
+        // We add the STACK and DEMO parameter, so that demos can use that to render e.g. the demo label
+        parameters.insert("STACK".to_owned(), install_parameters.stack_name.clone());
+        parameters.insert("DEMO".to_owned(), install_parameters.demo_name.clone());
+
+        Ok(parameters)
+    }
+
     fn supports_namespace(&self, namespace: impl Into<String>) -> bool {
         self.supported_namespaces.is_empty()
             || self.supported_namespaces.contains(&namespace.into())
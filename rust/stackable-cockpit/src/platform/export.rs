@@ -0,0 +1,414 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use semver::Version;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use snafu::{OptionExt, ResultExt, Snafu};
+use stackable_operator::kvp::Labels;
+use tracing::{debug, instrument};
+
+use crate::{
+    helm::ChartSourceKind,
+    platform::{
+        operator::{self, ChartSourceType, OperatorSpec},
+        plan::InstallPlan,
+    },
+    utils::yaml::values_for_operator,
+};
+
+const KUSTOMIZATION_FILE: &str = "kustomization.yaml";
+const NAMESPACES_FILE: &str = "namespaces.yaml";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("failed to parse version {version:?} of operator {name:?}"))]
+    ParseOperatorVersion {
+        source: semver::Error,
+        name: String,
+        version: String,
+    },
+
+    #[snafu(display("failed to parse operator spec"))]
+    OperatorSpecParse { source: operator::SpecParseError },
+
+    #[snafu(display(
+        "local chart {chart:?} cannot be exported, only charts from Helm repositories and OCI registries can be referenced"
+    ))]
+    UnsupportedLocalChart { chart: String },
+
+    #[snafu(display("no URL configured for Helm repository {repo_name:?}"))]
+    UnknownHelmRepository { repo_name: String },
+
+    #[snafu(display("failed to serialize {path:?}"))]
+    SerializeFile {
+        source: serde_yaml::Error,
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug)]
+pub struct ExportParameters {
+    /// URLs of the Stackable Helm repositories, keyed by repository name,
+    /// which are used for operators with the [`ChartSourceType::Repo`] chart
+    /// source.
+    pub helm_repo_urls: HashMap<String, String>,
+
+    /// Helm values of the operators, keyed by `<OPERATOR>-operator`.
+    pub operator_values: Mapping,
+
+    /// Labels which are added to all plain YAML objects, like `stackablectl`
+    /// does when installing them.
+    pub labels: Labels,
+}
+
+/// A file of an exported bundle. The `path` is relative to the directory the
+/// bundle is written to.
+#[derive(Debug)]
+pub struct ExportedFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Kustomization {
+    api_version: &'static str,
+    kind: &'static str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    resources: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    helm_charts: Vec<KustomizeHelmChart>,
+}
+
+impl Kustomization {
+    fn new(namespace: Option<String>) -> Self {
+        Self {
+            api_version: "kustomize.config.k8s.io/v1beta1",
+            kind: "Kustomization",
+            namespace,
+            resources: Vec::new(),
+            helm_charts: Vec::new(),
+        }
+    }
+}
+
+/// A Helm chart rendered by Kustomize, which requires the `--enable-helm`
+/// build option.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct KustomizeHelmChart {
+    name: String,
+    repo: String,
+    version: String,
+    release_name: String,
+    namespace: String,
+    values_file: String,
+    include_crds: bool,
+}
+
+/// Exports the install `plan` of a stack or demo as a bundle of files, which
+/// can be committed to Git and deployed using GitOps tools like Argo CD.
+///
+/// The bundle contains a Kustomize directory per namespace with the rendered
+/// plain YAML objects, and the operators and Helm charts as `helmCharts`
+/// entries with their values files. A top-level `kustomization.yaml`
+/// references the namespace directories and the namespaces to create.
+#[instrument(skip_all)]
+pub fn export_plan(
+    plan: &InstallPlan,
+    parameters: &ExportParameters,
+) -> Result<Vec<ExportedFile>, Error> {
+    debug!("Exporting install plan");
+
+    let labels: BTreeMap<String, String> = parameters.labels.clone().into();
+    let mut files = Vec::new();
+    let mut kustomizations: Vec<(String, Kustomization)> = plan
+        .namespaces
+        .iter()
+        .map(|namespace| {
+            (
+                namespace.clone(),
+                Kustomization::new(Some(namespace.clone())),
+            )
+        })
+        .collect();
+
+    for operator in &plan.operators {
+        let version = Version::parse(&operator.version).context(ParseOperatorVersionSnafu {
+            name: &operator.name,
+            version: &operator.version,
+        })?;
+        let operator_spec =
+            OperatorSpec::new(&operator.name, Some(version)).context(OperatorSpecParseSnafu)?;
+
+        let repo = operator_chart_repo(
+            &operator_spec,
+            &operator.chart_source,
+            &parameters.helm_repo_urls,
+        )?;
+        let values = operator_spec.helm_values(&values_for_operator(
+            &parameters.operator_values,
+            &operator.name,
+        ));
+
+        let values_file = format!("values/{name}.yaml", name = operator_spec.helm_name());
+        files.push(yaml_file(
+            PathBuf::from(&operator.namespace).join(&values_file),
+            &values,
+        )?);
+
+        kustomization(&mut kustomizations, &operator.namespace)
+            .helm_charts
+            .push(KustomizeHelmChart {
+                name: operator_spec.helm_name(),
+                repo,
+                version: operator.version.clone(),
+                release_name: operator_spec.helm_name(),
+                namespace: operator.namespace.clone(),
+                values_file,
+                include_crds: true,
+            });
+    }
+
+    for (index, object) in plan.objects.iter().enumerate() {
+        let mut content = object.content.clone();
+        add_labels(&mut content, &labels);
+
+        let manifest_file = format!(
+            "manifests/{index:03}-{kind}-{name}.yaml",
+            kind = object.kind.to_lowercase(),
+            name = object.name
+        );
+        files.push(yaml_file(
+            PathBuf::from(&object.namespace).join(&manifest_file),
+            &content,
+        )?);
+
+        kustomization(&mut kustomizations, &object.namespace)
+            .resources
+            .push(manifest_file);
+    }
+
+    for release in &plan.helm_releases {
+        if ChartSourceKind::from_url(&release.chart_repo) == ChartSourceKind::Local {
+            return UnsupportedLocalChartSnafu {
+                chart: &release.chart_repo,
+            }
+            .fail();
+        }
+
+        let values_file = format!("values/{name}.yaml", name = release.release_name);
+        files.push(ExportedFile {
+            path: PathBuf::from(&release.namespace).join(&values_file),
+            content: release.values.clone(),
+        });
+
+        kustomization(&mut kustomizations, &release.namespace)
+            .helm_charts
+            .push(KustomizeHelmChart {
+                name: release.chart_name.clone(),
+                repo: release.chart_repo.clone(),
+                version: release.chart_version.clone(),
+                release_name: release.release_name.clone(),
+                namespace: release.namespace.clone(),
+                values_file,
+                include_crds: true,
+            });
+    }
+
+    files.push(ExportedFile {
+        path: PathBuf::from(NAMESPACES_FILE),
+        content: namespaces_manifest(&plan.namespaces)?,
+    });
+
+    let mut root_kustomization = Kustomization::new(None);
+    root_kustomization
+        .resources
+        .push(NAMESPACES_FILE.to_owned());
+
+    for (namespace, kustomization) in kustomizations {
+        files.push(yaml_file(
+            PathBuf::from(&namespace).join(KUSTOMIZATION_FILE),
+            &kustomization,
+        )?);
+        root_kustomization.resources.push(namespace);
+    }
+
+    files.push(yaml_file(
+        PathBuf::from(KUSTOMIZATION_FILE),
+        &root_kustomization,
+    )?);
+
+    Ok(files)
+}
+
+/// Returns the Kustomization of `namespace`, which is added if the namespace
+/// is not part of the plan.
+fn kustomization<'a>(
+    kustomizations: &'a mut Vec<(String, Kustomization)>,
+    namespace: &str,
+) -> &'a mut Kustomization {
+    let index = match kustomizations.iter().position(|(ns, _)| ns == namespace) {
+        Some(index) => index,
+        None => {
+            kustomizations.push((
+                namespace.to_owned(),
+                Kustomization::new(Some(namespace.to_owned())),
+            ));
+            kustomizations.len() - 1
+        }
+    };
+
+    &mut kustomizations[index].1
+}
+
+fn operator_chart_repo(
+    operator: &OperatorSpec,
+    chart_source: &ChartSourceType,
+    helm_repo_urls: &HashMap<String, String>,
+) -> Result<String, Error> {
+    match chart_source {
+        ChartSourceType::OCI(registry) => Ok(registry.chart_repository()),
+        ChartSourceType::Repo => {
+            let repo_name = operator.helm_repo_name();
+            helm_repo_urls
+                .get(&repo_name)
+                .cloned()
+                .context(UnknownHelmRepositorySnafu { repo_name })
+        }
+        ChartSourceType::Local(path) => UnsupportedLocalChartSnafu {
+            chart: path.join(operator.helm_name()).to_string_lossy(),
+        }
+        .fail(),
+    }
+}
+
+fn add_labels(object: &mut Value, labels: &BTreeMap<String, String>) {
+    let Some(metadata) = object.get_mut("metadata").and_then(Value::as_mapping_mut) else {
+        return;
+    };
+
+    if !metadata.get("labels").is_some_and(Value::is_mapping) {
+        metadata.insert("labels".into(), Value::Mapping(Mapping::new()));
+    }
+
+    let object_labels = metadata
+        .get_mut("labels")
+        .and_then(Value::as_mapping_mut)
+        .expect("labels were just set to a mapping");
+
+    for (key, value) in labels {
+        object_labels.insert(key.as_str().into(), value.as_str().into());
+    }
+}
+
+fn namespaces_manifest(namespaces: &[String]) -> Result<String, Error> {
+    let mut documents = Vec::new();
+
+    for namespace in namespaces {
+        let mut metadata = Mapping::new();
+        metadata.insert("name".into(), namespace.as_str().into());
+
+        let mut object = Mapping::new();
+        object.insert("apiVersion".into(), "v1".into());
+        object.insert("kind".into(), "Namespace".into());
+        object.insert("metadata".into(), Value::Mapping(metadata));
+
+        documents.push(serde_yaml::to_string(&object).context(SerializeFileSnafu {
+            path: NAMESPACES_FILE,
+        })?);
+    }
+
+    Ok(documents.join("---\n"))
+}
+
+fn yaml_file<T: Serialize>(path: PathBuf, value: &T) -> Result<ExportedFile, Error> {
+    let content = serde_yaml::to_string(value).context(SerializeFileSnafu { path: &path })?;
+    Ok(ExportedFile { path, content })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::platform::plan::{PlannedHelmRelease, PlannedObject, PlannedOperator};
+
+    use super::*;
+
+    #[test]
+    fn export_plan_per_namespace() {
+        let plan = InstallPlan {
+            operators: vec![PlannedOperator {
+                name: "trino".to_owned(),
+                version: "25.3.0".to_owned(),
+                namespace: "stackable-operators".to_owned(),
+                chart_source: ChartSourceType::Repo,
+            }],
+            namespaces: vec!["stackable-operators".to_owned(), "trino".to_owned()],
+            objects: vec![PlannedObject {
+                api_version: "v1".to_owned(),
+                kind: "ConfigMap".to_owned(),
+                name: "trino-config".to_owned(),
+                namespace: "trino".to_owned(),
+                manifest: "manifests/trino.yaml".to_owned(),
+                content: serde_yaml::from_str(
+                    "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: trino-config\n",
+                )
+                .unwrap(),
+            }],
+            helm_releases: vec![PlannedHelmRelease {
+                release_name: "minio".to_owned(),
+                chart_name: "minio".to_owned(),
+                chart_version: "5.0.0".to_owned(),
+                chart_repo: "https://charts.min.io/".to_owned(),
+                namespace: "trino".to_owned(),
+                values: "mode: standalone\n".to_owned(),
+            }],
+        };
+        let parameters = ExportParameters {
+            helm_repo_urls: HashMap::from([(
+                "stackable-stable".to_owned(),
+                "https://repo.stackable.tech/repository/helm-stable/".to_owned(),
+            )]),
+            operator_values: serde_yaml::from_str("trino-operator:\n  replicas: 2\n").unwrap(),
+            labels: Labels::try_from([("stackable.tech/stack", "trino")]).unwrap(),
+        };
+
+        let files = export_plan(&plan, &parameters).unwrap();
+        let file = |path: &str| {
+            files
+                .iter()
+                .find(|file| file.path == PathBuf::from(path))
+                .map(|file| file.content.as_str())
+                .unwrap_or_else(|| panic!("file {path} was not exported"))
+        };
+
+        assert_eq!(
+            file("stackable-operators/values/trino-operator.yaml"),
+            "replicas: 2\n"
+        );
+        assert!(
+            file("trino/manifests/000-configmap-trino-config.yaml")
+                .contains("stackable.tech/stack: trino")
+        );
+        assert_eq!(file("trino/values/minio.yaml"), "mode: standalone\n");
+        assert!(
+            file("stackable-operators/kustomization.yaml")
+                .contains("repo: https://repo.stackable.tech/repository/helm-stable/")
+        );
+        assert!(
+            file("trino/kustomization.yaml")
+                .contains("- manifests/000-configmap-trino-config.yaml")
+        );
+        assert_eq!(
+            file("kustomization.yaml"),
+            "apiVersion: kustomize.config.k8s.io/v1beta1\nkind: Kustomization\nresources:\n- namespaces.yaml\n- stackable-operators\n- trino\n"
+        );
+    }
+}
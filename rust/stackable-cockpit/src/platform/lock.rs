@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt, Snafu};
+use tracing::{debug, instrument};
+
+use crate::{
+    helm::{self, ChartSourceKind, RepositoryAuth, chart_reference},
+    oci::{self, OciRegistry},
+    platform::{
+        operator::{self, ChartSourceType, OperatorSpec},
+        plan::InstallPlan,
+    },
+    utils::chartsource::ChartSourceMetadata,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("failed to parse version {version:?} of operator {name:?}"))]
+    ParseOperatorVersion {
+        source: semver::Error,
+        name: String,
+        version: String,
+    },
+
+    #[snafu(display("failed to parse operator spec"))]
+    OperatorSpecParse { source: operator::SpecParseError },
+
+    #[snafu(display("operator {name:?} has no version, only explicit versions can be locked"))]
+    MissingOperatorVersion { name: String },
+
+    #[snafu(display("local chart {chart:?} cannot be locked"))]
+    UnsupportedLocalChart { chart: String },
+
+    #[snafu(display("no URL configured for Helm repository {repo_name:?}"))]
+    UnknownHelmRepository { repo_name: String },
+
+    #[snafu(display("failed to parse OCI registry of chart {chart:?}"))]
+    ParseOciRegistry { source: oci::Error, chart: String },
+
+    #[snafu(display("failed to resolve digest of chart {chart:?}"))]
+    ResolveOciDigest { source: oci::Error, chart: String },
+
+    #[snafu(display("failed to retrieve Helm index file from {url:?}"))]
+    RetrieveHelmIndex { source: helm::Error, url: String },
+
+    #[snafu(display("chart {chart:?} in version {version} not found in Helm repository"))]
+    ChartVersionNotFound { chart: String, version: String },
+
+    #[snafu(display(
+        "Helm repository doesn't provide a digest for chart {chart:?} in version {version}"
+    ))]
+    MissingChartDigest { chart: String, version: String },
+}
+
+#[derive(Debug, Snafu)]
+pub enum VerifyError {
+    #[snafu(display("chart {chart:?} in version {version} is not locked"))]
+    ChartNotLocked { chart: String, version: String },
+
+    #[snafu(display(
+        "chart {chart:?} is locked to version {locked}, but version {requested} was requested"
+    ))]
+    VersionMismatch {
+        chart: String,
+        locked: String,
+        requested: String,
+    },
+
+    #[snafu(display(
+        "digest of chart {chart:?} in version {version} changed from {locked} to {actual}, it was likely re-pushed"
+    ))]
+    DigestMismatch {
+        chart: String,
+        version: String,
+        locked: String,
+        actual: String,
+    },
+}
+
+/// A lockfile pins every chart of a release (and optionally of a stack or
+/// demo) to an exact chart version and digest, so that installs are
+/// reproducible even when charts are re-pushed under the same tag.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lockfile {
+    /// Name of the release the lockfile was created for.
+    pub release: String,
+
+    pub charts: Vec<LockedChart>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockedChart {
+    /// Fully qualified chart reference, e.g.
+    /// `oci://oci.stackable.tech/sdp-charts/airflow-operator`.
+    pub chart: String,
+    pub version: String,
+
+    /// Digest of the OCI manifest or of the packaged chart for index.yaml-based
+    /// repositories.
+    pub digest: String,
+}
+
+impl Lockfile {
+    /// Verifies that all `charts` which are about to be installed match the
+    /// locked chart version and digest.
+    pub fn verify(&self, charts: &[LockedChart]) -> Result<(), VerifyError> {
+        for chart in charts {
+            let locked = self
+                .charts
+                .iter()
+                .find(|locked| locked.chart == chart.chart)
+                .context(ChartNotLockedSnafu {
+                    chart: &chart.chart,
+                    version: &chart.version,
+                })?;
+
+            if locked.version != chart.version {
+                return VersionMismatchSnafu {
+                    chart: &chart.chart,
+                    locked: &locked.version,
+                    requested: &chart.version,
+                }
+                .fail();
+            }
+
+            if locked.digest != chart.digest {
+                return DigestMismatchSnafu {
+                    chart: &chart.chart,
+                    version: &chart.version,
+                    locked: &locked.digest,
+                    actual: &chart.digest,
+                }
+                .fail();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pins all locked charts to their digest, so that the `helm_client`
+    /// refuses to install charts which were re-pushed after the lockfile was
+    /// verified.
+    pub fn pin(&self, helm_client: helm::Client) -> helm::Client {
+        self.charts.iter().fold(helm_client, |helm_client, locked| {
+            helm_client.with_chart_digest(&locked.chart, &locked.version, &locked.digest)
+        })
+    }
+}
+
+/// Resolves charts to their exact version and digest. Index files of
+/// index.yaml-based repositories are only retrieved once.
+#[derive(Debug)]
+pub struct ChartLocker<'a> {
+    auth: &'a RepositoryAuth,
+
+    /// URLs of the Stackable Helm repositories, keyed by repository name,
+    /// which are used for operators with the [`ChartSourceType::Repo`] chart
+    /// source.
+    helm_repo_urls: HashMap<String, String>,
+
+    indexes: HashMap<String, ChartSourceMetadata>,
+}
+
+impl<'a> ChartLocker<'a> {
+    pub fn new(auth: &'a RepositoryAuth, helm_repo_urls: HashMap<String, String>) -> Self {
+        Self {
+            auth,
+            helm_repo_urls,
+            indexes: HashMap::new(),
+        }
+    }
+
+    /// Locks all operators and Helm releases of the install `plan`.
+    #[instrument(skip_all)]
+    pub async fn lock_plan(&mut self, plan: &InstallPlan) -> Result<Vec<LockedChart>, Error> {
+        let mut charts = Vec::new();
+
+        for operator in &plan.operators {
+            let version = Version::parse(&operator.version).context(ParseOperatorVersionSnafu {
+                name: &operator.name,
+                version: &operator.version,
+            })?;
+            let operator_spec =
+                OperatorSpec::new(&operator.name, Some(version)).context(OperatorSpecParseSnafu)?;
+
+            charts.push(
+                self.lock_operator(&operator_spec, &operator.chart_source)
+                    .await?,
+            );
+        }
+
+        for release in &plan.helm_releases {
+            charts.push(
+                self.lock_chart(
+                    &release.chart_repo,
+                    &release.chart_name,
+                    &release.chart_version,
+                )
+                .await?,
+            );
+        }
+
+        Ok(charts)
+    }
+
+    /// Locks the chart of a single operator, which must have a version.
+    pub async fn lock_operator(
+        &mut self,
+        operator: &OperatorSpec,
+        chart_source: &ChartSourceType,
+    ) -> Result<LockedChart, Error> {
+        let version = operator
+            .version
+            .as_ref()
+            .context(MissingOperatorVersionSnafu {
+                name: &operator.name,
+            })?
+            .to_string();
+        let chart_name = operator.helm_name();
+
+        match chart_source {
+            ChartSourceType::OCI(registry) => {
+                self.lock_oci_chart(registry, &chart_name, &version).await
+            }
+            ChartSourceType::Repo => {
+                let repo_name = operator.helm_repo_name();
+                let repo_url = self
+                    .helm_repo_urls
+                    .get(&repo_name)
+                    .cloned()
+                    .context(UnknownHelmRepositorySnafu { repo_name })?;
+
+                self.lock_repo_chart(&repo_url, &chart_name, &version).await
+            }
+            ChartSourceType::Local(path) => UnsupportedLocalChartSnafu {
+                chart: path.join(&chart_name).to_string_lossy(),
+            }
+            .fail(),
+        }
+    }
+
+    /// Locks a chart referenced by its repository URL, e.g. of a Helm chart
+    /// manifest in a stack or demo.
+    pub async fn lock_chart(
+        &mut self,
+        repo_url: &str,
+        chart_name: &str,
+        version: &str,
+    ) -> Result<LockedChart, Error> {
+        let chart = chart_reference(repo_url, chart_name);
+
+        match ChartSourceKind::from_url(repo_url) {
+            ChartSourceKind::Oci => {
+                let registry =
+                    OciRegistry::parse(repo_url, None).context(ParseOciRegistrySnafu { chart })?;
+
+                self.lock_oci_chart(&registry, chart_name, version).await
+            }
+            ChartSourceKind::Repo => self.lock_repo_chart(repo_url, chart_name, version).await,
+            ChartSourceKind::Local => UnsupportedLocalChartSnafu { chart }.fail(),
+        }
+    }
+
+    async fn lock_oci_chart(
+        &self,
+        registry: &OciRegistry,
+        chart_name: &str,
+        version: &str,
+    ) -> Result<LockedChart, Error> {
+        let chart = chart_reference(&registry.chart_repository(), chart_name);
+        debug!(chart, version, "Resolving OCI chart digest");
+
+        let digest = oci::get_manifest_digest(registry, chart_name, version, self.auth)
+            .await
+            .context(ResolveOciDigestSnafu { chart: &chart })?;
+
+        Ok(LockedChart {
+            chart,
+            version: version.to_owned(),
+            digest,
+        })
+    }
+
+    async fn lock_repo_chart(
+        &mut self,
+        repo_url: &str,
+        chart_name: &str,
+        version: &str,
+    ) -> Result<LockedChart, Error> {
+        let chart = chart_reference(repo_url, chart_name);
+        debug!(chart, version, "Resolving Helm repository chart digest");
+
+        // The index file is located relative to the repository URL, which
+        // needs a trailing slash to not replace the last path segment
+        let repo_url = format!("{url}/", url = repo_url.trim_end_matches('/'));
+
+        if !self.indexes.contains_key(&repo_url) {
+            let index = helm::get_helm_index(&repo_url, self.auth)
+                .await
+                .context(RetrieveHelmIndexSnafu { url: &repo_url })?;
+            self.indexes.insert(repo_url.clone(), index);
+        }
+
+        let entry = self.indexes[&repo_url]
+            .entries
+            .get(chart_name)
+            .and_then(|entries| entries.iter().find(|entry| entry.version == version))
+            .context(ChartVersionNotFoundSnafu {
+                chart: &chart,
+                version,
+            })?;
+
+        let digest = entry.digest.clone().context(MissingChartDigestSnafu {
+            chart: &chart,
+            version,
+        })?;
+
+        Ok(LockedChart {
+            chart,
+            version: version.to_owned(),
+            digest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn locked_chart(version: &str, digest: &str) -> LockedChart {
+        LockedChart {
+            chart: "oci://oci.stackable.tech/sdp-charts/airflow-operator".to_owned(),
+            version: version.to_owned(),
+            digest: digest.to_owned(),
+        }
+    }
+
+    #[test]
+    fn verify_lockfile() {
+        let lockfile = Lockfile {
+            release: "25.11".to_owned(),
+            charts: vec![locked_chart("25.11.0", "sha256:aaaa")],
+        };
+
+        assert!(
+            lockfile
+                .verify(&[locked_chart("25.11.0", "sha256:aaaa")])
+                .is_ok()
+        );
+        assert!(matches!(
+            lockfile.verify(&[locked_chart("25.11.1", "sha256:aaaa")]),
+            Err(VerifyError::VersionMismatch { .. })
+        ));
+        assert!(matches!(
+            lockfile.verify(&[locked_chart("25.11.0", "sha256:bbbb")]),
+            Err(VerifyError::DigestMismatch { .. })
+        ));
+        assert!(matches!(
+            Lockfile::default().verify(&[locked_chart("25.11.0", "sha256:aaaa")]),
+            Err(VerifyError::ChartNotLocked { .. })
+        ));
+    }
+}
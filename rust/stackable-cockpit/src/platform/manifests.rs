@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
 use snafu::{ResultExt, Snafu};
 use stackable_operator::kvp::Labels;
@@ -11,7 +11,7 @@ use crate::{
     helm,
     utils::{
         k8s::{self, Client},
-        path::{IntoPathOrUrl, PathOrUrlParseError},
+        path::{IntoPathOrUrl, PathOrUrl, PathOrUrlParseError},
     },
     xfer::{
         self,
@@ -48,6 +48,18 @@ pub enum Error {
         source: helm::Error,
     },
 
+    /// This error indicates that upgrading the Helm release failed and it was
+    /// rolled back to the previous revision.
+    #[snafu(display(
+        "failed to upgrade Helm release {release_name} from {from} to {to}, rolled back to {from} ({error})"
+    ))]
+    HelmReleaseRolledBack {
+        release_name: String,
+        from: String,
+        to: String,
+        error: String,
+    },
+
     /// This error indicates that the Helm wrapper failed to uninstall the Helm
     /// release.
     #[snafu(display("failed to uninstall Helm chart"))]
@@ -56,9 +68,13 @@ pub enum Error {
         source: helm::Error,
     },
 
-    /// This error indicates that the Helm chart source kind is not supported.
-    #[snafu(display("local Helm chart sources are not yet supported (source: {chart_source:?})"))]
-    UnsupportedChartSource { chart_source: String },
+    /// This error indicates that a chart from a local chart source could not
+    /// be resolved.
+    #[snafu(display("failed to resolve local Helm chart {chart_name}"))]
+    ResolveLocalChart {
+        source: helm::Error,
+        chart_name: String,
+    },
 
     /// This error indicates that Helm chart options could not be serialized
     /// into YAML.
@@ -74,9 +90,26 @@ pub enum Error {
     DeployManifest { source: k8s::Error },
 }
 
+/// A manifest which has been fully rendered, but not yet applied to the
+/// cluster. Rendering doesn't require any access to the cluster, which makes
+/// it possible to inspect what a stack or demo will do before it does it.
+#[derive(Clone, Debug)]
+pub enum RenderedManifest {
+    /// A Helm chart with its resolved chart reference and values.
+    HelmChart {
+        chart: helm::Chart,
+        values_yaml: String,
+    },
+
+    /// A plain YAML manifest with all templating applied.
+    PlainYaml { source: String, content: String },
+}
+
 pub trait InstallManifestsExt {
-    // TODO (Techassi): This step shouldn't care about templating the manifests nor fetching them from remote
-    #[instrument(skip_all, fields(%namespace, indicatif.pb_show = true))]
+    /// Renders and afterwards applies the `manifests`. See
+    /// [`InstallManifestsExt::render_manifests`] and
+    /// [`InstallManifestsExt::apply_manifests`] for details.
+    #[instrument(skip_all, fields(%namespace))]
     #[allow(async_fn_in_trait)]
     async fn install_manifests(
         manifests: &[ManifestSpec],
@@ -84,70 +117,52 @@ pub trait InstallManifestsExt {
         namespace: &str,
         labels: Labels,
         client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         debug!("Installing manifests");
 
-        Span::current().pb_set_style(&PROGRESS_BAR_STYLE);
-        Span::current().pb_set_length(manifests.len() as u64);
+        let rendered =
+            Self::render_manifests(manifests, parameters, namespace, transfer_client).await?;
+
+        Self::apply_manifests(&rendered, namespace, labels, client, helm_client).await
+    }
+
+    /// Renders the `manifests` without touching the cluster. Plain YAML
+    /// manifests are rendered through the [`Template`] processor and Helm
+    /// charts are resolved to their target chart and values.
+    #[instrument(skip_all, fields(%namespace))]
+    #[allow(async_fn_in_trait)]
+    async fn render_manifests(
+        manifests: &[ManifestSpec],
+        parameters: &HashMap<String, String>,
+        namespace: &str,
+        transfer_client: &xfer::Client,
+    ) -> Result<Vec<RenderedManifest>, Error> {
+        debug!("Rendering manifests");
 
         let mut parameters = parameters.clone();
         // We need some additional templating capabilities, e.g. the namespace, so that stacks/demos
         // can use that to render e.g. the fqdn service names [which contain the namespace].
         parameters.insert("NAMESPACE".to_owned(), namespace.to_owned());
 
-        for manifest in manifests {
-            let parameters = parameters.clone();
+        let mut rendered = Vec::new();
 
+        for manifest in manifests {
             match manifest {
                 ManifestSpec::HelmChart(helm_file) => {
-                    debug!(helm_file, "Installing manifest from Helm chart");
+                    debug!(helm_file, "Rendering manifest from Helm chart");
 
-                    let helm_chart =
-                        get_helm_chart(helm_file, transfer_client, &parameters).await?;
-
-                    info!(helm_chart.name, helm_chart.version, "Installing Helm chart",);
-
-                    let chart_source = match helm_chart.repo.source_kind() {
-                        helm::ChartSourceKind::Repo => {
-                            helm::add_repo(&helm_chart.repo.name, &helm_chart.repo.url).context(
-                                AddHelmRepositorySnafu {
-                                    repo_name: helm_chart.repo.name.clone(),
-                                },
-                            )?;
-                            &helm_chart.repo.name
-                        }
-                        helm::ChartSourceKind::Oci => &helm_chart.repo.url,
-                        helm::ChartSourceKind::Local => {
-                            return UnsupportedChartSourceSnafu {
-                                chart_source: helm_chart.repo.url.clone(),
-                            }
-                            .fail();
-                        }
-                    };
+                    let chart = get_helm_chart(helm_file, transfer_client, &parameters).await?;
 
                     // Serialize chart options to string
-                    let values_yaml = serde_yaml::to_string(&helm_chart.options)
-                        .context(SerializeOptionsSnafu)?;
+                    let values_yaml =
+                        serde_yaml::to_string(&chart.options).context(SerializeOptionsSnafu)?;
 
-                    // Install the Helm chart using the Helm wrapper
-                    helm::upgrade_or_install_release_from_repo_or_registry(
-                        &helm_chart.release_name,
-                        helm::ChartVersion {
-                            chart_source,
-                            chart_name: &helm_chart.name,
-                            chart_version: Some(&helm_chart.version),
-                        },
-                        Some(&values_yaml),
-                        namespace,
-                        true,
-                    )
-                    .context(InstallHelmReleaseSnafu {
-                        release_name: helm_chart.release_name,
-                    })?;
+                    rendered.push(RenderedManifest::HelmChart { chart, values_yaml });
                 }
                 ManifestSpec::PlainYaml(manifest_file) => {
-                    debug!(manifest_file, "Installing YAML manifest");
+                    debug!(manifest_file, "Rendering YAML manifest");
 
                     // Read YAML manifest and apply templating
                     let path_or_url =
@@ -157,13 +172,123 @@ pub trait InstallManifestsExt {
                                 path_or_url: manifest_file.clone(),
                             })?;
 
-                    let manifests = transfer_client
+                    let content = transfer_client
                         .get(&path_or_url, &Template::new(&parameters))
                         .await
                         .context(FileTransferSnafu)?;
 
+                    rendered.push(RenderedManifest::PlainYaml {
+                        source: manifest_file.clone(),
+                        content,
+                    });
+                }
+            }
+        }
+
+        Ok(rendered)
+    }
+
+    /// Applies previously rendered manifests to the cluster. Helm charts are
+    /// installed (or upgraded) using the Helm wrapper, plain YAML manifests
+    /// are deployed using the kube client.
+    #[instrument(skip_all, fields(%namespace, indicatif.pb_show = true))]
+    #[allow(async_fn_in_trait)]
+    async fn apply_manifests(
+        manifests: &[RenderedManifest],
+        namespace: &str,
+        labels: Labels,
+        client: &Client,
+        helm_client: &helm::Client,
+    ) -> Result<(), Error> {
+        debug!("Applying manifests");
+
+        Span::current().pb_set_style(&PROGRESS_BAR_STYLE);
+        Span::current().pb_set_length(manifests.len() as u64);
+
+        for manifest in manifests {
+            match manifest {
+                RenderedManifest::HelmChart {
+                    chart: helm_chart,
+                    values_yaml,
+                } => {
+                    info!(helm_chart.name, helm_chart.version, "Installing Helm chart",);
+
+                    let (chart_source, chart_name) = match helm_chart.repo.source_kind() {
+                        helm::ChartSourceKind::Repo => {
+                            // Third-party repos are accessed anonymously, the Helm
+                            // wrapper uses the configured credentials of the
+                            // Stackable repos if the URL matches
+                            helm_client
+                                .add_repo(
+                                    &helm_chart.repo.name,
+                                    &helm_chart.repo.url,
+                                    &helm::RepositoryAuth::default(),
+                                )
+                                .await
+                                .context(AddHelmRepositorySnafu {
+                                    repo_name: helm_chart.repo.name.clone(),
+                                })?;
+                            (helm_chart.repo.name.clone(), helm_chart.name.clone())
+                        }
+                        helm::ChartSourceKind::Oci => {
+                            (helm_chart.repo.url.clone(), helm_chart.name.clone())
+                        }
+                        helm::ChartSourceKind::Local => {
+                            // Local charts are referenced by path, see helm::ChartVersion
+                            let chart_path = helm::resolve_local_chart(
+                                Path::new(&helm_chart.repo.url),
+                                &helm_chart.name,
+                                Some(&helm_chart.version),
+                            )
+                            .context(ResolveLocalChartSnafu {
+                                chart_name: helm_chart.name.clone(),
+                            })?;
+
+                            (String::new(), chart_path.to_string_lossy().into_owned())
+                        }
+                    };
+
+                    // Install the Helm chart using the Helm wrapper
+                    let status = helm_client
+                        .upgrade_or_install_release_from_repo_or_registry(
+                            &helm_chart.release_name,
+                            helm::ChartVersion {
+                                chart_source: &chart_source,
+                                chart_name: &chart_name,
+                                chart_version: Some(&helm_chart.version),
+                            },
+                            Some(values_yaml.as_str()),
+                            namespace,
+                            false,
+                        )
+                        .await
+                        .context(InstallHelmReleaseSnafu {
+                            release_name: helm_chart.release_name.clone(),
+                        })?;
+
+                    if let helm::InstallReleaseStatus::RolledBack {
+                        release_name,
+                        from,
+                        to,
+                        error,
+                    } = status
+                    {
+                        return HelmReleaseRolledBackSnafu {
+                            release_name,
+                            from,
+                            to,
+                            error,
+                        }
+                        .fail();
+                    }
+
+                    debug!(%status, "Installed Helm chart");
+                }
+                RenderedManifest::PlainYaml { source, content } => {
+                    debug!(source, "Installing YAML manifest");
+
                     client
-                        .deploy_manifests(&manifests, namespace, labels.clone())
+                        .deploy_manifests(content, namespace, labels.clone())
                         .await
                         .context(DeployManifestSnafu)?;
                 }
@@ -184,6 +309,7 @@ pub trait InstallManifestsExt {
         manifests: &[ManifestSpec],
         parameters: &mut HashMap<String, String>,
         namespace: &str,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         debug!("Uninstalling Helm manifests");
@@ -205,11 +331,12 @@ pub trait InstallManifestsExt {
                         helm_chart.version, "Uninstalling Helm chart",
                     );
 
-                    helm::uninstall_release(&helm_chart.release_name, namespace, true).context(
-                        UninstallHelmReleaseSnafu {
+                    helm_client
+                        .uninstall_release(&helm_chart.release_name, namespace)
+                        .await
+                        .context(UninstallHelmReleaseSnafu {
                             release_name: &helm_chart.release_name,
-                        },
-                    )?;
+                        })?;
                 }
                 ManifestSpec::PlainYaml(_) => {
                     // This function only handles uninstalling Helm Charts
@@ -221,6 +348,9 @@ pub trait InstallManifestsExt {
     }
 }
 
+/// Reads the Helm chart YAML file and applies templating. Relative local chart
+/// sources are resolved relative to the directory of the Helm chart file, if
+/// the file itself is local.
 pub async fn get_helm_chart(
     helm_file: &str,
     transfer_client: &xfer::Client,
@@ -231,7 +361,7 @@ pub async fn get_helm_chart(
         path_or_url: helm_file,
     })?;
 
-    let helmchart = transfer_client
+    let mut helmchart: helm::Chart = transfer_client
         .get(
             &helm_file_location,
             &Template::new(parameters).then(Yaml::default()),
@@ -239,5 +369,20 @@ pub async fn get_helm_chart(
         .await
         .context(FileTransferSnafu)?;
 
+    if let PathOrUrl::Path(helm_file_path) = &helm_file_location {
+        let chart_source = Path::new(&helmchart.repo.url);
+
+        if helmchart.repo.source_kind() == helm::ChartSourceKind::Local
+            && chart_source.is_relative()
+        {
+            if let Some(helm_file_dir) = helm_file_path.parent() {
+                helmchart.repo.url = helm_file_dir
+                    .join(chart_source)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+        }
+    }
+
     Ok(helmchart)
 }
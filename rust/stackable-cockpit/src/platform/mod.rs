@@ -1,9 +1,12 @@
 pub mod cluster;
 pub mod credentials;
 pub mod demo;
+pub mod export;
+pub mod lock;
 pub mod manifests;
 pub mod namespace;
 pub mod operator;
+pub mod plan;
 pub mod product;
 pub mod release;
 pub mod service;
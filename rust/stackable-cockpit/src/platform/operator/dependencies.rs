@@ -0,0 +1,202 @@
+use indexmap::{IndexMap, IndexSet};
+use snafu::Snafu;
+
+use crate::platform::operator::OperatorSpec;
+
+/// Operators every product operator depends on, as the products rely on the
+/// shared CRDs, secrets and listeners provided by them.
+pub const BASE_OPERATORS: &[&str] = &["commons", "secret", "listener"];
+
+#[derive(Debug, Snafu)]
+pub enum DependencyError {
+    #[snafu(display("circular dependency between the operators {operators}", operators = operators.join(", ")))]
+    CircularDependency { operators: Vec<String> },
+}
+
+/// Returns the built-in dependencies of the operator named `operator_name`,
+/// which are used if a release doesn't declare them explicitly.
+pub fn default_dependencies(operator_name: &str) -> Vec<String> {
+    if BASE_OPERATORS.contains(&operator_name) {
+        return Vec::new();
+    }
+
+    BASE_OPERATORS.iter().map(ToString::to_string).collect()
+}
+
+/// Describes which operators need to be installed (and ready) before another
+/// operator can be installed.
+#[derive(Clone, Debug, Default)]
+pub struct DependencyGraph {
+    dependencies: IndexMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    /// Creates a graph from a map of operator names to the names of the
+    /// operators they depend on.
+    pub fn new(dependencies: IndexMap<String, Vec<String>>) -> Self {
+        Self { dependencies }
+    }
+
+    /// Creates a graph using the built-in dependencies of the operators named
+    /// `operator_names`, e.g. all [`KnownOperators`](super::KnownOperators).
+    pub fn builtin<'a>(operator_names: impl IntoIterator<Item = &'a str>) -> Self {
+        Self::new(
+            operator_names
+                .into_iter()
+                .map(|name| (name.to_owned(), default_dependencies(name)))
+                .collect(),
+        )
+    }
+
+    /// Returns the direct dependencies of `operator_name`.
+    pub fn dependencies(&self, operator_name: &str) -> &[String] {
+        self.dependencies
+            .get(operator_name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the `operators` followed by all their (transitive) dependencies
+    /// which are not part of `operators` yet.
+    pub fn resolve(&self, operators: &[String]) -> Vec<String> {
+        let mut resolved: IndexSet<String> = operators.iter().cloned().collect();
+        let mut index = 0;
+
+        while let Some(operator_name) = resolved.get_index(index).cloned() {
+            for dependency in self.dependencies(&operator_name) {
+                resolved.insert(dependency.clone());
+            }
+
+            index += 1;
+        }
+
+        resolved.into_iter().collect()
+    }
+
+    /// Returns the `operators` followed by all their (transitive) dependencies.
+    /// Dependencies use the version of the first operator requiring them, as
+    /// all operators of a release share the same version.
+    pub fn resolve_specs(&self, operators: &[OperatorSpec]) -> Vec<OperatorSpec> {
+        let operator_names: Vec<String> = operators
+            .iter()
+            .map(|operator| operator.name.clone())
+            .collect();
+
+        self.resolve(&operator_names)
+            .into_iter()
+            .map(|name| {
+                if let Some(operator) = operators.iter().find(|operator| operator.name == name) {
+                    return operator.clone();
+                }
+
+                let version = operators
+                    .iter()
+                    .filter(|operator| {
+                        self.resolve(std::slice::from_ref(&operator.name))
+                            .contains(&name)
+                    })
+                    .find_map(|operator| operator.version.clone());
+
+                OperatorSpec { name, version }
+            })
+            .collect()
+    }
+
+    /// Splits `operators` into waves, which need to be installed one after
+    /// another. All operators of a wave only depend on operators of previous
+    /// waves. Dependencies which are not part of `operators` are ignored, as
+    /// they are expected to be installed already.
+    pub fn waves(&self, operators: &[String]) -> Result<Vec<Vec<String>>, DependencyError> {
+        let mut remaining: IndexSet<&String> = operators.iter().collect();
+        let mut waves = Vec::new();
+
+        while !remaining.is_empty() {
+            let wave: Vec<String> = remaining
+                .iter()
+                .filter(|operator_name| {
+                    self.dependencies(operator_name)
+                        .iter()
+                        .all(|dependency| !remaining.contains(dependency))
+                })
+                .map(|operator_name| operator_name.to_string())
+                .collect();
+
+            if wave.is_empty() {
+                return CircularDependencySnafu {
+                    operators: remaining
+                        .iter()
+                        .map(|operator_name| operator_name.to_string())
+                        .collect::<Vec<_>>(),
+                }
+                .fail();
+            }
+
+            remaining.retain(|operator_name| !wave.contains(operator_name));
+            waves.push(wave);
+        }
+
+        Ok(waves)
+    }
+
+    /// Returns all `candidates` which directly depend on `operator_name`.
+    pub fn dependents<'a>(&self, operator_name: &str, candidates: &'a [String]) -> Vec<&'a str> {
+        candidates
+            .iter()
+            .filter(|candidate| {
+                self.dependencies(candidate)
+                    .iter()
+                    .any(|dependency| dependency == operator_name)
+            })
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::platform::operator::VALID_OPERATORS;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn resolve_and_order_dependencies() {
+        let graph = DependencyGraph::builtin(VALID_OPERATORS.iter().copied());
+
+        let resolved = graph.resolve(&names(&["trino", "opa"]));
+        assert_eq!(
+            resolved,
+            names(&["trino", "opa", "commons", "secret", "listener"])
+        );
+
+        let waves = graph.waves(&resolved).unwrap();
+        assert_eq!(
+            waves,
+            vec![
+                names(&["commons", "secret", "listener"]),
+                names(&["trino", "opa"])
+            ]
+        );
+
+        assert_eq!(
+            graph.dependents("secret", &names(&["commons", "trino", "hive"])),
+            vec!["trino", "hive"]
+        );
+    }
+
+    #[test]
+    fn detect_circular_dependencies() {
+        let graph = DependencyGraph::new(IndexMap::from([
+            ("hive".to_owned(), names(&["trino"])),
+            ("trino".to_owned(), names(&["hive"])),
+            ("commons".to_owned(), Vec::new()),
+        ]));
+
+        assert!(matches!(
+            graph.waves(&names(&["commons", "hive", "trino"])),
+            Err(DependencyError::CircularDependency { operators }) if operators == names(&["hive", "trino"])
+        ));
+    }
+}
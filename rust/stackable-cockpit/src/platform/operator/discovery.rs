@@ -0,0 +1,256 @@
+use indexmap::IndexSet;
+use snafu::{ResultExt, Snafu, ensure};
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+use crate::{
+    helm::{self, RepositoryAuth},
+    oci,
+    platform::{
+        operator::{ChartSourceType, OperatorSpec, VALID_OPERATORS},
+        release::ReleaseList,
+    },
+    utils::chartsource::ChartSourceMetadata,
+    xfer,
+};
+
+#[derive(Debug, Snafu)]
+pub enum DiscoveryError {
+    #[snafu(display("failed to retrieve Helm repository index from {url:?}"))]
+    FetchRepoIndex { source: helm::Error, url: String },
+
+    #[snafu(display("failed to parse Helm repository index from {url:?}"))]
+    ParseRepoIndex {
+        source: serde_yaml::Error,
+        url: String,
+    },
+
+    #[snafu(display("failed to retrieve operators from OCI registry {registry:?}"))]
+    FetchOciOperators {
+        source: oci::Error,
+        registry: String,
+    },
+
+    #[snafu(display("unknown operator {name:?}, available operators are: {known}", known = known.join(", ")))]
+    UnknownOperator { name: String, known: Vec<String> },
+}
+
+/// The operators which are known to exist. They are discovered from the loaded
+/// release files and the index of the chart source, so that new operators can
+/// be installed without updating stackablectl. The built-in
+/// [`VALID_OPERATORS`] are used for local chart sources, which operators can't
+/// be discovered from, and if the chart source can't be reached.
+#[derive(Clone, Debug, Default)]
+pub struct KnownOperators {
+    names: IndexSet<String>,
+}
+
+impl KnownOperators {
+    pub fn builtin() -> Self {
+        Self {
+            names: VALID_OPERATORS.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// Returns all operators which are part of at least one release.
+    pub fn from_releases(release_list: &ReleaseList) -> Self {
+        Self {
+            names: release_list
+                .values()
+                .flat_map(|release| release.products.keys().cloned())
+                .collect(),
+        }
+    }
+
+    /// Discovers the operators of the `release_list` and the `chart_source`,
+    /// see [`Self::from_chart_source`]. If the chart source can't be reached,
+    /// e.g. when working offline, the built-in [`VALID_OPERATORS`] are used in
+    /// addition to the operators of the releases. They are also used if no
+    /// operators were found at all, e.g. for local chart sources.
+    #[instrument(skip_all)]
+    pub async fn discover(
+        release_list: Option<&ReleaseList>,
+        chart_source: &ChartSourceType,
+        helm_repo_urls: &[&str],
+        auth: &RepositoryAuth,
+        transfer_client: &xfer::Client,
+    ) -> Self {
+        let mut known_operators = release_list.map(Self::from_releases).unwrap_or_default();
+
+        match Self::from_chart_source(chart_source, helm_repo_urls, auth, transfer_client).await {
+            Ok(discovered) => known_operators.extend(discovered),
+            Err(err) => {
+                warn!(
+                    error = %err,
+                    "Failed to discover operators from the chart source, using built-in operators"
+                );
+                known_operators.extend(Self::builtin());
+            }
+        }
+
+        if known_operators.is_empty() {
+            debug!("No operators discovered, using built-in operators");
+            known_operators.extend(Self::builtin());
+        }
+
+        known_operators
+    }
+
+    /// Returns all operators with a chart in the `chart_source`, which is
+    /// accessed using `auth`. For index.yaml-based repositories, the index
+    /// files at `helm_repo_urls` are used. The index files and the operators
+    /// of OCI registries are cached using the `transfer_client`. Operators
+    /// can't be discovered from local chart sources.
+    #[instrument(skip_all)]
+    pub async fn from_chart_source(
+        chart_source: &ChartSourceType,
+        helm_repo_urls: &[&str],
+        auth: &RepositoryAuth,
+        transfer_client: &xfer::Client,
+    ) -> Result<Self, DiscoveryError> {
+        let mut names = IndexSet::new();
+
+        match chart_source {
+            ChartSourceType::Repo => {
+                for repo_url in helm_repo_urls {
+                    let index_url = helm::helm_index_url(repo_url)
+                        .context(FetchRepoIndexSnafu { url: *repo_url })?;
+
+                    let index_content = match get_cached(transfer_client, &index_url).await {
+                        Some(index_content) => index_content,
+                        None => {
+                            let index_content = helm::get_helm_index_content(repo_url, auth)
+                                .await
+                                .context(FetchRepoIndexSnafu { url: *repo_url })?;
+
+                            store_cached(transfer_client, &index_url, &index_content).await;
+                            index_content
+                        }
+                    };
+
+                    let index: ChartSourceMetadata = serde_yaml::from_str(&index_content)
+                        .context(ParseRepoIndexSnafu { url: *repo_url })?;
+
+                    names.extend(
+                        index
+                            .entries
+                            .keys()
+                            .filter_map(|chart_name| chart_name.strip_suffix("-operator"))
+                            .map(ToOwned::to_owned),
+                    );
+                }
+            }
+            ChartSourceType::OCI(registry) => {
+                let context = || FetchOciOperatorsSnafu {
+                    registry: registry.chart_repository(),
+                };
+                let catalog_url = registry.catalog_url().with_context(|_| context())?;
+
+                // The catalog can span multiple pages, so the operators found in
+                // it are cached instead, one per line
+                match get_cached(transfer_client, &catalog_url).await {
+                    Some(operators) => names.extend(operators.lines().map(ToOwned::to_owned)),
+                    None => {
+                        let operators = oci::get_operators(registry, auth)
+                            .await
+                            .with_context(|_| context())?;
+
+                        store_cached(transfer_client, &catalog_url, &operators.join("\n")).await;
+                        names.extend(operators);
+                    }
+                }
+            }
+            ChartSourceType::Local(_) => {}
+        }
+
+        debug!(
+            count = names.len(),
+            "Discovered operators from chart source"
+        );
+        Ok(Self { names })
+    }
+
+    /// Adds the operators of `other`.
+    pub fn extend(&mut self, other: Self) {
+        self.names.extend(other.names);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Returns an error if the `operator` is not known.
+    pub fn validate(&self, operator: &OperatorSpec) -> Result<(), DiscoveryError> {
+        ensure!(
+            self.contains(&operator.name),
+            UnknownOperatorSnafu {
+                name: &operator.name,
+                known: self.names.iter().cloned().collect::<Vec<_>>(),
+            }
+        );
+
+        Ok(())
+    }
+}
+
+/// Returns the content cached for `url`. Failing to read the cache is not
+/// fatal, the content is retrieved from the chart source instead.
+async fn get_cached(transfer_client: &xfer::Client, url: &Url) -> Option<String> {
+    transfer_client
+        .get_cached(url)
+        .await
+        .inspect_err(|err| debug!(error = %err, %url, "Failed to read cached content"))
+        .ok()
+        .flatten()
+}
+
+async fn store_cached(transfer_client: &xfer::Client, url: &Url, content: &str) {
+    if let Err(err) = transfer_client.store_cached(url, content).await {
+        debug!(error = %err, %url, "Failed to cache content");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{oci::OciRegistry, xfer::cache::Settings};
+
+    #[rstest]
+    #[case(ChartSourceType::Repo)]
+    #[case(ChartSourceType::OCI(OciRegistry {
+        plain_http: true,
+        ..OciRegistry::parse("127.0.0.1:1/sdp-charts", None).unwrap()
+    }))]
+    #[tokio::test]
+    async fn unreachable_chart_source_uses_builtin_operators(
+        #[case] chart_source: ChartSourceType,
+    ) {
+        let transfer_client = xfer::Client::new(Settings::disabled()).await.unwrap();
+
+        // Nothing listens on port 1, so the connection is refused
+        let known_operators = KnownOperators::discover(
+            None,
+            &chart_source,
+            &["http://127.0.0.1:1/"],
+            &RepositoryAuth::default(),
+            &transfer_client,
+        )
+        .await;
+
+        assert!(
+            VALID_OPERATORS
+                .iter()
+                .all(|operator| known_operators.contains(operator))
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use serde::Serialize;
+use snafu::{ResultExt, Snafu};
+use stackable_operator::{
+    k8s_openapi::{
+        api::core::v1::Pod,
+        apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+    },
+    kube::{ResourceExt, api::ListParams},
+};
+use tracing::{debug, instrument};
+
+use crate::{
+    helm,
+    platform::release::ReleaseList,
+    utils::k8s::{self, ListParamsExt},
+};
+
+/// Helm sets this annotation on all objects it installs, including CRDs which
+/// are part of the chart templates.
+const HELM_RELEASE_NAME_ANNOTATION: &str = "meta.helm.sh/release-name";
+
+#[derive(Debug, Snafu)]
+pub enum HealthError {
+    #[snafu(display("failed to list workloads of {release_name:?}"))]
+    ListWorkloads {
+        #[snafu(source(from(k8s::Error, Box::new)))]
+        source: Box<k8s::Error>,
+        release_name: String,
+    },
+
+    #[snafu(display("failed to list pods of {release_name:?}"))]
+    ListPods {
+        #[snafu(source(from(k8s::Error, Box::new)))]
+        source: Box<k8s::Error>,
+        release_name: String,
+    },
+}
+
+/// Health of an installed operator, which is derived from the readiness of its
+/// Deployments and DaemonSets, its pods and its CRDs.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorHealth {
+    pub ready_replicas: i32,
+    pub desired_replicas: i32,
+
+    /// Sum of the restart counts of all operator containers.
+    pub restarts: i32,
+
+    /// Image tags of the operator containers, e.g. `25.3.0`.
+    pub image_tags: Vec<String>,
+
+    /// Name of the release the installed chart version belongs to.
+    pub release: Option<String>,
+
+    pub established_crds: usize,
+    pub total_crds: usize,
+
+    /// Whether the Helm release is deployed, all replicas are ready and all
+    /// CRDs are established.
+    pub healthy: bool,
+}
+
+impl OperatorHealth {
+    /// Collects the health of the operator named `operator_name`, which was
+    /// installed as `helm_release`. The workloads and pods are matched by the
+    /// `app.kubernetes.io/instance` label Helm charts set to the release name.
+    /// The `crds` of the cluster are passed in, so that they only need to be
+    /// listed once for all operators.
+    #[instrument(skip_all, fields(%operator_name))]
+    pub async fn collect(
+        operator_name: &str,
+        helm_release: &helm::Release,
+        release_list: Option<&ReleaseList>,
+        crds: &[CustomResourceDefinition],
+        client: &k8s::Client,
+    ) -> Result<Self, HealthError> {
+        debug!("Collecting operator health");
+
+        let namespace = Some(helm_release.namespace.as_str());
+        let mut list_params = ListParams::default();
+        list_params.add_label(format!("app.kubernetes.io/instance={}", helm_release.name));
+
+        let mut ready_replicas = 0;
+        let mut desired_replicas = 0;
+
+        for deployment in client
+            .list_deployments(namespace, &list_params)
+            .await
+            .context(ListWorkloadsSnafu {
+                release_name: &helm_release.name,
+            })?
+        {
+            let status = deployment.status.unwrap_or_default();
+            ready_replicas += status.ready_replicas.unwrap_or_default();
+            desired_replicas += deployment.spec.and_then(|spec| spec.replicas).unwrap_or(1);
+        }
+
+        // Some operators, e.g. the secret and listener operator, run parts of
+        // them as DaemonSets
+        for daemon_set in client
+            .list_daemon_sets(namespace, &list_params)
+            .await
+            .context(ListWorkloadsSnafu {
+                release_name: &helm_release.name,
+            })?
+        {
+            let status = daemon_set.status.unwrap_or_default();
+            ready_replicas += status.number_ready;
+            desired_replicas += status.desired_number_scheduled;
+        }
+
+        let pods = client
+            .list_pods(namespace, &list_params)
+            .await
+            .context(ListPodsSnafu {
+                release_name: &helm_release.name,
+            })?;
+
+        let operator_crds: Vec<_> = crds
+            .iter()
+            .filter(|crd| is_operator_crd(crd, operator_name, &helm_release.name))
+            .collect();
+        let established_crds = operator_crds
+            .iter()
+            .filter(|crd| is_established(crd))
+            .count();
+
+        let release = release_list.and_then(|release_list| {
+            release_list
+                .iter()
+                .find(|(_, release)| {
+                    release
+                        .products
+                        .get(operator_name)
+                        .is_some_and(|product| product.version.to_string() == helm_release.version)
+                })
+                .map(|(release_name, _)| release_name.clone())
+        });
+
+        let healthy = helm_release.status == "deployed"
+            && desired_replicas > 0
+            && ready_replicas >= desired_replicas
+            && established_crds == operator_crds.len();
+
+        Ok(Self {
+            ready_replicas,
+            desired_replicas,
+            restarts: restarts(&pods.items),
+            image_tags: image_tags(&pods.items),
+            release,
+            established_crds,
+            total_crds: operator_crds.len(),
+            healthy,
+        })
+    }
+}
+
+fn restarts(pods: &[Pod]) -> i32 {
+    pods.iter()
+        .filter_map(|pod| pod.status.as_ref()?.container_statuses.as_ref())
+        .flatten()
+        .map(|container_status| container_status.restart_count)
+        .sum()
+}
+
+fn image_tags(pods: &[Pod]) -> Vec<String> {
+    let mut tags: Vec<String> = pods
+        .iter()
+        .filter_map(|pod| pod.spec.as_ref())
+        .flat_map(|spec| &spec.containers)
+        .filter_map(|container| container.image.as_deref().and_then(image_tag))
+        .map(ToOwned::to_owned)
+        .collect();
+
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Returns the tag of an image reference, e.g. `25.3.0` for
+/// `oci.stackable.tech/sdp/airflow-operator:25.3.0`. Digests are ignored.
+fn image_tag(image: &str) -> Option<&str> {
+    let image = image.split('@').next()?;
+    let (name, tag) = image.rsplit_once(':')?;
+
+    // The colon belongs to the registry port if the tag contains a slash
+    (!tag.contains('/') && !name.is_empty()).then_some(tag)
+}
+
+/// CRDs belong to an operator if they were installed by its Helm release or
+/// their group is named after the operator, e.g. `airflow.stackable.tech` or
+/// `secrets.stackable.tech`. Operators which maintain their CRDs themselves
+/// don't set the Helm annotation.
+fn is_operator_crd(
+    crd: &CustomResourceDefinition,
+    operator_name: &str,
+    release_name: &str,
+) -> bool {
+    if crd
+        .annotations()
+        .get(HELM_RELEASE_NAME_ANNOTATION)
+        .is_some_and(|name| name == release_name)
+    {
+        return true;
+    }
+
+    let Some(group_name) = crd.spec.group.strip_suffix(".stackable.tech") else {
+        return false;
+    };
+    let operator_name = operator_name.trim_end_matches("-k8s");
+
+    group_name == operator_name || group_name == format!("{operator_name}s")
+}
+
+fn is_established(crd: &CustomResourceDefinition) -> bool {
+    crd.status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .is_some_and(|conditions| {
+            conditions
+                .iter()
+                .any(|condition| condition.type_ == "Established" && condition.status == "True")
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("oci.stackable.tech/sdp/airflow-operator:25.3.0", Some("25.3.0"))]
+    #[case("localhost:5000/sdp/airflow-operator:0.0.0-dev", Some("0.0.0-dev"))]
+    #[case("localhost:5000/sdp/airflow-operator", None)]
+    #[case("airflow-operator:25.3.0@sha256:abc", Some("25.3.0"))]
+    fn parse_image_tag(#[case] image: &str, #[case] tag: Option<&str>) {
+        assert_eq!(image_tag(image), tag);
+    }
+}
@@ -0,0 +1,108 @@
+use std::{future, io, str::FromStr};
+
+use futures::{Stream, TryStreamExt as _};
+use tracing::Level;
+
+/// Returns the level of a JSON formatted operator log line, e.g.
+/// `{"timestamp":"...","level":"INFO","fields":{...}}`. Returns [`None`] if
+/// the line isn't JSON or doesn't contain a valid level.
+pub fn json_log_level(line: &str) -> Option<Level> {
+    let line: serde_json::Value = serde_json::from_str(line).ok()?;
+    Level::from_str(line.get("level")?.as_str()?).ok()
+}
+
+/// Returns if a log `line` should be shown when only lines with `level` or a
+/// more severe level are requested. Lines without a level (e.g. plain text
+/// lines) are always shown.
+pub fn matches_log_level(line: &str, level: Level) -> bool {
+    json_log_level(line).is_none_or(|line_level| line_level <= level)
+}
+
+/// Filters the log `lines` of a single container by `level` (see
+/// [`matches_log_level`]) and prefixes the remaining lines with `prefix`, e.g.
+/// `[pod/container] `. The lines are filtered before they are prefixed, as
+/// prefixed lines can't be parsed as JSON anymore.
+pub fn filter_and_prefix_log_lines<S>(
+    lines: S,
+    level: Option<Level>,
+    prefix: String,
+) -> impl Stream<Item = io::Result<String>>
+where
+    S: Stream<Item = io::Result<String>>,
+{
+    lines
+        .try_filter(move |line| {
+            future::ready(level.is_none_or(|level| matches_log_level(line, level)))
+        })
+        .map_ok(move |line| format!("{prefix}{line}"))
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt as _;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(
+        r#"{"level":"ERROR","fields":{"message":"failed"}}"#,
+        Level::WARN,
+        true
+    )]
+    #[case(
+        r#"{"level":"INFO","fields":{"message":"reconciled"}}"#,
+        Level::INFO,
+        true
+    )]
+    #[case(
+        r#"{"level":"DEBUG","fields":{"message":"details"}}"#,
+        Level::INFO,
+        false
+    )]
+    #[case("2025-03-01T12:00:00Z DEBUG plain text line", Level::ERROR, true)]
+    fn filter_log_lines(#[case] line: &str, #[case] level: Level, #[case] matches: bool) {
+        assert_eq!(matches_log_level(line, level), matches);
+    }
+
+    #[tokio::test]
+    async fn filter_prefixed_log_lines() {
+        let container_lines = |lines: &[&str]| {
+            futures::stream::iter(
+                lines
+                    .iter()
+                    .map(|line| Ok(line.to_string()))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let operator = filter_and_prefix_log_lines(
+            container_lines(&[
+                r#"{"level":"DEBUG","fields":{"message":"details"}}"#,
+                r#"{"level":"ERROR","fields":{"message":"failed"}}"#,
+            ]),
+            Some(Level::INFO),
+            "[secret-operator/secret-operator] ".to_owned(),
+        );
+        let driver = filter_and_prefix_log_lines(
+            container_lines(&[
+                r#"{"level":"INFO","fields":{"message":"published"}}"#,
+                "plain text line",
+            ]),
+            Some(Level::INFO),
+            "[secret-operator/node-driver] ".to_owned(),
+        );
+
+        let mut lines: Vec<String> = operator.chain(driver).try_collect().await.unwrap();
+        lines.sort();
+
+        assert_eq!(
+            lines,
+            vec![
+                "[secret-operator/node-driver] plain text line",
+                r#"[secret-operator/node-driver] {"level":"INFO","fields":{"message":"published"}}"#,
+                r#"[secret-operator/secret-operator] {"level":"ERROR","fields":{"message":"failed"}}"#,
+            ]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, path::PathBuf, str::FromStr};
 
 use listener_operator::LISTENER_CLASS_PRESET;
 use semver::Version;
@@ -9,15 +9,29 @@ use tracing::{Span, info, instrument};
 use tracing_indicatif::{indicatif_println, span_ext::IndicatifSpanExt};
 
 use crate::{
-    constants::{
-        HELM_OCI_REGISTRY, HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST,
-    },
+    constants::{HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST},
     helm,
+    oci::OciRegistry,
     utils::operator_chart_name,
 };
 
+mod dependencies;
+mod discovery;
+mod health;
 pub mod listener_operator;
-
+mod logs;
+mod upgrade;
+mod values;
+
+pub use dependencies::*;
+pub use discovery::*;
+pub use health::*;
+pub use logs::*;
+pub use upgrade::*;
+pub use values::*;
+
+/// Built-in list of operators, which is only used if the known operators can't
+/// be discovered, e.g. for local chart sources. See [`KnownOperators`].
 pub const VALID_OPERATORS: &[&str] = &[
     "airflow",
     "commons",
@@ -53,9 +67,7 @@ pub enum SpecParseError {
     EmptyInput,
 
     #[snafu(display(
-        "invalid operator name {name:?}. \
-        It could be the case that this version of stackablectl is too old to know about this particular operator, \
-        in which case you should update it."
+        "invalid operator name {name:?}, only lowercase alphanumeric characters and '-' are allowed"
     ))]
     InvalidName { name: String },
 }
@@ -100,11 +112,9 @@ impl FromStr for OperatorSpec {
         // because of invalid spec format
         ensure!(len <= 2, InvalidEqualSignCountSnafu);
 
-        // Check if the provided operator name is in the list of valid operators
-        ensure!(
-            VALID_OPERATORS.contains(&parts[0]),
-            InvalidNameSnafu { name: parts[0] }
-        );
+        // Only the syntax of the name is checked here, as the known operators
+        // need to be discovered first, see KnownOperators
+        ensure!(is_valid_name(parts[0]), InvalidNameSnafu { name: parts[0] });
 
         // If there is only one part, the input didn't include
         // the optional version identifier
@@ -151,11 +161,7 @@ impl OperatorSpec {
     {
         let name = name.as_ref();
 
-        if !VALID_OPERATORS.contains(&name) {
-            return Err(SpecParseError::InvalidName {
-                name: name.to_string(),
-            });
-        }
+        ensure!(is_valid_name(name), InvalidNameSnafu { name });
 
         Ok(Self {
             name: name.to_string(),
@@ -187,7 +193,25 @@ impl OperatorSpec {
         .into()
     }
 
-    /// Installs the operator using Helm.
+    /// Returns the Helm values the operator is installed with, which are the
+    /// user-provided `values` plus values set by stackablectl itself, like the
+    /// listener class preset of the listener-operator.
+    pub fn helm_values(&self, values: &Mapping) -> Mapping {
+        let mut helm_values = values.clone();
+        if self.name == "listener" {
+            let preset = LISTENER_CLASS_PRESET
+                .get()
+                .expect("LISTENER_CLASS_PRESET must have been set")
+                .as_helm_value();
+            helm_values.insert(Value::String("preset".to_string()), preset);
+        }
+
+        helm_values
+    }
+
+    /// Installs the operator using Helm. If `force` is set, an operator which
+    /// is already installed in another version is upgraded in place instead,
+    /// replacing conflicting resources.
     #[instrument(skip_all, fields(
         %namespace,
         name = %self.name,
@@ -197,67 +221,142 @@ impl OperatorSpec {
         version = self.version.as_ref().map(tracing::field::display),
         indicatif.pb_show = true
     ))]
-    pub fn install(
+    pub async fn install(
         &self,
         namespace: &str,
         chart_source: &ChartSourceType,
         values: &Mapping,
+        force: bool,
+        helm_client: &helm::Client,
     ) -> Result<(), helm::Error> {
         info!(operator = %self, "Installing operator");
         Span::current()
             .pb_set_message(format!("Installing {name}-operator", name = self.name).as_str());
 
+        let release_name = self.helm_name();
+        let version = self.version.as_ref().map(|v| v.to_string());
+        let (chart_source, chart_name) = self.chart(chart_source)?;
+        let helm_values_yaml = self.helm_values_yaml(values);
+        let chart = helm::ChartVersion {
+            chart_version: version.as_deref(),
+            chart_name: &chart_name,
+            chart_source: &chart_source,
+        };
+
+        if !force {
+            // Install using Helm
+            helm_client
+                .install_release_from_repo_or_registry(
+                    &release_name,
+                    chart,
+                    helm_values_yaml.as_deref(),
+                    namespace,
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let status = helm_client
+            .upgrade_or_install_release_from_repo_or_registry(
+                &release_name,
+                chart,
+                helm_values_yaml.as_deref(),
+                namespace,
+                true,
+            )
+            .await?;
+
+        if let helm::InstallReleaseStatus::RolledBack { error, .. } = status {
+            return Err(helm::Error::UpgradeRelease {
+                source: helm::InstallReleaseError::RolledBack {
+                    name: release_name,
+                    upgrade_error: error,
+                },
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Renders the chart of the operator without installing it and returns
+    /// the resulting manifests, including the CRDs shipped by the chart.
+    #[instrument(skip_all, fields(
+        %namespace,
+        name = %self.name,
+        version = self.version.as_ref().map(tracing::field::display),
+    ))]
+    pub async fn template(
+        &self,
+        namespace: &str,
+        chart_source: &ChartSourceType,
+        values: &Mapping,
+        helm_client: &helm::Client,
+    ) -> Result<String, helm::Error> {
+        let version = self.version.as_ref().map(|v| v.to_string());
+        let (chart_source, chart_name) = self.chart(chart_source)?;
+        let helm_values_yaml = self.helm_values_yaml(values);
+
+        helm_client
+            .template_release(
+                &self.helm_name(),
+                helm::ChartVersion {
+                    chart_version: version.as_deref(),
+                    chart_name: &chart_name,
+                    chart_source: &chart_source,
+                },
+                helm_values_yaml.as_deref(),
+                namespace,
+            )
+            .await
+    }
+
+    /// Returns the chart source and chart name used by Helm for the provided
+    /// `chart_source`.
+    fn chart(&self, chart_source: &ChartSourceType) -> Result<(String, String), helm::Error> {
         let version = self.version.as_ref().map(|v| v.to_string());
         let helm_name = self.helm_name();
 
         // we can't resolve this any earlier as, for the repository case,
         // this will be dependent on the operator version.
-        let chart_source = match chart_source {
-            ChartSourceType::OCI => HELM_OCI_REGISTRY.to_string(),
-            ChartSourceType::Repo => self.helm_repo_name(),
-        };
+        Ok(match chart_source {
+            ChartSourceType::OCI(registry) => (registry.chart_repository(), helm_name),
+            ChartSourceType::Repo => (self.helm_repo_name(), helm_name),
+            ChartSourceType::Local(path) => {
+                // Local charts are referenced by path, see helm::ChartVersion
+                let chart_path = helm::resolve_local_chart(path, &helm_name, version.as_deref())?;
+                (String::new(), chart_path.to_string_lossy().into_owned())
+            }
+        })
+    }
 
-        let mut helm_values = values.clone();
-        if self.name == "listener" {
-            let preset = LISTENER_CLASS_PRESET
-                .get()
-                .expect("LISTENER_CLASS_PRESET must have been set")
-                .as_helm_value();
-            helm_values.insert(Value::String("preset".to_string()), preset);
-        }
+    fn helm_values_yaml(&self, values: &Mapping) -> Option<String> {
+        let helm_values = self.helm_values(values);
 
-        let helm_values_yaml = if helm_values.is_empty() {
+        if helm_values.is_empty() {
             None
         } else {
             Some(
                 serde_yaml::to_string(&helm_values)
                     .expect("serializing a small YAML Mapping back to a YAML string can't fail"),
             )
-        };
-
-        // Install using Helm
-        helm::install_release_from_repo_or_registry(
-            &helm_name,
-            helm::ChartVersion {
-                chart_version: version.as_deref(),
-                chart_name: &helm_name,
-                chart_source: &chart_source,
-            },
-            helm_values_yaml.as_deref(),
-            namespace,
-            true,
-        )?;
-
-        Ok(())
+        }
     }
 
     /// Uninstalls the operator using Helm.
     #[instrument(skip_all, fields(%namespace))]
-    pub fn uninstall<T>(&self, namespace: T) -> Result<(), helm::Error>
+    pub async fn uninstall<T>(
+        &self,
+        namespace: T,
+        helm_client: &helm::Client,
+    ) -> Result<(), helm::Error>
     where
         T: AsRef<str> + std::fmt::Display + std::fmt::Debug,
     {
-        match helm::uninstall_release(&self.helm_name(), namespace.as_ref(), true) {
+        match helm_client
+            .uninstall_release(&self.helm_name(), namespace.as_ref())
+            .await
+        {
             Ok(status) => {
                 indicatif_println!("{status}");
                 Ok(())
@@ -267,14 +366,28 @@ impl OperatorSpec {
     }
 }
 
+/// Operator names are used as part of Helm release and chart names, so they
+/// need to be valid DNS labels.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ChartSourceType {
     /// OCI registry
-    OCI,
+    OCI(OciRegistry),
 
     /// index.yaml-based repositories: resolution (dev, test, stable) is based on the version and thus may be operator-specific
     Repo,
+
+    /// Local directory containing chart directories or packaged charts (`.tgz`)
+    Local(PathBuf),
 }
 
 #[cfg(test)]
@@ -333,6 +446,19 @@ mod test {
         }
     }
 
+    #[rstest]
+    #[case("new-operator-name", true)]
+    #[case("spark-k8s=1.2.3", true)]
+    #[case("Airflow", false)]
+    #[case("-airflow", false)]
+    #[case("air flow", false)]
+    fn operator_spec_name(#[case] input: &str, #[case] valid: bool) {
+        match OperatorSpec::try_from(input) {
+            Ok(spec) => assert!(valid, "SHOULD FAIL: {spec}"),
+            Err(err) => assert!(!valid && matches!(err, SpecParseError::InvalidName { .. })),
+        }
+    }
+
     #[rstest]
     #[case("airflow=0.0.0-nightly", HELM_REPO_NAME_DEV)]
     #[case("airflow=0.0.0-pr123", HELM_REPO_NAME_TEST)]
@@ -0,0 +1,500 @@
+use semver::Version;
+use serde::Deserialize;
+use serde_yaml::Mapping;
+use snafu::{OptionExt, ResultExt, Snafu, ensure};
+use stackable_operator::{
+    k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+    kube::{ResourceExt, core::ObjectList},
+};
+use tracing::{Span, debug, info, instrument, warn};
+use tracing_indicatif::span_ext::IndicatifSpanExt as _;
+
+use crate::{
+    helm::{self, InstallReleaseStatus},
+    platform::{
+        operator::{ChartSourceType, OperatorSpec},
+        release::{self, CrdStatus, resolve_operator_crds},
+    },
+    utils::{k8s, yaml::merge_values},
+    xfer,
+};
+
+#[derive(Debug, Snafu)]
+pub enum OperatorUpgradeError {
+    #[snafu(display("no version specified to upgrade {operator}-operator to"))]
+    MissingVersion { operator: String },
+
+    #[snafu(display("failed to retrieve Helm release {release_name:?}"))]
+    GetRelease {
+        source: helm::Error,
+        release_name: String,
+    },
+
+    #[snafu(display("{release_name} is not installed in namespace {namespace:?}"))]
+    NotInstalled {
+        release_name: String,
+        namespace: String,
+    },
+
+    #[snafu(display("failed to retrieve values of Helm release {release_name:?}"))]
+    ReleaseValues {
+        source: helm::Error,
+        release_name: String,
+    },
+
+    #[snafu(display("failed to resolve CRDs of {operator}-operator"))]
+    ResolveCrds {
+        #[snafu(source(from(release::Error, Box::new)))]
+        source: Box<release::Error>,
+        operator: String,
+    },
+
+    #[snafu(display("failed to deserialize CRDs of {operator}-operator"))]
+    DeserializeCrds {
+        source: serde_yaml::Error,
+        operator: String,
+    },
+
+    #[snafu(display("failed to list installed CRDs"))]
+    ListCrds {
+        #[snafu(source(from(k8s::Error, Box::new)))]
+        source: Box<k8s::Error>,
+    },
+
+    #[snafu(display(
+        "refusing to downgrade {operator}-operator from {from} to {to}, because the storage version of CRD {crd} would change from {current} to {target}"
+    ))]
+    StorageVersionDowngrade {
+        operator: String,
+        from: String,
+        to: String,
+        crd: String,
+        current: String,
+        target: String,
+    },
+
+    #[snafu(display(
+        "refusing to upgrade {operator}-operator from {from} to {to}, because it can't be determined if this is a downgrade, which changes the storage version of its CRDs (use --force-downgrade to upgrade anyway)"
+    ))]
+    UnknownUpgradeDirection {
+        operator: String,
+        from: String,
+        to: String,
+    },
+
+    #[snafu(display(
+        "refusing to downgrade {operator}-operator from {from} to {to}, because the storage version of CRD {crd} can't be determined (use --force-downgrade to downgrade anyway)"
+    ))]
+    UnknownStorageVersion {
+        operator: String,
+        from: String,
+        to: String,
+        crd: String,
+    },
+
+    #[snafu(display(
+        "refusing to downgrade {operator}-operator from {from} to {to}, because the operator maintains its CRDs itself, so their storage versions can't be checked (use --force-downgrade to downgrade anyway)"
+    ))]
+    OperatorManagedCrdsDowngrade {
+        operator: String,
+        from: String,
+        to: String,
+    },
+
+    #[snafu(display("failed to serialize installed CRDs of {operator}-operator"))]
+    SerializeCrds {
+        source: serde_yaml::Error,
+        operator: String,
+    },
+
+    #[snafu(display("failed to replace CRDs of {operator}-operator"))]
+    ReplaceCrds {
+        #[snafu(source(from(k8s::Error, Box::new)))]
+        source: Box<k8s::Error>,
+        operator: String,
+    },
+
+    #[snafu(display("failed to upgrade Helm release {release_name:?}"))]
+    UpgradeRelease {
+        source: helm::Error,
+        release_name: String,
+    },
+
+    #[snafu(display(
+        "failed to restore the previous CRDs of {operator}-operator after the upgrade failed ({upgrade_error})"
+    ))]
+    RestoreCrds {
+        #[snafu(source(from(k8s::Error, Box::new)))]
+        source: Box<k8s::Error>,
+        operator: String,
+        upgrade_error: String,
+    },
+}
+
+#[derive(Debug)]
+pub struct OperatorUpgradeParameters {
+    pub operator_namespace: String,
+    pub chart_source: ChartSourceType,
+
+    /// Helm values of the operator, which are merged into the values supplied
+    /// when the operator was installed.
+    pub operator_values: Mapping,
+
+    /// See [`release::CrdUpgradeParameters::github_fallback`].
+    pub crd_github_fallback: bool,
+
+    /// Downgrade the operator even if the storage version of its CRDs changes
+    /// or can't be checked.
+    pub force_downgrade: bool,
+
+    /// Replace conflicting resources (delete and re-create them) instead of
+    /// patching them when upgrading the Helm release.
+    pub force: bool,
+}
+
+#[derive(Debug)]
+pub struct OperatorUpgradeReport {
+    pub status: InstallReleaseStatus,
+    pub crds: CrdStatus,
+}
+
+/// Whether the target version of an upgrade is newer than the installed one.
+#[derive(Debug, PartialEq, Eq)]
+enum UpgradeDirection {
+    Upgrade,
+    Downgrade,
+
+    /// The installed version can't be parsed, so the versions can't be
+    /// compared.
+    Unknown,
+}
+
+impl UpgradeDirection {
+    /// Dev and PR builds (like `0.0.0-dev` or `0.0.0-pr123`) are built from
+    /// the main branch or a pull request and are considered newer than any
+    /// release, which is why upgrading to them is never a downgrade.
+    fn of(current_version: &str, target_version: &Version) -> Self {
+        if is_dev_build(target_version) {
+            return Self::Upgrade;
+        }
+
+        match Version::parse(current_version) {
+            Ok(current_version) if is_dev_build(&current_version) => Self::Downgrade,
+            Ok(current_version) if *target_version < current_version => Self::Downgrade,
+            Ok(_) => Self::Upgrade,
+            Err(_) => Self::Unknown,
+        }
+    }
+}
+
+impl OperatorSpec {
+    /// Upgrades (or downgrades) the installed operator in place to the version
+    /// of this spec. The CRDs are replaced before the Helm release is upgraded,
+    /// so that the new operator version finds the CRDs it expects. If the
+    /// upgrade fails or is rolled back, the previous CRDs are restored. Values
+    /// supplied when the operator was installed are preserved.
+    #[instrument(skip_all, fields(
+        name = %self.name,
+        version = self.version.as_ref().map(tracing::field::display),
+        indicatif.pb_show = true
+    ))]
+    pub async fn upgrade(
+        &self,
+        parameters: &OperatorUpgradeParameters,
+        k8s_client: &k8s::Client,
+        helm_client: &helm::Client,
+        transfer_client: &xfer::Client,
+    ) -> Result<OperatorUpgradeReport, OperatorUpgradeError> {
+        info!(operator = %self, "Upgrading operator");
+        Span::current()
+            .pb_set_message(format!("Upgrading {name}-operator", name = self.name).as_str());
+
+        let namespace = &parameters.operator_namespace;
+        let release_name = self.helm_name();
+        let target_version = self.version.as_ref().context(MissingVersionSnafu {
+            operator: &self.name,
+        })?;
+
+        let release = helm_client
+            .get_release(&release_name, namespace)
+            .await
+            .context(GetReleaseSnafu {
+                release_name: &release_name,
+            })?
+            .context(NotInstalledSnafu {
+                release_name: &release_name,
+                namespace,
+            })?;
+
+        // Previously supplied values are kept, new values take precedence
+        let mut values = helm_client
+            .release_values(&release_name, namespace)
+            .await
+            .context(ReleaseValuesSnafu {
+                release_name: &release_name,
+            })?;
+        merge_values(&mut values, &parameters.operator_values);
+
+        let crds = resolve_operator_crds(
+            self,
+            namespace,
+            &parameters.chart_source,
+            &values,
+            parameters.crd_github_fallback,
+            helm_client,
+            transfer_client,
+        )
+        .await
+        .context(ResolveCrdsSnafu {
+            operator: &self.name,
+        })?;
+
+        let direction = UpgradeDirection::of(&release.version, target_version);
+        debug!(
+            ?direction,
+            from = release.version,
+            "Determined upgrade direction"
+        );
+
+        let mut crd_snapshot = None;
+
+        match &crds.status {
+            CrdStatus::Replace { names, .. } => {
+                let installed_crds = k8s_client.list_crds().await.context(ListCrdsSnafu)?;
+
+                if direction != UpgradeDirection::Upgrade && !parameters.force_downgrade {
+                    self.check_storage_versions(
+                        &release.version,
+                        &crds.manifests,
+                        &installed_crds,
+                    )?;
+                }
+
+                crd_snapshot = Some(self.snapshot_crds(&installed_crds, names)?);
+
+                k8s_client
+                    .replace_crds(&crds.manifests)
+                    .await
+                    .context(ReplaceCrdsSnafu {
+                        operator: &self.name,
+                    })?;
+            }
+            CrdStatus::OperatorManaged => {
+                ensure!(
+                    direction == UpgradeDirection::Upgrade || parameters.force_downgrade,
+                    OperatorManagedCrdsDowngradeSnafu {
+                        operator: &self.name,
+                        from: &release.version,
+                        to: target_version.to_string(),
+                    }
+                );
+            }
+            CrdStatus::Missing => {}
+        }
+
+        let version = target_version.to_string();
+        let upgrade_result = async {
+            let (chart_source, chart_name) =
+                self.chart(&parameters.chart_source)
+                    .context(UpgradeReleaseSnafu {
+                        release_name: &release_name,
+                    })?;
+            let helm_values_yaml = self.helm_values_yaml(&values);
+
+            helm_client
+                .upgrade_or_install_release_from_repo_or_registry(
+                    &release_name,
+                    helm::ChartVersion {
+                        chart_version: Some(&version),
+                        chart_name: &chart_name,
+                        chart_source: &chart_source,
+                    },
+                    helm_values_yaml.as_deref(),
+                    namespace,
+                    parameters.force,
+                )
+                .await
+                .context(UpgradeReleaseSnafu {
+                    release_name: &release_name,
+                })
+        }
+        .await;
+
+        // The previous operator version is running again (or still), so it
+        // needs the previous CRDs
+        let upgrade_error = match &upgrade_result {
+            Ok(InstallReleaseStatus::RolledBack { error, .. }) => Some(error.clone()),
+            Ok(_) => None,
+            Err(error) => Some(error.to_string()),
+        };
+
+        if let (Some(upgrade_error), Some(crd_snapshot)) = (upgrade_error, crd_snapshot) {
+            warn!(operator = %self, "Upgrading operator failed, restoring previous CRDs");
+
+            k8s_client
+                .replace_crds(&crd_snapshot)
+                .await
+                .context(RestoreCrdsSnafu {
+                    operator: &self.name,
+                    upgrade_error,
+                })?;
+        }
+
+        let status = upgrade_result?;
+
+        if let InstallReleaseStatus::RolledBack { .. } = status {
+            warn!(operator = %self, "Upgrading operator failed, rolled back");
+        }
+
+        Ok(OperatorUpgradeReport {
+            status,
+            crds: crds.status,
+        })
+    }
+
+    /// Downgrading an operator across a storage version of its CRDs can make
+    /// the stored objects unreadable for the older operator, so this returns
+    /// an error if the storage version of any installed CRD would change or
+    /// can't be determined.
+    fn check_storage_versions(
+        &self,
+        current_version: &str,
+        manifests: &str,
+        installed_crds: &ObjectList<CustomResourceDefinition>,
+    ) -> Result<(), OperatorUpgradeError> {
+        let target_version = self
+            .version
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+
+        for document in serde_yaml::Deserializer::from_str(manifests) {
+            let crd =
+                CustomResourceDefinition::deserialize(document).context(DeserializeCrdsSnafu {
+                    operator: &self.name,
+                })?;
+
+            // Without an installed CRD, there are no stored objects
+            let Some(installed_crd) = installed_crds
+                .iter()
+                .find(|installed_crd| installed_crd.name_any() == crd.name_any())
+            else {
+                continue;
+            };
+
+            let (Some(current), Some(target)) =
+                (storage_version(installed_crd), storage_version(&crd))
+            else {
+                return UnknownStorageVersionSnafu {
+                    operator: &self.name,
+                    from: current_version,
+                    to: &target_version,
+                    crd: crd.name_any(),
+                }
+                .fail();
+            };
+
+            debug!(
+                crd = crd.name_any(),
+                current, target, "Comparing CRD storage versions"
+            );
+
+            if Version::parse(current_version).is_err() {
+                // The direction is unknown, so only identical storage versions
+                // are accepted
+                ensure!(
+                    current == target,
+                    UnknownUpgradeDirectionSnafu {
+                        operator: &self.name,
+                        from: current_version,
+                        to: &target_version,
+                    }
+                );
+            }
+
+            ensure!(
+                current == target,
+                StorageVersionDowngradeSnafu {
+                    operator: &self.name,
+                    from: current_version,
+                    to: &target_version,
+                    crd: crd.name_any(),
+                    current,
+                    target,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the installed CRDs with the provided `names` as multi-document
+    /// YAML string, which is used to restore them if the upgrade fails. CRDs
+    /// which are not installed yet are not part of the snapshot and are kept
+    /// when restoring, as deleting them would delete their objects as well.
+    fn snapshot_crds(
+        &self,
+        installed_crds: &ObjectList<CustomResourceDefinition>,
+        names: &[String],
+    ) -> Result<String, OperatorUpgradeError> {
+        let mut documents = Vec::new();
+
+        for installed_crd in installed_crds
+            .iter()
+            .filter(|installed_crd| names.contains(&installed_crd.name_any()))
+        {
+            // Fields managed by the API server are set again when restoring
+            let mut crd = installed_crd.clone();
+            crd.metadata.creation_timestamp = None;
+            crd.metadata.generation = None;
+            crd.metadata.managed_fields = None;
+            crd.metadata.resource_version = None;
+            crd.metadata.uid = None;
+            crd.status = None;
+
+            documents.push(serde_yaml::to_string(&crd).context(SerializeCrdsSnafu {
+                operator: &self.name,
+            })?);
+        }
+
+        Ok(documents.join("---\n"))
+    }
+}
+
+fn storage_version(crd: &CustomResourceDefinition) -> Option<&str> {
+    crd.spec
+        .versions
+        .iter()
+        .find(|version| version.storage)
+        .map(|version| version.name.as_str())
+}
+
+fn is_dev_build(version: &Version) -> bool {
+    version.major == 0 && version.minor == 0 && version.patch == 0 && !version.pre.is_empty()
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("25.3.0", "25.7.0", UpgradeDirection::Upgrade)]
+    #[case("25.7.0", "25.3.0", UpgradeDirection::Downgrade)]
+    #[case("25.7.0", "0.0.0-dev", UpgradeDirection::Upgrade)]
+    #[case("0.0.0-dev", "0.0.0-pr123", UpgradeDirection::Upgrade)]
+    #[case("0.0.0-dev", "25.7.0", UpgradeDirection::Downgrade)]
+    #[case("unknown", "25.7.0", UpgradeDirection::Unknown)]
+    fn upgrade_direction(
+        #[case] current_version: &str,
+        #[case] target_version: &str,
+        #[case] expected: UpgradeDirection,
+    ) {
+        let target_version = Version::parse(target_version).unwrap();
+        assert_eq!(
+            UpgradeDirection::of(current_version, &target_version),
+            expected
+        );
+    }
+}
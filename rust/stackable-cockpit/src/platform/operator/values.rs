@@ -0,0 +1,405 @@
+use std::fmt::Display;
+
+use serde_json::Value as JsonValue;
+use serde_yaml::{Mapping, Value};
+use snafu::{ResultExt, Snafu};
+use tracing::{debug, instrument};
+
+use crate::{
+    helm::{self, ChartValues},
+    platform::operator::{ChartSourceType, OperatorSpec},
+};
+
+#[derive(Debug, Snafu)]
+pub enum ValuesValidationError {
+    #[snafu(display("failed to retrieve default values of the {operator}-operator chart"))]
+    ChartValues {
+        source: helm::Error,
+        operator: String,
+    },
+
+    #[snafu(display("failed to parse values schema of the {operator}-operator chart"))]
+    ParseSchema {
+        source: serde_json::Error,
+        operator: String,
+    },
+}
+
+/// A problem found in the Helm values of an operator. The `path` is the
+/// dot-separated path of the affected key, e.g. `podAnnotations.team`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValuesIssue {
+    /// The key is not known to the chart and is most likely ignored.
+    UnknownKey { path: String },
+
+    /// The top-level section doesn't belong to any known operator, e.g.
+    /// because of a typo like `airfow-operator`, and is ignored.
+    UnknownOperator { section: String },
+
+    /// The value doesn't have the type the chart expects.
+    TypeMismatch {
+        path: String,
+        expected: String,
+        found: &'static str,
+    },
+}
+
+impl Display for ValuesIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValuesIssue::UnknownKey { path } => write!(f, "unknown key {path:?}"),
+            ValuesIssue::UnknownOperator { section } => {
+                write!(f, "unknown operator section {section:?}")
+            }
+            ValuesIssue::TypeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(f, "key {path:?} must be of type {expected}, found {found}"),
+        }
+    }
+}
+
+impl OperatorSpec {
+    /// Validates the user-provided Helm `values` of the operator against the
+    /// chart. Operators without values are skipped, so that the chart is only
+    /// loaded if necessary.
+    #[instrument(skip_all, fields(name = %self.name))]
+    pub async fn validate_values(
+        &self,
+        chart_source: &ChartSourceType,
+        values: &Mapping,
+        helm_client: &helm::Client,
+    ) -> Result<Vec<ValuesIssue>, ValuesValidationError> {
+        if values.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        debug!("Validating operator values");
+
+        let version = self.version.as_ref().map(|v| v.to_string());
+        let (chart_source, chart_name) = self.chart(chart_source).context(ChartValuesSnafu {
+            operator: &self.name,
+        })?;
+
+        let chart_values = helm_client
+            .chart_values(helm::ChartVersion {
+                chart_version: version.as_deref(),
+                chart_name: &chart_name,
+                chart_source: &chart_source,
+            })
+            .await
+            .context(ChartValuesSnafu {
+                operator: &self.name,
+            })?;
+
+        validate_values(values, &chart_values).context(ParseSchemaSnafu {
+            operator: &self.name,
+        })
+    }
+}
+
+/// Validates `values` against the `values.schema.json` of the chart. Charts
+/// without a schema are validated against their default values instead, which
+/// can only detect unknown keys and type mismatches of keys with defaults.
+///
+/// Only the structural keywords of the schema (`type`, `properties`,
+/// `additionalProperties` and `items`) are checked. Keys not listed in the
+/// `properties` of the schema are reported as unknown, unless the schema
+/// describes them using `additionalProperties`.
+pub fn validate_values(
+    values: &Mapping,
+    chart_values: &ChartValues,
+) -> Result<Vec<ValuesIssue>, serde_json::Error> {
+    let mut issues = Vec::new();
+    let values = Value::Mapping(values.clone());
+
+    if chart_values.schema.trim().is_empty() {
+        check_defaults(
+            "",
+            &values,
+            &Value::Mapping(chart_values.values.clone()),
+            &mut issues,
+        );
+    } else {
+        let schema: JsonValue = serde_json::from_str(&chart_values.schema)?;
+        check_schema("", &values, &schema, &mut issues);
+    }
+
+    Ok(issues)
+}
+
+/// Returns an issue for every top-level section of the `operator_values`
+/// which doesn't belong to one of the `known_operators`. Sections are named
+/// after the chart of the operator, e.g. `airflow-operator`.
+pub fn check_operator_sections<'a>(
+    operator_values: &Mapping,
+    known_operators: impl IntoIterator<Item = &'a str>,
+) -> Vec<ValuesIssue> {
+    let known_sections: Vec<String> = known_operators
+        .into_iter()
+        .map(|operator| format!("{operator}-operator"))
+        .collect();
+
+    operator_values
+        .keys()
+        .map(key_name)
+        .filter(|section| !known_sections.contains(section))
+        .map(|section| ValuesIssue::UnknownOperator { section })
+        .collect()
+}
+
+fn check_schema(path: &str, value: &Value, schema: &JsonValue, issues: &mut Vec<ValuesIssue>) {
+    // Helm removes keys set to null from the defaults before validating
+    if value.is_null() {
+        return;
+    }
+
+    let expected_types: Vec<&str> = match schema.get("type") {
+        Some(JsonValue::String(expected_type)) => vec![expected_type.as_str()],
+        Some(JsonValue::Array(expected_types)) => expected_types
+            .iter()
+            .filter_map(JsonValue::as_str)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    if !expected_types.is_empty()
+        && !expected_types
+            .iter()
+            .any(|expected_type| has_type(value, expected_type))
+    {
+        issues.push(ValuesIssue::TypeMismatch {
+            path: path.to_owned(),
+            expected: expected_types.join(" or "),
+            found: type_name(value),
+        });
+        return;
+    }
+
+    match value {
+        Value::Mapping(mapping) => {
+            let properties = schema.get("properties").and_then(JsonValue::as_object);
+            let additional_properties = schema.get("additionalProperties");
+
+            for (key, value) in mapping {
+                let path = join_path(path, key);
+
+                match (
+                    properties.and_then(|properties| properties.get(&key_name(key))),
+                    additional_properties,
+                ) {
+                    (Some(property), _) => check_schema(&path, value, property, issues),
+                    (None, Some(additional @ JsonValue::Object(_))) => {
+                        check_schema(&path, value, additional, issues)
+                    }
+                    (None, Some(JsonValue::Bool(false))) => {
+                        issues.push(ValuesIssue::UnknownKey { path })
+                    }
+                    // Without properties, the schema doesn't describe the keys
+                    (None, None) if properties.is_some() => {
+                        issues.push(ValuesIssue::UnknownKey { path })
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+        Value::Sequence(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    check_schema(&format!("{path}[{index}]"), item, item_schema, issues);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_defaults(path: &str, value: &Value, default: &Value, issues: &mut Vec<ValuesIssue>) {
+    // A null default means the chart doesn't set a default, so anything goes
+    if value.is_null() || default.is_null() {
+        return;
+    }
+
+    if !same_type(value, default) {
+        issues.push(ValuesIssue::TypeMismatch {
+            path: path.to_owned(),
+            expected: type_name(default).to_owned(),
+            found: type_name(value),
+        });
+        return;
+    }
+
+    let (Value::Mapping(mapping), Value::Mapping(defaults)) = (value, default) else {
+        return;
+    };
+
+    // Empty defaults like `podAnnotations: {}` accept arbitrary keys
+    if defaults.is_empty() {
+        return;
+    }
+
+    for (key, value) in mapping {
+        let path = join_path(path, key);
+
+        match defaults.get(key) {
+            Some(default) => check_defaults(&path, value, default, issues),
+            None => issues.push(ValuesIssue::UnknownKey { path }),
+        }
+    }
+}
+
+fn has_type(value: &Value, expected_type: &str) -> bool {
+    match expected_type {
+        "object" => value.is_mapping(),
+        "array" => value.is_sequence(),
+        "string" => value.is_string(),
+        "boolean" => value.is_bool(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "null" => value.is_null(),
+        // Unknown types are not checked
+        _ => true,
+    }
+}
+
+/// Integers and floats are considered the same type, as charts often use
+/// integer defaults for values which also accept floats.
+fn same_type(value: &Value, other: &Value) -> bool {
+    (value.is_number() && other.is_number()) || type_name(value) == type_name(other)
+}
+
+/// Returns the JSON schema name of the type of `value`.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Sequence(_) => "array",
+        Value::Mapping(_) => "object",
+        Value::Tagged(_) => "tagged value",
+    }
+}
+
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        key => serde_yaml::to_string(key)
+            .unwrap_or_default()
+            .trim_end()
+            .to_owned(),
+    }
+}
+
+fn join_path(path: &str, key: &Value) -> String {
+    if path.is_empty() {
+        key_name(key)
+    } else {
+        format!("{path}.{key}", key = key_name(key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "properties": {
+            "replicas": { "type": "integer" },
+            "image": {
+                "type": "object",
+                "properties": {
+                    "repository": { "type": "string" },
+                    "pullPolicy": { "type": "string" }
+                }
+            },
+            "podAnnotations": {
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            },
+            "tolerations": { "type": "array" }
+        }
+    }"#;
+
+    const DEFAULTS: &str = r#"
+replicas: 1
+image:
+  repository: oci.stackable.tech/sdp/airflow-operator
+  pullPolicy: IfNotPresent
+podAnnotations: {}
+tolerations: []
+nodeSelector: ~
+"#;
+
+    #[rstest]
+    #[case("image:\n  pullPolicy: Always\npodAnnotations:\n  team: data\n", vec![])]
+    #[case("imagee:\n  pullPolicy: Always\n", vec![
+        ValuesIssue::UnknownKey { path: "imagee".to_owned() }
+    ])]
+    #[case("image:\n  pullPolicyy: Always\n", vec![
+        ValuesIssue::UnknownKey { path: "image.pullPolicyy".to_owned() }
+    ])]
+    #[case("replicas: two\n", vec![ValuesIssue::TypeMismatch {
+        path: "replicas".to_owned(),
+        expected: "integer".to_owned(),
+        found: "string",
+    }])]
+    #[case("tolerations:\n  key: example\n", vec![ValuesIssue::TypeMismatch {
+        path: "tolerations".to_owned(),
+        expected: "array".to_owned(),
+        found: "object",
+    }])]
+    fn validate_against_schema(#[case] values: &str, #[case] expected: Vec<ValuesIssue>) {
+        let chart_values = ChartValues {
+            values: Mapping::new(),
+            schema: SCHEMA.to_owned(),
+        };
+        let values: Mapping = serde_yaml::from_str(values).unwrap();
+
+        assert_eq!(validate_values(&values, &chart_values).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("image:\n  pullPolicy: Always\npodAnnotations:\n  team: data\n", vec![])]
+    #[case("nodeSelector:\n  disktype: ssd\n", vec![])]
+    #[case("image:\n  pullPolicyy: Always\n", vec![
+        ValuesIssue::UnknownKey { path: "image.pullPolicyy".to_owned() }
+    ])]
+    #[case("replicas: 1.5\n", vec![])]
+    #[case("podAnnotations: team\n", vec![ValuesIssue::TypeMismatch {
+        path: "podAnnotations".to_owned(),
+        expected: "object".to_owned(),
+        found: "string",
+    }])]
+    fn validate_against_defaults(#[case] values: &str, #[case] expected: Vec<ValuesIssue>) {
+        let chart_values = ChartValues {
+            values: serde_yaml::from_str(DEFAULTS).unwrap(),
+            schema: String::new(),
+        };
+        let values: Mapping = serde_yaml::from_str(values).unwrap();
+
+        assert_eq!(validate_values(&values, &chart_values).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("airflow-operator:\n  replicas: 1\ncommons-operator: {}\n", vec![])]
+    #[case("airfow-operator:\n  replicas: 1\n", vec![
+        ValuesIssue::UnknownOperator { section: "airfow-operator".to_owned() }
+    ])]
+    #[case("airflow:\n  replicas: 1\n", vec![
+        ValuesIssue::UnknownOperator { section: "airflow".to_owned() }
+    ])]
+    fn unknown_operator_sections(#[case] values: &str, #[case] expected: Vec<ValuesIssue>) {
+        let values: Mapping = serde_yaml::from_str(values).unwrap();
+
+        assert_eq!(
+            check_operator_sections(&values, ["airflow", "commons"]),
+            expected
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use stackable_operator::kube::{ResourceExt, core::DynamicObject};
+
+use crate::platform::{manifests::RenderedManifest, operator::ChartSourceType, release};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    /// This error indicates that a rendered YAML manifest could not be parsed
+    /// into Kubernetes objects.
+    #[snafu(display("failed to parse rendered manifest {manifest:?}"))]
+    ParseManifest {
+        source: serde_yaml::Error,
+        manifest: String,
+    },
+}
+
+/// An install plan describes every change a stack or demo install would make
+/// to the cluster, without actually making it. It is built from the release
+/// and the rendered manifests.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPlan {
+    /// Operators which will be installed, in install order.
+    pub operators: Vec<PlannedOperator>,
+
+    /// Namespaces which will be created if they don't exist yet.
+    pub namespaces: Vec<String>,
+
+    /// Objects which will be applied using the kube client.
+    pub objects: Vec<PlannedObject>,
+
+    /// Helm releases which will be installed or upgraded.
+    pub helm_releases: Vec<PlannedHelmRelease>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedOperator {
+    pub name: String,
+    pub version: String,
+    pub namespace: String,
+    pub chart_source: ChartSourceType,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedObject {
+    pub api_version: String,
+    pub kind: String,
+    pub name: String,
+
+    /// The namespace the object will be applied in. Cluster-scoped objects
+    /// will ignore the namespace, which can only be determined when talking
+    /// to the cluster.
+    pub namespace: String,
+
+    /// The manifest file this object was rendered from.
+    pub manifest: String,
+
+    /// The rendered object, e.g. to export it.
+    #[serde(skip)]
+    pub content: serde_yaml::Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedHelmRelease {
+    pub release_name: String,
+    pub chart_name: String,
+    pub chart_version: String,
+    pub chart_repo: String,
+    pub namespace: String,
+    pub values: String,
+}
+
+impl InstallPlan {
+    /// Adds a namespace which needs to be created. Duplicates are ignored.
+    pub fn add_namespace(&mut self, namespace: impl Into<String>) {
+        let namespace = namespace.into();
+
+        if !self.namespaces.contains(&namespace) {
+            self.namespaces.push(namespace);
+        }
+    }
+
+    /// Adds all operators of the release which would be installed with the
+    /// provided include and exclude filters.
+    pub fn add_release(
+        &mut self,
+        release: &release::ReleaseSpec,
+        include_products: &[String],
+        exclude_products: &[String],
+        namespace: &str,
+        chart_source: &ChartSourceType,
+    ) {
+        let operators = release
+            .filter_products(include_products, exclude_products)
+            .into_iter()
+            .map(|(name, product)| PlannedOperator {
+                version: product.version.to_string(),
+                namespace: namespace.to_owned(),
+                chart_source: chart_source.clone(),
+                name,
+            });
+
+        self.operators.extend(operators);
+    }
+
+    /// Adds rendered manifests to the plan. Plain YAML manifests are split up
+    /// into the individual objects they contain.
+    pub fn add_manifests(
+        &mut self,
+        manifests: &[RenderedManifest],
+        namespace: &str,
+    ) -> Result<(), Error> {
+        for manifest in manifests {
+            match manifest {
+                RenderedManifest::HelmChart { chart, values_yaml } => {
+                    self.helm_releases.push(PlannedHelmRelease {
+                        release_name: chart.release_name.clone(),
+                        chart_name: chart.name.clone(),
+                        chart_version: chart.version.clone(),
+                        chart_repo: chart.repo.url.clone(),
+                        namespace: namespace.to_owned(),
+                        values: values_yaml.clone(),
+                    })
+                }
+                RenderedManifest::PlainYaml { source, content } => {
+                    for document in serde_yaml::Deserializer::from_str(content) {
+                        let content = serde_yaml::Value::deserialize(document).context(
+                            ParseManifestSnafu {
+                                manifest: source.clone(),
+                            },
+                        )?;
+
+                        // Empty documents, e.g. caused by a leading or trailing
+                        // separator or only containing comments, are skipped
+                        if content.is_null() {
+                            continue;
+                        }
+
+                        let object: DynamicObject = serde_yaml::from_value(content.clone())
+                            .context(ParseManifestSnafu {
+                                manifest: source.clone(),
+                            })?;
+
+                        let (api_version, kind) = object
+                            .types
+                            .as_ref()
+                            .map(|t| (t.api_version.clone(), t.kind.clone()))
+                            .unwrap_or_default();
+
+                        self.objects.push(PlannedObject {
+                            name: object.name_any(),
+                            namespace: namespace.to_owned(),
+                            manifest: source.clone(),
+                            api_version,
+                            kind,
+                            content,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn add_manifests_splits_documents() {
+        let manifests = vec![RenderedManifest::PlainYaml {
+            source: "manifests/trino.yaml".into(),
+            content: "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: first\n---\napiVersion: trino.stackable.tech/v1alpha1\nkind: TrinoCluster\nmetadata:\n  name: trino\n".into(),
+        }];
+
+        let mut plan = InstallPlan::default();
+        plan.add_manifests(&manifests, "default").unwrap();
+
+        assert_eq!(plan.objects.len(), 2);
+        assert_eq!(plan.objects[0].kind, "ConfigMap");
+        assert_eq!(plan.objects[0].name, "first");
+        assert_eq!(plan.objects[1].api_version, "trino.stackable.tech/v1alpha1");
+        assert_eq!(plan.objects[1].namespace, "default");
+    }
+
+    #[test]
+    fn add_manifests_skips_empty_documents() {
+        let manifests = vec![RenderedManifest::PlainYaml {
+            source: "manifests/trino.yaml".into(),
+            content: "---\napiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: first\n---\n# Only a comment\n---\n".into(),
+        }];
+
+        let mut plan = InstallPlan::default();
+        plan.add_manifests(&manifests, "default").unwrap();
+
+        assert_eq!(plan.objects.len(), 1);
+        assert_eq!(plan.objects[0].name, "first");
+    }
+
+    #[test]
+    fn add_namespace_deduplicates() {
+        let mut plan = InstallPlan::default();
+        plan.add_namespace("stackable-operators");
+        plan.add_namespace("default");
+        plan.add_namespace("default");
+
+        assert_eq!(plan.namespaces, vec!["stackable-operators", "default"]);
+    }
+}
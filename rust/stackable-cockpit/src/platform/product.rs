@@ -10,4 +10,10 @@ pub struct ProductSpec {
     #[serde(rename = "operatorVersion")]
     #[cfg_attr(feature = "openapi", schema(value_type = String))]
     pub version: Version,
+
+    /// Names of the operators which need to be installed before this operator.
+    /// If not set, the built-in dependencies are used, see
+    /// [`default_dependencies`](crate::platform::operator::default_dependencies).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
 }
@@ -0,0 +1,564 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use snafu::{ResultExt, Snafu};
+use stackable_operator::k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceDefinition, JSONSchemaProps, JSONSchemaPropsOrArray,
+};
+use tracing::{Span, info, instrument};
+use tracing_indicatif::span_ext::IndicatifSpanExt as _;
+
+use crate::{
+    PROGRESS_BAR_STYLE,
+    constants::DEFAULT_OPERATOR_NAMESPACE,
+    helm,
+    platform::{
+        operator::{self, ChartSourceType, OperatorSpec},
+        release::{self, CrdStatus, ReleaseSpec, resolve_operator_crds},
+    },
+    xfer,
+};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("failed to parse operator spec"))]
+    OperatorSpecParse { source: operator::SpecParseError },
+
+    #[snafu(display("failed to retrieve CRD manifests of {product_name}-operator {version}"))]
+    FetchCrds {
+        #[snafu(source(from(release::Error, Box::new)))]
+        source: Box<release::Error>,
+        product_name: String,
+        version: String,
+    },
+
+    #[snafu(display("failed to deserialize CRD manifests of {product_name}-operator {version}"))]
+    DeserializeCrds {
+        source: serde_yaml::Error,
+        product_name: String,
+        version: String,
+    },
+}
+
+/// Differences between two releases.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseDiff {
+    pub from: String,
+    pub to: String,
+
+    /// Operators which were added, removed or changed their version.
+    pub operators: Vec<OperatorDiff>,
+
+    /// Differences of the CRDs shipped by the changed operators. This is only
+    /// populated when requested via [`ReleaseDiff::with_crds`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crds: Vec<CrdDiff>,
+
+    /// Operators for which no CRD manifests are available, e.g. because they
+    /// maintain their CRDs themselves.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crds_unavailable: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorDiff {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_version: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_version: Option<String>,
+
+    pub change: Change,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added => write!(f, "added"),
+            Change::Removed => write!(f, "removed"),
+            Change::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrdDiff {
+    /// Name of the CRD, e.g. `druidclusters.druid.stackable.tech`.
+    pub name: String,
+
+    pub change: Option<Change>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_versions: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_versions: Vec<String>,
+
+    /// Schema fields added in versions present in both releases, formatted as
+    /// `<VERSION>: <FIELD PATH>`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_fields: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_fields: Vec<String>,
+
+    /// Schema fields whose type changed, formatted as
+    /// `<VERSION>: <FIELD PATH> (<OLD TYPE> -> <NEW TYPE>)`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed_fields: Vec<String>,
+}
+
+impl CrdDiff {
+    fn is_empty(&self) -> bool {
+        self.change.is_none()
+            && self.added_versions.is_empty()
+            && self.removed_versions.is_empty()
+            && self.added_fields.is_empty()
+            && self.removed_fields.is_empty()
+            && self.changed_fields.is_empty()
+    }
+}
+
+impl ReleaseDiff {
+    /// Compares the operators of two releases.
+    pub fn new(from_name: &str, from: &ReleaseSpec, to_name: &str, to: &ReleaseSpec) -> Self {
+        let mut operators = Vec::new();
+
+        for (name, product) in &from.products {
+            match to.products.get(name) {
+                Some(to_product) if to_product.version == product.version => {}
+                Some(to_product) => operators.push(OperatorDiff {
+                    name: name.clone(),
+                    from_version: Some(product.version.to_string()),
+                    to_version: Some(to_product.version.to_string()),
+                    change: Change::Changed,
+                }),
+                None => operators.push(OperatorDiff {
+                    name: name.clone(),
+                    from_version: Some(product.version.to_string()),
+                    to_version: None,
+                    change: Change::Removed,
+                }),
+            }
+        }
+
+        for (name, product) in &to.products {
+            if !from.products.contains_key(name) {
+                operators.push(OperatorDiff {
+                    name: name.clone(),
+                    from_version: None,
+                    to_version: Some(product.version.to_string()),
+                    change: Change::Added,
+                });
+            }
+        }
+
+        operators.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            from: from_name.to_owned(),
+            to: to_name.to_owned(),
+            operators,
+            ..Default::default()
+        }
+    }
+
+    /// Additionally compares the CRDs of all changed operators. The CRDs are
+    /// taken from the operator charts in the `chart_source`, CRDs missing in a
+    /// chart are retrieved from the upstream GitHub repository instead.
+    #[instrument(skip_all, fields(indicatif.pb_show = true))]
+    pub async fn with_crds(
+        mut self,
+        from: &ReleaseSpec,
+        to: &ReleaseSpec,
+        chart_source: &ChartSourceType,
+        helm_client: &helm::Client,
+        transfer_client: &xfer::Client,
+    ) -> Result<Self> {
+        info!("Comparing CRDs");
+        Span::current().pb_set_style(&PROGRESS_BAR_STYLE);
+        Span::current().pb_set_length(self.operators.len() as u64);
+
+        for operator in &self.operators {
+            Span::current().pb_set_message(&format!(
+                "Comparing CRDs of {name}-operator",
+                name = operator.name
+            ));
+
+            let from_crds = fetch_crds(
+                &operator.name,
+                from,
+                chart_source,
+                helm_client,
+                transfer_client,
+            )
+            .await?;
+            let to_crds = fetch_crds(
+                &operator.name,
+                to,
+                chart_source,
+                helm_client,
+                transfer_client,
+            )
+            .await?;
+
+            match (from_crds, to_crds) {
+                (Some(from_crds), Some(to_crds)) => {
+                    self.crds.extend(diff_crds(&from_crds, &to_crds))
+                }
+                (Some(from_crds), None) if operator.change == Change::Removed => {
+                    self.crds.extend(diff_crds(&from_crds, &[]))
+                }
+                (None, Some(to_crds)) if operator.change == Change::Added => {
+                    self.crds.extend(diff_crds(&[], &to_crds))
+                }
+                _ => self.crds_unavailable.push(operator.name.clone()),
+            }
+
+            Span::current().pb_inc(1);
+        }
+
+        Ok(self)
+    }
+}
+
+/// Returns the CRDs of the operator `product_name` in the version of the
+/// `release`, or [`None`] if the release doesn't contain the operator or no
+/// CRDs are available for it, e.g. because the operator maintains them itself.
+async fn fetch_crds(
+    product_name: &str,
+    release: &ReleaseSpec,
+    chart_source: &ChartSourceType,
+    helm_client: &helm::Client,
+    transfer_client: &xfer::Client,
+) -> Result<Option<Vec<CustomResourceDefinition>>> {
+    let Some(product) = release.products.get(product_name) else {
+        return Ok(None);
+    };
+    let version = product.version.to_string();
+
+    let operator = OperatorSpec::new(product_name, Some(product.version.clone()))
+        .context(OperatorSpecParseSnafu)?;
+    let crds = resolve_operator_crds(
+        &operator,
+        DEFAULT_OPERATOR_NAMESPACE,
+        chart_source,
+        &Mapping::new(),
+        true,
+        helm_client,
+        transfer_client,
+    )
+    .await
+    .context(FetchCrdsSnafu {
+        product_name,
+        version: &version,
+    })?;
+
+    if !matches!(crds.status, CrdStatus::Replace { .. }) {
+        return Ok(None);
+    }
+
+    let crds = parse_crds(&crds.manifests).context(DeserializeCrdsSnafu {
+        product_name,
+        version,
+    })?;
+
+    Ok(Some(crds))
+}
+
+fn parse_crds(manifests: &str) -> Result<Vec<CustomResourceDefinition>, serde_yaml::Error> {
+    let mut crds = Vec::new();
+
+    for document in serde_yaml::Deserializer::from_str(manifests) {
+        let document = Value::deserialize(document)?;
+
+        // Manifests can contain empty documents, e.g. after a trailing `---`
+        if !document.is_null() {
+            crds.push(serde_yaml::from_value(document)?);
+        }
+    }
+
+    Ok(crds)
+}
+
+/// Compares two sets of CRDs and returns the differences of every CRD which
+/// changed.
+fn diff_crds(from: &[CustomResourceDefinition], to: &[CustomResourceDefinition]) -> Vec<CrdDiff> {
+    let from = crds_by_name(from);
+    let to = crds_by_name(to);
+
+    let names: BTreeSet<_> = from.keys().chain(to.keys()).collect();
+    let mut diffs = Vec::new();
+
+    for name in names {
+        let from_versions = from.get(name).map(schema_fields).unwrap_or_default();
+        let to_versions = to.get(name).map(schema_fields).unwrap_or_default();
+
+        let mut diff = CrdDiff {
+            name: name.to_string(),
+            change: match (from.contains_key(name), to.contains_key(name)) {
+                (false, true) => Some(Change::Added),
+                (true, false) => Some(Change::Removed),
+                _ => None,
+            },
+            ..Default::default()
+        };
+
+        for (version, from_fields) in &from_versions {
+            let Some(to_fields) = to_versions.get(version) else {
+                diff.removed_versions.push(version.clone());
+                continue;
+            };
+
+            for (path, from_type) in from_fields {
+                match to_fields.get(path) {
+                    Some(to_type) if to_type != from_type => diff
+                        .changed_fields
+                        .push(format!("{version}: {path} ({from_type} -> {to_type})")),
+                    Some(_) => {}
+                    None => diff.removed_fields.push(format!("{version}: {path}")),
+                }
+            }
+
+            for path in to_fields.keys() {
+                if !from_fields.contains_key(path) {
+                    diff.added_fields.push(format!("{version}: {path}"));
+                }
+            }
+        }
+
+        for version in to_versions.keys() {
+            if !from_versions.contains_key(version) {
+                diff.added_versions.push(version.clone());
+            }
+        }
+
+        if !diff.is_empty() {
+            if diff.change.is_none() {
+                diff.change = Some(Change::Changed);
+            }
+            diffs.push(diff);
+        }
+    }
+
+    diffs
+}
+
+fn crds_by_name(crds: &[CustomResourceDefinition]) -> BTreeMap<&str, &CustomResourceDefinition> {
+    crds.iter()
+        .filter_map(|crd| crd.metadata.name.as_deref().map(|name| (name, crd)))
+        .collect()
+}
+
+/// Returns the flattened schema fields (path to type) of every version of the
+/// CRD.
+fn schema_fields(crd: &&CustomResourceDefinition) -> BTreeMap<String, BTreeMap<String, String>> {
+    crd.spec
+        .versions
+        .iter()
+        .map(|version| {
+            let mut fields = BTreeMap::new();
+
+            if let Some(schema) = version
+                .schema
+                .as_ref()
+                .and_then(|schema| schema.open_api_v3_schema.as_ref())
+            {
+                collect_fields(schema, "", &mut fields);
+            }
+
+            (version.name.clone(), fields)
+        })
+        .collect()
+}
+
+fn collect_fields(schema: &JSONSchemaProps, prefix: &str, fields: &mut BTreeMap<String, String>) {
+    for (name, property) in schema.properties.iter().flatten() {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}.{name}")
+        };
+
+        fields.insert(
+            path.clone(),
+            property.type_.clone().unwrap_or_else(|| "any".to_owned()),
+        );
+        collect_fields(property, &path, fields);
+    }
+
+    if let Some(JSONSchemaPropsOrArray::Schema(items)) = &schema.items {
+        collect_fields(items, &format!("{prefix}[]"), fields);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indexmap::IndexMap;
+    use semver::Version;
+
+    use super::*;
+    use crate::platform::product::ProductSpec;
+
+    fn release(products: &[(&str, &str)]) -> ReleaseSpec {
+        ReleaseSpec {
+            date: "2025-01-01".to_owned(),
+            description: "Test release".to_owned(),
+            supported_kubernetes_versions: None,
+            products: products
+                .iter()
+                .map(|(name, version)| {
+                    (
+                        name.to_string(),
+                        ProductSpec {
+                            version: Version::parse(version).unwrap(),
+                            depends_on: None,
+                        },
+                    )
+                })
+                .collect::<IndexMap<_, _>>(),
+        }
+    }
+
+    fn crd(versions: &str) -> CustomResourceDefinition {
+        serde_yaml::from_str(&format!(
+            "
+apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: druidclusters.druid.stackable.tech
+spec:
+  group: druid.stackable.tech
+  names:
+    kind: DruidCluster
+    plural: druidclusters
+  scope: Namespaced
+  versions:
+{versions}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn diff_operators() {
+        let from = release(&[
+            ("airflow", "25.3.0"),
+            ("druid", "25.3.0"),
+            ("hive", "25.3.0"),
+        ]);
+        let to = release(&[
+            ("airflow", "25.3.0"),
+            ("druid", "25.7.0"),
+            ("kafka", "25.7.0"),
+        ]);
+
+        let diff = ReleaseDiff::new("25.3", &from, "25.7", &to);
+        let changes: Vec<_> = diff
+            .operators
+            .iter()
+            .map(|operator| (operator.name.as_str(), operator.change))
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("druid", Change::Changed),
+                ("hive", Change::Removed),
+                ("kafka", Change::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_crds_skips_empty_documents() {
+        let crd = serde_yaml::to_string(&crd("
+  - name: v1alpha1
+    served: true
+    storage: true
+"))
+        .unwrap();
+
+        let crds = parse_crds(&format!("---\n{crd}---\n")).unwrap();
+
+        assert_eq!(crds.len(), 1);
+    }
+
+    #[test]
+    fn diff_crd_schemas() {
+        let from = crd("
+  - name: v1alpha1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              image:
+                type: string
+              replicas:
+                type: integer
+");
+        let to = crd("
+  - name: v1alpha1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              image:
+                type: object
+              servers:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    name:
+                      type: string
+  - name: v1alpha2
+    served: true
+    storage: true
+");
+
+        let diffs = diff_crds(&[from], &[to]);
+        assert_eq!(diffs.len(), 1);
+
+        let diff = &diffs[0];
+        assert_eq!(diff.change, Some(Change::Changed));
+        assert_eq!(diff.added_versions, vec!["v1alpha2"]);
+        assert_eq!(
+            diff.added_fields,
+            vec!["v1alpha1: spec.servers", "v1alpha1: spec.servers[].name"]
+        );
+        assert_eq!(diff.removed_fields, vec!["v1alpha1: spec.replicas"]);
+        assert_eq!(
+            diff.changed_fields,
+            vec!["v1alpha1: spec.image (string -> object)"]
+        );
+    }
+}
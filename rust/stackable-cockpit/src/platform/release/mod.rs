@@ -1,7 +1,9 @@
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
+pub mod diff;
 mod spec;
+pub mod upgrade;
 
 pub use spec::*;
 
@@ -1,11 +1,11 @@
 use futures::{StreamExt as _, TryStreamExt};
 use indexmap::IndexMap;
 use reqwest::StatusCode;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use serde_yaml::Mapping;
-use snafu::{ResultExt, Snafu};
-use tokio::task::JoinError;
-use tracing::{Instrument, Span, debug, info, instrument};
+use serde_yaml::{Mapping, Value};
+use snafu::{OptionExt, ResultExt, Snafu};
+use tracing::{Instrument, Span, debug, info, instrument, warn};
 use tracing_indicatif::span_ext::IndicatifSpanExt as _;
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
@@ -13,7 +13,8 @@ use utoipa::ToSchema;
 use crate::{
     PROGRESS_BAR_STYLE, helm,
     platform::{
-        operator::{self, ChartSourceType, OperatorSpec},
+        cluster::{self, KubernetesVersionError},
+        operator::{self, ChartSourceType, DependencyError, DependencyGraph, OperatorSpec},
         product,
     },
     utils::{
@@ -26,6 +27,8 @@ use crate::{
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+const CRD_KIND: &str = "CustomResourceDefinition";
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("failed to parse operator spec"))]
@@ -38,6 +41,26 @@ pub enum Error {
         path_or_url: String,
     },
 
+    #[snafu(display("failed to render chart of {product_name}-operator"))]
+    HelmTemplate {
+        source: helm::Error,
+        product_name: String,
+    },
+
+    #[snafu(display("failed to deserialize rendered manifests of {product_name}-operator"))]
+    DeserializeManifests {
+        source: serde_yaml::Error,
+        product_name: String,
+    },
+
+    #[snafu(display(
+        "cannot override the version of {product_name:?}, as it is not part of the release"
+    ))]
+    UnknownProduct { product_name: String },
+
+    #[snafu(display("failed to resolve operator dependencies"))]
+    ResolveDependencies { source: DependencyError },
+
     /// This error indicates that receiving remote content failed.
     #[snafu(display("failed to receive remote content"))]
     FileTransfer { source: xfer::Error },
@@ -48,8 +71,13 @@ pub enum Error {
     #[snafu(display("failed to uninstall release using Helm"))]
     HelmUninstall { source: helm::Error },
 
-    #[snafu(display("failed to launch background task"))]
-    BackgroundTask { source: JoinError },
+    #[snafu(display(
+        "release {release_name:?} doesn't support the Kubernetes version of the cluster"
+    ))]
+    KubernetesVersion {
+        source: KubernetesVersionError,
+        release_name: String,
+    },
 
     #[snafu(display("failed to deploy manifests using the kube client"))]
     DeployManifest {
@@ -69,14 +97,125 @@ pub struct ReleaseSpec {
     /// A short description of this release
     pub description: String,
 
+    /// Kubernetes versions this release supports, e.g. `>=1.29, <1.33`. If not
+    /// set, the release is assumed to support every Kubernetes version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub supported_kubernetes_versions: Option<VersionReq>,
+
     /// List of products and their version in this release
     pub products: IndexMap<String, product::ProductSpec>,
 }
 
+#[derive(Debug)]
+pub struct ReleaseInstallParameters {
+    pub operator_namespace: String,
+    pub chart_source: ChartSourceType,
+    pub operator_values: Mapping,
+
+    /// Upgrade operators which are already installed in another version in
+    /// place instead of failing, replacing conflicting resources.
+    pub force: bool,
+}
+
+#[derive(Debug)]
+pub struct CrdUpgradeParameters {
+    pub operator_namespace: String,
+    pub chart_source: ChartSourceType,
+    pub operator_values: Mapping,
+
+    /// Retrieve the CRDs from the upstream GitHub repository of the operator
+    /// if its chart neither contains CRDs nor lets the operator maintain them.
+    pub github_fallback: bool,
+}
+
+/// Where the CRDs of an operator were taken from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CrdSource {
+    Chart,
+    GitHub,
+}
+
+/// Describes what happens with the CRDs of an operator during an upgrade.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CrdStatus {
+    /// The CRDs with the provided `names` are replaced.
+    Replace {
+        source: CrdSource,
+        names: Vec<String>,
+    },
+
+    /// The operator maintains its CRDs itself, so nothing is replaced.
+    OperatorManaged,
+
+    /// Neither the chart contains CRDs nor does the operator maintain them.
+    Missing,
+}
+
+#[derive(Debug)]
+pub struct OperatorCrds {
+    pub status: CrdStatus,
+
+    /// The CRD manifests as multi-document YAML string, which is empty unless
+    /// the CRDs are replaced.
+    pub manifests: String,
+}
+
+/// Lists which CRDs were replaced during an upgrade and which operators were
+/// skipped.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrdUpgradeReport {
+    pub replaced: Vec<ReplacedCrd>,
+
+    /// Operators which maintain their CRDs themselves.
+    pub operator_managed: Vec<String>,
+
+    /// Operators for which no CRDs were found.
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplacedCrd {
+    pub operator: String,
+    pub name: String,
+    pub source: CrdSource,
+}
+
 impl ReleaseSpec {
+    /// Checks if the prerequisites to install the release named `release_name`
+    /// are met. Currently this checks if the cluster runs a supported
+    /// Kubernetes version. If `skip_kubernetes_version_check` is set, an
+    /// unsupported version only results in a warning.
+    #[instrument(skip_all, fields(%release_name))]
+    pub async fn check_prerequisites(
+        &self,
+        release_name: &str,
+        client: &Client,
+        skip_kubernetes_version_check: bool,
+    ) -> Result<()> {
+        debug!("Checking prerequisites before installing release");
+
+        if let Some(supported_versions) = &self.supported_kubernetes_versions {
+            cluster::check_kubernetes_version(
+                client,
+                supported_versions,
+                &format!("release {release_name}"),
+                skip_kubernetes_version_check,
+            )
+            .await
+            .context(KubernetesVersionSnafu { release_name })?;
+        }
+
+        Ok(())
+    }
+
     /// Installs a release by installing individual operators.
     #[instrument(skip_all, fields(
-        %namespace,
+        namespace = %parameters.operator_namespace,
         product.included = tracing::field::Empty,
         product.excluded = tracing::field::Empty,
         indicatif.pb_show = true
@@ -85,9 +224,8 @@ impl ReleaseSpec {
         &self,
         include_products: &[String],
         exclude_products: &[String],
-        namespace: &str,
-        chart_source: &ChartSourceType,
-        operator_values: &Mapping,
+        parameters: &ReleaseInstallParameters,
+        helm_client: &helm::Client,
     ) -> Result<()> {
         info!("Installing release");
         Span::current().pb_set_style(&PROGRESS_BAR_STYLE);
@@ -100,64 +238,95 @@ impl ReleaseSpec {
         });
 
         let operators = self.filter_products(include_products, exclude_products);
+        let operator_names: Vec<String> = operators.iter().map(|(name, _)| name.clone()).collect();
+
+        // Operators are installed in waves, so that the operators others depend
+        // on are installed (and ready) before their dependents.
+        let waves = self
+            .dependency_graph()
+            .waves(&operator_names)
+            .context(ResolveDependenciesSnafu)?;
 
         Span::current().pb_set_length(operators.len() as u64);
 
-        let namespace = namespace.to_string();
+        for wave in waves {
+            debug!(operators = wave.join(", "), "Installing operator wave");
+
+            let wave_operators = operators
+                .iter()
+                .filter(|(name, _)| wave.contains(name))
+                .cloned();
+
+            Self::install_operators(wave_operators, parameters, helm_client).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Installs the provided `operators` concurrently.
+    async fn install_operators(
+        operators: impl Iterator<Item = (String, product::ProductSpec)>,
+        parameters: &ReleaseInstallParameters,
+        helm_client: &helm::Client,
+    ) -> Result<()> {
         futures::stream::iter(operators)
             .map(|(product_name, product)| {
                 let task_span =
                     tracing::info_span!("install_operator", product_name = tracing::field::Empty);
 
-                let namespace = namespace.clone();
-                let chart_source = chart_source.clone();
-                let operator_helm_values = values_for_operator(operator_values, &product_name);
-                // Helm installs currently `block_in_place`, so we need to spawn each job onto a separate task to
-                // get useful parallelism.
-                tokio::spawn(
-                    async move {
-                        Span::current().record("product_name", &product_name);
-                        info!("Installing {product_name}-operator");
-
-                        // Create operator spec
-                        let operator =
-                            OperatorSpec::new(&product_name, Some(product.version.clone()))
-                                .context(OperatorSpecParseSnafu)?;
-
-                        // Install operator
-                        operator
-                            .install(&namespace, &chart_source, &operator_helm_values)
-                            .context(HelmInstallSnafu)?;
-
-                        info!("Installed {product_name}-operator");
-
-                        Ok(())
-                    }
-                    .instrument(task_span),
-                )
+                let operator_helm_values =
+                    values_for_operator(&parameters.operator_values, &product_name);
+                // Helm operations run on the blocking thread pool, so polling the installs
+                // concurrently is enough to get useful parallelism.
+                async move {
+                    Span::current().record("product_name", &product_name);
+                    info!("Installing {product_name}-operator");
+
+                    // Create operator spec
+                    let operator = OperatorSpec::new(&product_name, Some(product.version.clone()))
+                        .context(OperatorSpecParseSnafu)?;
+
+                    // Install operator
+                    operator
+                        .install(
+                            &parameters.operator_namespace,
+                            &parameters.chart_source,
+                            &operator_helm_values,
+                            parameters.force,
+                            helm_client,
+                        )
+                        .await
+                        .context(HelmInstallSnafu)?;
+
+                    info!("Installed {product_name}-operator");
+
+                    Ok(())
+                }
+                .instrument(task_span)
             })
             .buffer_unordered(10)
-            .map(|res| {
-                Span::current().pb_inc(1);
-                res.context(BackgroundTaskSnafu)?
-            })
+            .inspect(|_| Span::current().pb_inc(1))
             .try_collect::<()>()
             .await
     }
 
-    /// Upgrades a release by upgrading individual operators.
+    /// Replaces the CRDs of the operators of a release. The CRDs are taken
+    /// from the same chart which is installed afterwards. Only if enabled via
+    /// [`CrdUpgradeParameters::github_fallback`], CRDs missing in the chart
+    /// are retrieved from the upstream GitHub repository.
     #[instrument(skip_all, fields(
-        %namespace,
+        namespace = %parameters.operator_namespace,
         indicatif.pb_show = true
     ))]
     pub async fn upgrade_crds(
         &self,
         include_products: &[String],
         exclude_products: &[String],
-        namespace: &str,
+        parameters: &CrdUpgradeParameters,
         k8s_client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
-    ) -> Result<()> {
+    ) -> Result<CrdUpgradeReport> {
         info!("Upgrading CRDs for release");
         Span::current().pb_set_style(&PROGRESS_BAR_STYLE);
 
@@ -172,60 +341,52 @@ impl ReleaseSpec {
 
         Span::current().pb_set_length(operators.len() as u64);
 
+        let mut report = CrdUpgradeReport::default();
+
         for (product_name, product) in operators {
             info!("Upgrading CRDs for {product_name}-operator");
             let iter_span = tracing::info_span!("upgrade_crds_iter", indicatif.pb_show = true);
 
-            async move {
-                Span::current().pb_set_message(format!("Ugrading CRDs for {product_name}-operator").as_str());
-
-                let release_branch = match product.version.pre.as_str() {
-                    "dev" => "main".to_string(),
-                    _ => {
-                        product.version.to_string()
+            async {
+                Span::current().pb_set_message(format!("Upgrading CRDs for {product_name}-operator").as_str());
+
+                let operator = OperatorSpec::new(&product_name, Some(product.version.clone()))
+                    .context(OperatorSpecParseSnafu)?;
+                let crds = resolve_operator_crds(
+                    &operator,
+                    &parameters.operator_namespace,
+                    &parameters.chart_source,
+                    &values_for_operator(&parameters.operator_values, &product_name),
+                    parameters.github_fallback,
+                    helm_client,
+                    transfer_client,
+                )
+                .await?;
+
+                match crds.status {
+                    CrdStatus::Replace { source, names } => {
+                        // Upgrade CRDs
+                        k8s_client
+                            .replace_crds(&crds.manifests)
+                            .await
+                            .context(DeployManifestSnafu)?;
+
+                        info!("Upgraded {product_name}-operator CRDs");
+                        report.replaced.extend(names.into_iter().map(|name| ReplacedCrd {
+                            operator: product_name.clone(),
+                            name,
+                            source,
+                        }));
                     }
-                };
-
-                let request_url_string = &format!(
-                    "https://raw.githubusercontent.com/stackabletech/{product_name}-operator/{release_branch}/deploy/helm/{product_name}-operator/crds/crds.yaml"
-                );
-                let request_url = request_url_string.into_path_or_url().context(ParsePathOrUrlSnafu {
-                    path_or_url: request_url_string,
-                })?;
-
-                // Get CRD manifests from request_url
-                let crd_manifests = transfer_client
-                    .get(&request_url, &Text)
-                    .await;
-                let crd_manifests = match crd_manifests {
-                        Ok(crd_manifests) => crd_manifests,
-                        Err(crate::xfer::Error::FetchRemoteContent{source: reqwest_error})
-                            if reqwest_error.status() == Some(StatusCode::NOT_FOUND) => {
-                            // Ignore 404, as CRD versioning is rolled out to operators.
-                            // Starting with secret-operator 25.11.0, the CRD is maintained by the operator,
-                            // making this entire functionality obsolete.
-                            // As only some of the operators are migrated yet, some operator crds.yaml's
-                            // return a 404, others don't.
-                            debug!(
-                                product = product_name,
-                                // https://opentelemetry.io/docs/specs/semconv/http/http-spans/#http-client-span
-                                url.full = request_url_string,
-                                "Skipped updating CRD, as it doesn't exist in the upstream GitHub repo because the operator most likely uses CRD versioning"
-                            );
-                            return Ok(());
-                        },
-                        Err(err) => {
-                            return Err(Error::FileTransfer { source: err });
-                        },
-                    };
-
-                // Upgrade CRDs
-                k8s_client
-                    .replace_crds(&crd_manifests)
-                    .await
-                    .context(DeployManifestSnafu)?;
-
-                info!("Upgraded {product_name}-operator CRDs");
+                    CrdStatus::OperatorManaged => {
+                        debug!("Skipped replacing CRDs, as they are maintained by {product_name}-operator");
+                        report.operator_managed.push(product_name.clone());
+                    }
+                    CrdStatus::Missing => {
+                        warn!("The chart of {product_name}-operator doesn't contain any CRDs");
+                        report.missing.push(product_name.clone());
+                    }
+                }
 
                 Ok::<(), Error>(())
             }.instrument(iter_span).await?;
@@ -233,15 +394,16 @@ impl ReleaseSpec {
             Span::current().pb_inc(1);
         }
 
-        Ok(())
+        Ok(report)
     }
 
     #[instrument(skip_all, fields(indicatif.pb_show = true))]
-    pub fn uninstall(
+    pub async fn uninstall(
         &self,
         include_products: &[String],
         exclude_products: &[String],
         namespace: &str,
+        helm_client: &helm::Client,
     ) -> Result<()> {
         info!("Uninstalling release");
 
@@ -252,7 +414,9 @@ impl ReleaseSpec {
             Span::current().record("product.excluded", product);
         });
 
-        let operators = self.filter_products(include_products, exclude_products);
+        // Dependencies are not uninstalled, as other operators might still
+        // depend on them
+        let operators = self.select_products(include_products, exclude_products);
 
         Span::current().pb_set_style(&PROGRESS_BAR_STYLE);
         Span::current().pb_set_length(operators.len() as u64);
@@ -265,7 +429,9 @@ impl ReleaseSpec {
                 .context(OperatorSpecParseSnafu)?;
 
             // Uninstall operator
-            helm::uninstall_release(&operator.helm_name(), namespace, true)
+            helm_client
+                .uninstall_release(&operator.helm_name(), namespace)
+                .await
                 .context(HelmUninstallSnafu)?;
 
             Span::current().pb_inc(1);
@@ -274,11 +440,56 @@ impl ReleaseSpec {
         Ok(())
     }
 
-    /// Filters out products based on if they are included or excluded.
+    /// Returns a copy of this release in which the versions of the provided
+    /// `operators` are overridden, e.g. to validate a hotfix or development
+    /// build before it is released. Operators without a version keep the
+    /// version of the release.
+    pub fn with_version_overrides(&self, operators: &[OperatorSpec]) -> Result<Self> {
+        let mut release = self.clone();
+
+        for operator in operators {
+            let Some(version) = &operator.version else {
+                continue;
+            };
+
+            let product =
+                release
+                    .products
+                    .get_mut(&operator.name)
+                    .context(UnknownProductSnafu {
+                        product_name: &operator.name,
+                    })?;
+
+            debug!(
+                product_name = operator.name,
+                release_version = %product.version,
+                %version,
+                "Overriding product version"
+            );
+            product.version = version.clone();
+        }
+
+        Ok(release)
+    }
+
+    /// Filters out products based on if they are included or excluded. The
+    /// dependencies of included products are included as well, unless they are
+    /// excluded explicitly.
     pub fn filter_products(
         &self,
         include_products: &[String],
         exclude_products: &[String],
+    ) -> Vec<(String, product::ProductSpec)> {
+        let include_products = self.dependency_graph().resolve(include_products);
+        self.select_products(&include_products, exclude_products)
+    }
+
+    /// Selects the included products without the excluded ones, without taking
+    /// dependencies into account.
+    fn select_products(
+        &self,
+        include_products: &[String],
+        exclude_products: &[String],
     ) -> Vec<(String, product::ProductSpec)> {
         self.products
             .iter()
@@ -287,4 +498,205 @@ impl ReleaseSpec {
             .map(|(name, product)| (name.clone(), product.clone()))
             .collect()
     }
+
+    /// Returns the dependencies between the operators of this release. Products
+    /// without explicitly declared dependencies use the built-in ones.
+    /// Dependencies which are not part of the release are ignored.
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        DependencyGraph::new(
+            self.products
+                .iter()
+                .map(|(name, product)| {
+                    let dependencies = product
+                        .depends_on
+                        .clone()
+                        .unwrap_or_else(|| operator::default_dependencies(name))
+                        .into_iter()
+                        .filter(|dependency| self.products.contains_key(dependency))
+                        .collect();
+
+                    (name.clone(), dependencies)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Determines the CRDs of an `operator` by rendering its chart. Charts of
+/// operators which maintain their CRDs themselves don't contain any CRDs, but
+/// grant the operator permission to manage them. If `github_fallback` is
+/// enabled, CRDs missing in the chart are retrieved from GitHub instead.
+#[instrument(skip_all, fields(name = %operator.name))]
+pub async fn resolve_operator_crds(
+    operator: &OperatorSpec,
+    namespace: &str,
+    chart_source: &ChartSourceType,
+    values: &Mapping,
+    github_fallback: bool,
+    helm_client: &helm::Client,
+    transfer_client: &xfer::Client,
+) -> Result<OperatorCrds> {
+    let manifests = operator
+        .template(namespace, chart_source, values, helm_client)
+        .await
+        .context(HelmTemplateSnafu {
+            product_name: &operator.name,
+        })?;
+    let documents = parse_manifests(&manifests).context(DeserializeManifestsSnafu {
+        product_name: &operator.name,
+    })?;
+
+    let crds: Vec<_> = documents
+        .iter()
+        .filter(|document| document.get("kind").and_then(Value::as_str) == Some(CRD_KIND))
+        .collect();
+
+    if !crds.is_empty() {
+        return Ok(OperatorCrds {
+            status: CrdStatus::Replace {
+                source: CrdSource::Chart,
+                names: crd_names(&crds),
+            },
+            manifests: join_manifests(&crds),
+        });
+    }
+
+    if manages_crds(&documents) {
+        return Ok(OperatorCrds {
+            status: CrdStatus::OperatorManaged,
+            manifests: String::new(),
+        });
+    }
+
+    if github_fallback {
+        if let Some(version) = &operator.version {
+            if let Some(manifests) =
+                fetch_crd_manifests(&operator.name, version, transfer_client).await?
+            {
+                let documents = parse_manifests(&manifests).context(DeserializeManifestsSnafu {
+                    product_name: &operator.name,
+                })?;
+                let crds: Vec<_> = documents.iter().collect();
+
+                return Ok(OperatorCrds {
+                    status: CrdStatus::Replace {
+                        source: CrdSource::GitHub,
+                        names: crd_names(&crds),
+                    },
+                    manifests,
+                });
+            }
+        }
+    }
+
+    Ok(OperatorCrds {
+        status: CrdStatus::Missing,
+        manifests: String::new(),
+    })
+}
+
+fn parse_manifests(manifests: &str) -> Result<Vec<Value>, serde_yaml::Error> {
+    let mut documents = Vec::new();
+
+    for document in serde_yaml::Deserializer::from_str(manifests) {
+        let document = Value::deserialize(document)?;
+
+        // Templates which render to nothing result in empty documents
+        if !document.is_null() {
+            documents.push(document);
+        }
+    }
+
+    Ok(documents)
+}
+
+fn crd_names(crds: &[&Value]) -> Vec<String> {
+    crds.iter()
+        .filter_map(|crd| crd.get("metadata")?.get("name")?.as_str())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+fn join_manifests(documents: &[&Value]) -> String {
+    documents
+        .iter()
+        .map(|document| {
+            serde_yaml::to_string(document).expect("serializing a parsed YAML value can't fail")
+        })
+        .collect::<Vec<_>>()
+        .join("---\n")
+}
+
+/// Returns whether any (Cluster)Role in the rendered chart `documents` allows
+/// the operator to create or update CRDs.
+fn manages_crds(documents: &[Value]) -> bool {
+    let contains = |values: Option<&Value>, needles: &[&str]| {
+        values.and_then(Value::as_sequence).is_some_and(|values| {
+            values
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|value| needles.contains(&value))
+        })
+    };
+
+    documents
+        .iter()
+        .filter(|document| {
+            matches!(
+                document.get("kind").and_then(Value::as_str),
+                Some("ClusterRole" | "Role")
+            )
+        })
+        .filter_map(|role| role.get("rules").and_then(Value::as_sequence))
+        .flatten()
+        .any(|rule| {
+            contains(rule.get("resources"), &["customresourcedefinitions", "*"])
+                && contains(rule.get("verbs"), &["create", "update", "patch", "*"])
+        })
+}
+
+/// Retrieves the CRD manifests of an operator in the provided `version` from
+/// the upstream GitHub repository. Returns [`None`] if the operator doesn't
+/// ship a `crds.yaml` file.
+#[instrument(skip(transfer_client))]
+pub async fn fetch_crd_manifests(
+    product_name: &str,
+    version: &Version,
+    transfer_client: &xfer::Client,
+) -> Result<Option<String>> {
+    let release_branch = match version.pre.as_str() {
+        "dev" => "main".to_string(),
+        _ => version.to_string(),
+    };
+
+    let request_url_string = &format!(
+        "https://raw.githubusercontent.com/stackabletech/{product_name}-operator/{release_branch}/deploy/helm/{product_name}-operator/crds/crds.yaml"
+    );
+    let request_url = request_url_string
+        .into_path_or_url()
+        .context(ParsePathOrUrlSnafu {
+            path_or_url: request_url_string,
+        })?;
+
+    // Get CRD manifests from request_url
+    match transfer_client.get(&request_url, &Text).await {
+        Ok(crd_manifests) => Ok(Some(crd_manifests)),
+        Err(crate::xfer::Error::FetchRemoteContent {
+            source: reqwest_error,
+        }) if reqwest_error.status() == Some(StatusCode::NOT_FOUND) => {
+            // Ignore 404, as CRD versioning is rolled out to operators.
+            // Starting with secret-operator 25.11.0, the CRD is maintained by the operator,
+            // making this entire functionality obsolete.
+            // As only some of the operators are migrated yet, some operator crds.yaml's
+            // return a 404, others don't.
+            debug!(
+                product = product_name,
+                // https://opentelemetry.io/docs/specs/semconv/http/http-spans/#http-client-span
+                url.full = request_url_string,
+                "Skipped retrieving CRD, as it doesn't exist in the upstream GitHub repo because the operator most likely uses CRD versioning"
+            );
+            Ok(None)
+        }
+        Err(err) => Err(Error::FileTransfer { source: err }),
+    }
 }
@@ -0,0 +1,425 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use snafu::{ResultExt, Snafu};
+use tracing::{Span, debug, info, instrument};
+use tracing_indicatif::span_ext::IndicatifSpanExt as _;
+
+#[cfg(doc)]
+use crate::platform::release::CrdUpgradeParameters;
+use crate::{
+    PROGRESS_BAR_STYLE, helm,
+    platform::{
+        operator::{self, ChartSourceType, OperatorSpec},
+        release::{self, CrdStatus, ReleaseSpec, resolve_operator_crds},
+    },
+    utils::{operator_chart_name, yaml::values_for_operator},
+    xfer,
+};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("failed to list installed Helm releases"))]
+    ListReleases { source: helm::Error },
+
+    #[snafu(display("failed to retrieve values of Helm release {release_name:?}"))]
+    ReleaseValues {
+        source: helm::Error,
+        release_name: String,
+    },
+
+    #[snafu(display("failed to parse operator spec"))]
+    OperatorSpecParse { source: operator::SpecParseError },
+
+    #[snafu(display("failed to resolve CRDs of {product_name}-operator"))]
+    ResolveCrds {
+        #[snafu(source(from(release::Error, Box::new)))]
+        source: Box<release::Error>,
+        product_name: String,
+    },
+
+    #[snafu(display("failed to read upgrade state from {path:?}"))]
+    ReadState {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to deserialize upgrade state from {path:?}"))]
+    DeserializeState {
+        source: serde_yaml::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to serialize upgrade state"))]
+    SerializeState { source: serde_yaml::Error },
+
+    #[snafu(display("failed to write upgrade state to {path:?}"))]
+    WriteState {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to remove upgrade state at {path:?}"))]
+    RemoveState {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+}
+
+/// The steps of a release upgrade, in the order they are executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpgradeStep {
+    UninstallOperators,
+    ReplaceCrds,
+    InstallOperators,
+}
+
+impl Display for UpgradeStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpgradeStep::UninstallOperators => write!(f, "uninstall operators"),
+            UpgradeStep::ReplaceCrds => write!(f, "replace CRDs"),
+            UpgradeStep::InstallOperators => write!(f, "install operators"),
+        }
+    }
+}
+
+/// Describes what upgrading to a release will do, before anything is changed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradePlan {
+    pub release: String,
+    pub namespace: String,
+    pub operators: Vec<OperatorUpgrade>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorUpgrade {
+    pub name: String,
+
+    /// Currently installed chart version, [`None`] if the operator will be
+    /// newly installed.
+    pub current_version: Option<String>,
+    pub target_version: String,
+
+    /// The CRDs which will be replaced.
+    pub crds: CrdStatus,
+
+    /// Helm values which will change, formatted as `<PATH> (<CHANGE>)`.
+    pub changed_values: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct UpgradeParameters {
+    pub release_name: String,
+
+    /// Operators which are upgraded in addition to the installed ones.
+    pub include_products: Vec<String>,
+    pub exclude_products: Vec<String>,
+    pub operator_namespace: String,
+    pub operator_values: Mapping,
+    pub chart_source: ChartSourceType,
+
+    /// See [`CrdUpgradeParameters::github_fallback`].
+    pub crd_github_fallback: bool,
+}
+
+impl UpgradePlan {
+    /// Computes the upgrade plan for all currently installed operators of the
+    /// release plus the included products, without the excluded products.
+    #[instrument(skip_all, fields(
+        release = %parameters.release_name,
+        namespace = %parameters.operator_namespace,
+        indicatif.pb_show = true
+    ))]
+    pub async fn new(
+        release: &ReleaseSpec,
+        parameters: &UpgradeParameters,
+        helm_client: &helm::Client,
+        transfer_client: &xfer::Client,
+    ) -> Result<Self> {
+        let namespace = &parameters.operator_namespace;
+
+        info!("Planning release upgrade");
+        Span::current().pb_set_style(&PROGRESS_BAR_STYLE);
+
+        let installed_releases = helm_client
+            .list_releases(namespace)
+            .await
+            .context(ListReleasesSnafu)?;
+
+        // Included products are installed along with their dependencies
+        let include_products = release
+            .dependency_graph()
+            .resolve(&parameters.include_products);
+
+        let products: Vec<_> = release
+            .filter_products(&[], &parameters.exclude_products)
+            .into_iter()
+            .filter_map(|(name, product)| {
+                let current_version = installed_releases
+                    .iter()
+                    .find(|release| release.name == operator_chart_name(&name))
+                    .map(|release| release.version.clone());
+
+                (current_version.is_some() || include_products.contains(&name)).then_some((
+                    name,
+                    product,
+                    current_version,
+                ))
+            })
+            .collect();
+
+        Span::current().pb_set_length(products.len() as u64);
+
+        let mut operators = Vec::new();
+
+        for (name, product, current_version) in products {
+            Span::current().pb_set_message(&format!("Planning upgrade of {name}-operator"));
+
+            let operator = OperatorSpec::new(&name, Some(product.version.clone()))
+                .context(OperatorSpecParseSnafu)?;
+
+            let current_values = if current_version.is_some() {
+                helm_client
+                    .release_values(&operator.helm_name(), namespace)
+                    .await
+                    .context(ReleaseValuesSnafu {
+                        release_name: operator.helm_name(),
+                    })?
+            } else {
+                Mapping::new()
+            };
+            let values = values_for_operator(&parameters.operator_values, &name);
+            let crds = resolve_operator_crds(
+                &operator,
+                namespace,
+                &parameters.chart_source,
+                &values,
+                parameters.crd_github_fallback,
+                helm_client,
+                transfer_client,
+            )
+            .await
+            .context(ResolveCrdsSnafu {
+                product_name: &name,
+            })?;
+            let target_values = operator.helm_values(&values);
+
+            operators.push(OperatorUpgrade {
+                name,
+                current_version,
+                target_version: product.version.to_string(),
+                crds: crds.status,
+                changed_values: diff_values(&current_values, &target_values),
+            });
+
+            Span::current().pb_inc(1);
+        }
+
+        Ok(Self {
+            release: parameters.release_name.clone(),
+            namespace: namespace.clone(),
+            operators,
+        })
+    }
+
+    /// Returns the names of all operators which are part of the upgrade.
+    pub fn operators(&self) -> Vec<String> {
+        self.operators
+            .iter()
+            .map(|operator| operator.name.clone())
+            .collect()
+    }
+
+    /// Returns the names of all operators which are currently installed and
+    /// need to be uninstalled first.
+    pub fn installed_operators(&self) -> Vec<String> {
+        self.operators
+            .iter()
+            .filter(|operator| operator.current_version.is_some())
+            .map(|operator| operator.name.clone())
+            .collect()
+    }
+}
+
+/// Progress of an upgrade, which is persisted after every step, so that a
+/// failed upgrade can be resumed. This is required because the installed
+/// operators can't be determined anymore once they were uninstalled.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeState {
+    pub plan: UpgradePlan,
+
+    #[serde(default)]
+    pub completed_steps: Vec<UpgradeStep>,
+
+    /// The step which failed during the last run, if any.
+    pub failed_step: Option<UpgradeStep>,
+}
+
+impl UpgradeState {
+    pub fn new(plan: UpgradePlan) -> Self {
+        Self {
+            plan,
+            completed_steps: Vec::new(),
+            failed_step: None,
+        }
+    }
+
+    /// Loads the upgrade state from `path`. Returns [`None`] if no upgrade is
+    /// in progress.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(path).context(ReadStateSnafu { path })?;
+        let state = serde_yaml::from_str(&contents).context(DeserializeStateSnafu { path })?;
+
+        Ok(Some(state))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        debug!(path = %path.display(), "Saving upgrade state");
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context(WriteStateSnafu { path })?;
+        }
+
+        let contents = serde_yaml::to_string(self).context(SerializeStateSnafu)?;
+        std::fs::write(path, contents).context(WriteStateSnafu { path })
+    }
+
+    /// Removes the upgrade state at `path` once the upgrade completed.
+    pub fn remove(path: &Path) -> Result<()> {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(err).context(RemoveStateSnafu { path })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_completed(&self, step: UpgradeStep) -> bool {
+        self.completed_steps.contains(&step)
+    }
+
+    /// Marks `step` as completed and persists the state.
+    pub fn complete(&mut self, step: UpgradeStep, path: &Path) -> Result<()> {
+        self.completed_steps.push(step);
+        self.failed_step = None;
+        self.save(path)
+    }
+
+    /// Marks `step` as failed and persists the state.
+    pub fn fail(&mut self, step: UpgradeStep, path: &Path) -> Result<()> {
+        self.failed_step = Some(step);
+        self.save(path)
+    }
+}
+
+/// Compares the `current` and `target` Helm values and returns the paths of
+/// all values which will be added, removed or changed.
+fn diff_values(current: &Mapping, target: &Mapping) -> Vec<String> {
+    let mut current_values = BTreeMap::new();
+    let mut target_values = BTreeMap::new();
+    flatten_values(current, "", &mut current_values);
+    flatten_values(target, "", &mut target_values);
+
+    let mut changes = Vec::new();
+
+    for (path, value) in &current_values {
+        match target_values.get(path) {
+            Some(target_value) if target_value != value => {
+                changes.push(format!("{path} (changed)"))
+            }
+            Some(_) => {}
+            None => changes.push(format!("{path} (removed)")),
+        }
+    }
+
+    for path in target_values.keys() {
+        if !current_values.contains_key(path) {
+            changes.push(format!("{path} (added)"));
+        }
+    }
+
+    changes.sort();
+    changes
+}
+
+fn flatten_values<'a>(values: &'a Mapping, prefix: &str, flat: &mut BTreeMap<String, &'a Value>) {
+    for (key, value) in values {
+        let key = match key {
+            Value::String(key) => key.clone(),
+            other => serde_yaml::to_string(other)
+                .unwrap_or_default()
+                .trim_end()
+                .to_owned(),
+        };
+        let path = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match value {
+            Value::Mapping(mapping) if !mapping.is_empty() => flatten_values(mapping, &path, flat),
+            value => {
+                flat.insert(path, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_helm_values() {
+        let current: Mapping = serde_yaml::from_str(
+            "
+image:
+  repository: oci.stackable.tech/sdp/airflow-operator
+  pullPolicy: IfNotPresent
+resources:
+  limits:
+    memory: 128Mi
+",
+        )
+        .unwrap();
+        let target: Mapping = serde_yaml::from_str(
+            "
+image:
+  repository: oci.stackable.tech/sdp/airflow-operator
+resources:
+  limits:
+    memory: 256Mi
+podAnnotations:
+  foo: bar
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            diff_values(&current, &target),
+            vec![
+                "image.pullPolicy (removed)",
+                "podAnnotations.foo (added)",
+                "resources.limits.memory (changed)",
+            ]
+        );
+        assert!(diff_values(&current, &current).is_empty());
+    }
+}
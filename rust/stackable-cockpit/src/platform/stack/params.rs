@@ -17,6 +17,10 @@ pub struct StackInstallParameters {
 
     pub parameters: Vec<String>,
     pub skip_release: bool,
+
+    /// Only warn instead of failing if the stack or release doesn't support the
+    /// Kubernetes version of the cluster.
+    pub skip_kubernetes_version_check: bool,
     pub labels: Labels,
     pub chart_source: ChartSourceType,
     pub operator_values: Mapping,
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use semver::VersionReq;
 use serde::{Deserialize, Serialize};
 use serde_yaml::Mapping;
 use snafu::{OptionExt, ResultExt, Snafu};
@@ -9,11 +12,13 @@ use utoipa::ToSchema;
 
 use crate::{
     common::manifest::ManifestSpec,
+    helm,
     platform::{
-        cluster::{ResourceRequests, ResourceRequestsError},
+        cluster::{self, KubernetesVersionError, ResourceRequests, ResourceRequestsError},
         manifests::{self, InstallManifestsExt},
         namespace,
         operator::ChartSourceType,
+        plan::{self, InstallPlan},
         release,
         stack::{StackInstallParameters, StackUninstallParameters},
     },
@@ -53,6 +58,14 @@ pub enum Error {
     #[snafu(display("stack resource requests error"), context(false))]
     StackResourceRequests { source: ResourceRequestsError },
 
+    #[snafu(display("stack Kubernetes version error"), context(false))]
+    StackKubernetesVersion { source: KubernetesVersionError },
+
+    /// This error indicates that the release doesn't support the Kubernetes
+    /// version of the cluster.
+    #[snafu(display("release prerequisites are not met"))]
+    ReleasePrerequisites { source: release::Error },
+
     /// This error indicates that the stack doesn't support being installed in
     /// the provided namespace.
     #[snafu(display("unable install stack in namespace {requested:?}, only {supported:?} supported", supported = supported.join(", ")))]
@@ -70,6 +83,12 @@ pub enum Error {
     #[snafu(display("failed to install stack manifests"))]
     InstallManifests { source: manifests::Error },
 
+    #[snafu(display("failed to render stack manifests"))]
+    RenderManifests { source: manifests::Error },
+
+    #[snafu(display("failed to build install plan"))]
+    BuildPlan { source: plan::Error },
+
     #[snafu(display("failed to uninstall Helm manifests"))]
     UninstallHelmManifests { source: manifests::Error },
 
@@ -112,6 +131,12 @@ pub struct StackSpec {
     /// The resource requests the stack imposes on a Kubernetes cluster
     pub resource_requests: Option<ResourceRequests>,
 
+    /// Kubernetes versions this stack supports, e.g. `>=1.29`. If not set, the
+    /// stack is assumed to support every Kubernetes version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub supported_kubernetes_versions: Option<VersionReq>,
+
     /// A variable number of supported parameters
     #[serde(default)]
     pub parameters: Vec<StackParameter>,
@@ -125,14 +150,22 @@ impl StackSpec {
     ///
     /// - Does the stack support to be installed in the requested namespace?
     /// - Does the cluster have enough resources available to run this stack?
+    /// - Do the stack and its release (if installed) support the Kubernetes
+    ///   version of the cluster?
     #[instrument(skip_all)]
-    pub async fn check_prerequisites(&self, client: &Client, namespace: &str) -> Result<(), Error> {
+    pub async fn check_prerequisites(
+        &self,
+        release_list: &release::ReleaseList,
+        client: &Client,
+        install_parameters: &StackInstallParameters,
+    ) -> Result<(), Error> {
         debug!("Checking prerequisites before installing stack");
 
         // Returns an error if the stack doesn't support to be installed in the
         // requested product namespace. When installing a demo, this check is
         // already done on the demo spec level, however we still need to check
         // here, as stacks can be installed on their own.
+        let namespace = &install_parameters.stack_namespace;
         if !self.supports_namespace(namespace) {
             return Err(Error::UnsupportedNamespace {
                 supported: self.supported_namespaces.clone(),
@@ -158,6 +191,37 @@ impl StackSpec {
             }
         }
 
+        if let Some(supported_versions) = &self.supported_kubernetes_versions {
+            cluster::check_kubernetes_version(
+                client,
+                supported_versions,
+                &format!(
+                    "stack {stack_name}",
+                    stack_name = install_parameters.stack_name
+                ),
+                install_parameters.skip_kubernetes_version_check,
+            )
+            .await?;
+        }
+
+        // The release is only checked if it is installed as part of the stack
+        if !install_parameters.skip_release {
+            let release = release_list
+                .get(&self.release)
+                .context(NoSuchReleaseSnafu {
+                    name: self.release.clone(),
+                })?;
+
+            release
+                .check_prerequisites(
+                    &self.release,
+                    client,
+                    install_parameters.skip_kubernetes_version_check,
+                )
+                .await
+                .context(ReleasePrerequisitesSnafu)?;
+        }
+
         Ok(())
     }
 
@@ -171,10 +235,11 @@ impl StackSpec {
         release_list: release::ReleaseList,
         install_parameters: StackInstallParameters,
         client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         // First, we check if the prerequisites are met
-        self.check_prerequisites(client, &install_parameters.stack_namespace)
+        self.check_prerequisites(&release_list, client, &install_parameters)
             .await?;
 
         // Second, we install the release if not opted out
@@ -190,6 +255,7 @@ impl StackSpec {
                 &install_parameters.operator_namespace,
                 &install_parameters.chart_source,
                 &install_parameters.operator_values,
+                helm_client,
             )
             .await?;
         }
@@ -203,7 +269,7 @@ impl StackSpec {
             })?;
 
         // Finally install the stack manifests
-        self.prepare_manifests(install_parameters, client, transfer_client)
+        self.prepare_manifests(install_parameters, client, helm_client, transfer_client)
             .await
     }
 
@@ -216,6 +282,7 @@ impl StackSpec {
         release_list: release::ReleaseList,
         uninstall_parameters: StackUninstallParameters,
         client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         // Uninstall Helm Charts
@@ -233,6 +300,7 @@ impl StackSpec {
             &self.manifests,
             parameters,
             &uninstall_parameters.stack_namespace.to_owned(),
+            helm_client,
             transfer_client,
         )
         .await
@@ -256,8 +324,12 @@ impl StackSpec {
 
         // Delete operators and the operator namespace
         if !uninstall_parameters.skip_operators {
-            self.uninstall_release(release_list, &uninstall_parameters.operator_namespace)
-                .await?;
+            self.uninstall_release(
+                release_list,
+                &uninstall_parameters.operator_namespace,
+                helm_client,
+            )
+            .await?;
 
             client
                 .delete_namespace(uninstall_parameters.operator_namespace)
@@ -283,6 +355,7 @@ impl StackSpec {
         operator_namespace: &str,
         chart_source: &ChartSourceType,
         operator_values: &Mapping,
+        helm_client: &helm::Client,
     ) -> Result<(), Error> {
         info!(self.release, "Trying to install release");
         Span::current().pb_set_message("Installing operators");
@@ -294,15 +367,16 @@ impl StackSpec {
                 name: self.release.clone(),
             })?;
 
+        let parameters = release::ReleaseInstallParameters {
+            operator_namespace: operator_namespace.to_owned(),
+            chart_source: chart_source.clone(),
+            operator_values: operator_values.clone(),
+            force: false,
+        };
+
         // Install the release
         release
-            .install(
-                &self.operators,
-                &[],
-                operator_namespace,
-                chart_source,
-                operator_values,
-            )
+            .install(&self.operators, &[], &parameters, helm_client)
             .await
             .context(InstallReleaseSnafu)
     }
@@ -312,6 +386,7 @@ impl StackSpec {
         &self,
         release_list: release::ReleaseList,
         operator_namespace: &str,
+        helm_client: &helm::Client,
     ) -> Result<(), Error> {
         info!(self.release, "Trying to uninstall release");
         Span::current().pb_set_message("Uninstalling operators");
@@ -325,7 +400,8 @@ impl StackSpec {
 
         // Uninstall the release
         release
-            .uninstall(&self.operators, &[], operator_namespace)
+            .uninstall(&self.operators, &[], operator_namespace, helm_client)
+            .await
             .context(UninstallReleaseSnafu)
     }
 
@@ -334,22 +410,13 @@ impl StackSpec {
         &self,
         install_parameters: StackInstallParameters,
         client: &Client,
+        helm_client: &helm::Client,
         transfer_client: &xfer::Client,
     ) -> Result<(), Error> {
         info!("Installing stack manifests");
         Span::current().pb_set_message("Installing manifests");
 
-        let mut parameters = install_parameters
-            .parameters
-            .to_owned()
-            .into_params(&self.parameters)
-            .context(ParseParametersSnafu)?;
-
-        // We add the STACK and optionally DEMO parameter, so that stacks can use that to render e.g. the stack label
-        parameters.insert("STACK".to_owned(), install_parameters.stack_name);
-        if let Some(demo_name) = install_parameters.demo_name {
-            parameters.insert("DEMO".to_owned(), demo_name);
-        }
+        let parameters = self.manifest_parameters(&install_parameters)?;
 
         Self::install_manifests(
             &self.manifests,
@@ -357,12 +424,92 @@ impl StackSpec {
             &install_parameters.stack_namespace,
             install_parameters.labels,
             client,
+            helm_client,
             transfer_client,
         )
         .await
         .context(InstallManifestsSnafu)
     }
 
+    /// Builds an [`InstallPlan`] which describes what [`StackSpec::install`]
+    /// would do with the same parameters. This doesn't access the cluster,
+    /// which is why the cluster resource requests are not validated.
+    #[instrument(skip_all, fields(
+        stack_name = %install_parameters.stack_name,
+        stack_namespace = %install_parameters.stack_namespace,
+    ))]
+    pub async fn plan(
+        &self,
+        release_list: &release::ReleaseList,
+        install_parameters: &StackInstallParameters,
+        transfer_client: &xfer::Client,
+    ) -> Result<InstallPlan, Error> {
+        debug!("Building install plan for stack");
+
+        if !self.supports_namespace(&install_parameters.stack_namespace) {
+            return Err(Error::UnsupportedNamespace {
+                supported: self.supported_namespaces.clone(),
+                requested: install_parameters.stack_namespace.clone(),
+            });
+        }
+
+        let mut plan = InstallPlan::default();
+
+        if !install_parameters.skip_release {
+            let release = release_list
+                .get(&self.release)
+                .context(NoSuchReleaseSnafu {
+                    name: self.release.clone(),
+                })?;
+
+            plan.add_namespace(&install_parameters.operator_namespace);
+            plan.add_release(
+                release,
+                &self.operators,
+                &[],
+                &install_parameters.operator_namespace,
+                &install_parameters.chart_source,
+            );
+        }
+
+        plan.add_namespace(&install_parameters.stack_namespace);
+
+        let parameters = self.manifest_parameters(install_parameters)?;
+        let manifests = Self::render_manifests(
+            &self.manifests,
+            &parameters,
+            &install_parameters.stack_namespace,
+            transfer_client,
+        )
+        .await
+        .context(RenderManifestsSnafu)?;
+
+        plan.add_manifests(&manifests, &install_parameters.stack_namespace)
+            .context(BuildPlanSnafu)?;
+
+        Ok(plan)
+    }
+
+    /// Returns the parameters used to render the stack manifests.
+    fn manifest_parameters(
+        &self,
+        install_parameters: &StackInstallParameters,
+    ) -> Result<HashMap<String, String>, Error> {
+        let mut parameters = install_parameters
+            .parameters
+            .to_owned()
+            .into_params(&self.parameters)
+            .context(ParseParametersSnafu)?;
+
+        // We add the STACK and optionally DEMO parameter, so that stacks can use that to render e.g. the stack label
+        parameters.insert("STACK".to_owned(), install_parameters.stack_name.clone());
+        if let Some(demo_name) = &install_parameters.demo_name {
+            parameters.insert("DEMO".to_owned(), demo_name.clone());
+        }
+
+        Ok(parameters)
+    }
+
     fn supports_namespace(&self, namespace: impl Into<String>) -> bool {
         self.supported_namespaces.is_empty()
             || self.supported_namespaces.contains(&namespace.into())
@@ -14,4 +14,9 @@ pub struct ChartSourceMetadata {
 pub struct ChartSourceEntry {
     pub name: String,
     pub version: String,
+
+    /// Digest of the packaged chart, which is only available for
+    /// index.yaml-based repositories.
+    #[serde(default)]
+    pub digest: Option<String>,
 }
@@ -1,5 +1,7 @@
 use std::{collections::BTreeMap, ops::Deref, string::FromUtf8Error};
 
+use futures::{AsyncBufReadExt as _, Stream};
+use semver::Version;
 use serde::Deserialize;
 use snafu::{OptionExt, ResultExt, Snafu};
 use stackable_operator::{
@@ -7,14 +9,14 @@ use stackable_operator::{
     k8s_openapi::{
         Resource,
         api::{
-            apps::v1::{Deployment, StatefulSet},
-            core::v1::{Endpoints, Namespace, Node, Secret, Service},
+            apps::v1::{DaemonSet, Deployment, StatefulSet},
+            core::v1::{Endpoints, Namespace, Node, Pod, Secret, Service},
         },
         apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
     },
     kube::{
         self, Api, Discovery, ResourceExt,
-        api::{DeleteParams, ListParams, Patch, PatchParams, PostParams},
+        api::{DeleteParams, ListParams, LogParams, Patch, PatchParams, PostParams},
         core::{DynamicObject, GroupVersionKind, ObjectList, ObjectMeta, TypeMeta},
         discovery::{self, ApiCapabilities, ApiResource, Scope},
     },
@@ -42,6 +44,12 @@ pub enum Error {
     #[snafu(display("failed to fetch data from Kubernetes API"))]
     KubeClientFetch { source: kube::error::Error },
 
+    #[snafu(display("failed to stream logs of pod {pod:?}"))]
+    StreamPodLogs {
+        source: kube::error::Error,
+        pod: String,
+    },
+
     #[snafu(display("failed to patch/create Kubernetes object"))]
     KubeClientPatch { source: kube::error::Error },
 
@@ -128,7 +136,15 @@ impl Client {
         let labels: BTreeMap<String, String> = labels.into();
 
         for manifest in serde_yaml::Deserializer::from_str(manifests) {
-            let mut object = DynamicObject::deserialize(manifest).context(DeserializeYamlSnafu)?;
+            // Skip empty documents, like the install plan does
+            let manifest =
+                serde_yaml::Value::deserialize(manifest).context(DeserializeYamlSnafu)?;
+            if manifest.is_null() {
+                continue;
+            }
+
+            let mut object: DynamicObject =
+                serde_yaml::from_value(manifest).context(DeserializeYamlSnafu)?;
 
             // Add our own labels to the object
             object.labels_mut().extend(labels.clone());
@@ -530,6 +546,77 @@ impl Client {
         Ok(stateful_sets)
     }
 
+    /// Lists [`DaemonSet`]s by matching labels. [`ListParamsExt`] provides a
+    /// utility function to create [`ListParams`] based on a app name and other
+    /// labels.
+    pub async fn list_daemon_sets(
+        &self,
+        namespace: Option<&str>,
+        list_params: &ListParams,
+    ) -> ListResult<DaemonSet> {
+        let daemon_set_api: Api<DaemonSet> = match namespace {
+            Some(namespace) => Api::namespaced(self.client.clone(), namespace),
+            None => Api::all(self.client.clone()),
+        };
+
+        let daemon_sets = daemon_set_api
+            .list(list_params)
+            .await
+            .context(KubeClientFetchSnafu)?;
+
+        Ok(daemon_sets)
+    }
+
+    /// Lists [`Pod`]s by matching labels.
+    pub async fn list_pods(
+        &self,
+        namespace: Option<&str>,
+        list_params: &ListParams,
+    ) -> ListResult<Pod> {
+        let pod_api: Api<Pod> = match namespace {
+            Some(namespace) => Api::namespaced(self.client.clone(), namespace),
+            None => Api::all(self.client.clone()),
+        };
+
+        let pods = pod_api
+            .list(list_params)
+            .await
+            .context(KubeClientFetchSnafu)?;
+
+        Ok(pods)
+    }
+
+    /// Streams the logs of the container selected by `log_params` of the
+    /// [`Pod`] with `pod_name` line by line. If requested, the stream follows
+    /// the logs until the container terminates.
+    pub async fn stream_pod_logs(
+        &self,
+        namespace: &str,
+        pod_name: &str,
+        log_params: &LogParams,
+    ) -> Result<impl Stream<Item = std::io::Result<String>> + Send> {
+        let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+
+        let logs = pod_api
+            .log_stream(pod_name, log_params)
+            .await
+            .context(StreamPodLogsSnafu { pod: pod_name })?;
+
+        Ok(logs.lines())
+    }
+
+    /// Lists all [`CustomResourceDefinition`]s in the cluster.
+    pub async fn list_crds(&self) -> ListResult<CustomResourceDefinition> {
+        let crd_api: Api<CustomResourceDefinition> = Api::all(self.client.clone());
+
+        let crds = crd_api
+            .list(&ListParams::default())
+            .await
+            .context(KubeClientFetchSnafu)?;
+
+        Ok(crds)
+    }
+
     pub async fn list_nodes(&self) -> ListResult<Node> {
         let node_api: Api<Node> = Api::all(self.client.clone());
 
@@ -603,13 +690,32 @@ impl Client {
         Ok(())
     }
 
-    /// Retrieves [`ClusterInfo`][cluster::ClusterInfo] which contains resource
-    /// information for the current cluster. It should be noted that
-    /// [`ClusterInfo`][cluster::ClusterInfo] contains data about allocatable
-    /// resources. These values don't reflect currently available resources.
+    /// Retrieves [`ClusterInfo`][cluster::ClusterInfo] which contains version
+    /// and resource information for the current cluster. It should be noted
+    /// that [`ClusterInfo`][cluster::ClusterInfo] contains data about
+    /// allocatable resources. These values don't reflect currently available
+    /// resources.
     pub async fn get_cluster_info(&self) -> Result<cluster::ClusterInfo> {
         let nodes = self.list_nodes().await?;
-        cluster::ClusterInfo::from_nodes(nodes).context(ClusterInformationSnafu)
+        let version_info = self
+            .client
+            .apiserver_version()
+            .await
+            .context(KubeClientFetchSnafu)?;
+
+        cluster::ClusterInfo::new(nodes, &version_info).context(ClusterInformationSnafu)
+    }
+
+    /// Retrieves the version of the Kubernetes API server, see
+    /// [`parse_kubernetes_version`][cluster::parse_kubernetes_version].
+    pub async fn get_kubernetes_version(&self) -> Result<Version> {
+        let version_info = self
+            .client
+            .apiserver_version()
+            .await
+            .context(KubeClientFetchSnafu)?;
+
+        cluster::parse_kubernetes_version(&version_info).context(ClusterInformationSnafu)
     }
 
     pub async fn get_endpoints(&self, namespace: &str, name: &str) -> Result<Endpoints> {
@@ -1,4 +1,13 @@
 use serde_yaml::{Mapping, Value};
+use snafu::{Snafu, ensure};
+
+#[derive(Debug, Snafu)]
+#[snafu(display(
+    "invalid operator value override {expression:?}, expected the form <OPERATOR>-operator.<KEY>=<VALUE>"
+))]
+pub struct InvalidSetExpressionError {
+    pub expression: String,
+}
 
 /// Extracts the Helm values for a specific operator from the operator values mapping.
 ///
@@ -11,3 +20,184 @@ pub fn values_for_operator(operator_values: &Mapping, operator_name: &str) -> Ma
         .cloned()
         .unwrap_or_default()
 }
+
+/// Merges the `overrides` into the `base` values. Nested mappings are merged
+/// recursively, all other values (including sequences) are replaced.
+pub fn merge_values(base: &mut Mapping, overrides: &Mapping) {
+    for (key, value) in overrides {
+        match (base.get_mut(key), value) {
+            (Some(Value::Mapping(base_mapping)), Value::Mapping(override_mapping)) => {
+                merge_values(base_mapping, override_mapping)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Applies a Helm `--set` style override like
+/// `airflow-operator.image.repository=example.com/airflow-operator` to the
+/// operator values. Missing (or non-mapping) parent keys are created.
+///
+/// Dots in keys can be escaped using a backslash, e.g.
+/// `airflow-operator.podAnnotations.example\.com/team=data`. Values are parsed
+/// as YAML scalars, so `true` and `2` result in a boolean and an integer.
+pub fn set_value(
+    operator_values: &mut Mapping,
+    expression: &str,
+) -> Result<(), InvalidSetExpressionError> {
+    let invalid = || InvalidSetExpressionSnafu { expression };
+
+    let Some((key, value)) = expression.split_once('=') else {
+        return invalid().fail();
+    };
+
+    let path = split_key(key);
+    ensure!(
+        path.len() >= 2
+            && path[0].ends_with("-operator")
+            && path.iter().all(|segment| !segment.is_empty()),
+        invalid()
+    );
+
+    let (last, parents) = path.split_last().expect("path has at least two segments");
+    let mut current = operator_values;
+
+    for segment in parents {
+        let segment = Value::String(segment.clone());
+        if !current.get(&segment).is_some_and(Value::is_mapping) {
+            current.insert(segment.clone(), Value::Mapping(Mapping::new()));
+        }
+
+        current = current
+            .get_mut(&segment)
+            .and_then(Value::as_mapping_mut)
+            .expect("parent key was just set to a mapping");
+    }
+
+    current.insert(Value::String(last.clone()), parse_scalar(value));
+    Ok(())
+}
+
+/// Splits a dot-separated key into its segments, taking escaped dots into
+/// account.
+fn split_key(key: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut chars = key.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        let segment = segments.last_mut().expect("segments are never empty");
+
+        match char {
+            '\\' if chars.peek() == Some(&'.') => {
+                segment.push('.');
+                chars.next();
+            }
+            '.' => segments.push(String::new()),
+            char => segment.push(char),
+        }
+    }
+
+    segments
+}
+
+fn parse_scalar(value: &str) -> Value {
+    if value.is_empty() {
+        return Value::String(String::new());
+    }
+
+    match serde_yaml::from_str(value) {
+        Ok(value @ (Value::Null | Value::Bool(_) | Value::Number(_))) => value,
+        _ => Value::String(value.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn merge_nested_values() {
+        let mut base: Mapping = serde_yaml::from_str(
+            "
+image:
+  repository: oci.stackable.tech/sdp/airflow-operator
+  pullPolicy: IfNotPresent
+tolerations:
+  - key: a
+",
+        )
+        .unwrap();
+        let overrides: Mapping = serde_yaml::from_str(
+            "
+image:
+  pullPolicy: Always
+tolerations: []
+",
+        )
+        .unwrap();
+
+        merge_values(&mut base, &overrides);
+
+        let expected: Mapping = serde_yaml::from_str(
+            "
+image:
+  repository: oci.stackable.tech/sdp/airflow-operator
+  pullPolicy: Always
+tolerations: []
+",
+        )
+        .unwrap();
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn set_nested_values() {
+        let mut values: Mapping = serde_yaml::from_str(
+            "
+airflow-operator:
+  image:
+    repository: oci.stackable.tech/sdp/airflow-operator
+  replicas: 1
+",
+        )
+        .unwrap();
+
+        set_value(&mut values, "airflow-operator.image.pullPolicy=Always").unwrap();
+        set_value(&mut values, "airflow-operator.replicas=2").unwrap();
+        set_value(
+            &mut values,
+            r"airflow-operator.podAnnotations.example\.com/team=data",
+        )
+        .unwrap();
+        set_value(&mut values, "trino-operator.enabled=true").unwrap();
+
+        let expected: Mapping = serde_yaml::from_str(
+            "
+airflow-operator:
+  image:
+    repository: oci.stackable.tech/sdp/airflow-operator
+    pullPolicy: Always
+  replicas: 2
+  podAnnotations:
+    example.com/team: data
+trino-operator:
+  enabled: true
+",
+        )
+        .unwrap();
+        assert_eq!(values, expected);
+    }
+
+    #[rstest]
+    #[case("airflow-operator.image.repository")]
+    #[case("airflow-operator=foo")]
+    #[case("airflow.image.repository=foo")]
+    #[case("airflow-operator..repository=foo")]
+    fn set_invalid_expression(#[case] expression: &str) {
+        assert!(set_value(&mut Mapping::new(), expression).is_err());
+    }
+}
@@ -90,6 +90,25 @@ impl Client {
         }
     }
 
+    /// Retrieves the content cached for `url`, or [`None`] if the cache missed
+    /// or is expired. Together with [`Client::store_cached`], this allows
+    /// caching remote content which can't be retrieved by this client, e.g.
+    /// because it requires authentication.
+    pub async fn get_cached(&self, url: &Url) -> Result<Option<String>> {
+        match self.cache.retrieve(url).await.context(CacheRetrieveSnafu)? {
+            Status::Hit(content) => Ok(Some(content)),
+            Status::Expired | Status::Miss => Ok(None),
+        }
+    }
+
+    /// Stores the `content` retrieved from `url` in the cache.
+    pub async fn store_cached(&self, url: &Url, content: &str) -> Result<()> {
+        self.cache
+            .store(url, content)
+            .await
+            .context(CacheStoreSnafu)
+    }
+
     /// Lists all currently cached files.
     ///
     /// This function does not make any requests to remote resources.
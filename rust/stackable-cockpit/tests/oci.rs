@@ -0,0 +1,179 @@
+use std::{collections::HashMap, net::SocketAddr};
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde_json::json;
+use stackable_cockpit::{
+    constants::{HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST},
+    helm::RepositoryAuth,
+    oci::{self, OciRegistry, RegistryApi},
+};
+
+const TAGS_PATH: &str = "/v2/sdp-charts/airflow-operator/tags/list";
+const CATALOG_PATH: &str = "/v2/_catalog";
+const TOKEN: &str = "registry-token";
+
+/// Starts an OCI distribution registry stand-in, which serves the `router` on
+/// a random local port.
+async fn start_registry(router: Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    address
+}
+
+fn registry(address: SocketAddr) -> OciRegistry {
+    OciRegistry {
+        host: address.to_string(),
+        project: "sdp-charts".to_owned(),
+        api: RegistryApi::Distribution,
+        plain_http: true,
+    }
+}
+
+/// Returns the tags of the `airflow-operator` chart in two pages.
+async fn tags(Query(query): Query<HashMap<String, String>>) -> Response {
+    match query.get("last") {
+        None => (
+            [(
+                header::LINK,
+                format!("<{TAGS_PATH}?n=2&last=25.3.0-pr123>; rel=\"next\""),
+            )],
+            Json(json!({
+                "name": "sdp-charts/airflow-operator",
+                "tags": ["24.11.1", "25.3.0-pr123"]
+            })),
+        )
+            .into_response(),
+        Some(_) => Json(json!({
+            "name": "sdp-charts/airflow-operator",
+            "tags": ["25.3.0", "0.0.0-dev"]
+        }))
+        .into_response(),
+    }
+}
+
+/// Returns the repositories of the registry in two pages.
+async fn catalog(Query(query): Query<HashMap<String, String>>) -> Response {
+    match query.get("last") {
+        None => (
+            [(
+                header::LINK,
+                format!("<{CATALOG_PATH}?n=2&last=other/hello-operator>; rel=\"next\""),
+            )],
+            Json(json!({
+                "repositories": ["other/hello-operator", "sdp-charts/airflow-operator"]
+            })),
+        )
+            .into_response(),
+        Some(_) => Json(json!({
+            "repositories": ["sdp-charts/commons", "sdp-charts/trino-operator"]
+        }))
+        .into_response(),
+    }
+}
+
+/// Like [`tags`], but requires a bearer token, which can be requested from
+/// the token endpoint announced in the challenge (`realm`).
+async fn authorized_tags(
+    State(realm): State<String>,
+    headers: HeaderMap,
+    query: Query<HashMap<String, String>>,
+) -> Response {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    if authorization != Some(format!("Bearer {TOKEN}").as_str()) {
+        let challenge = format!(
+            "Bearer realm=\"{realm}\",service=\"registry\",scope=\"repository:sdp-charts/airflow-operator:pull\""
+        );
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, challenge)],
+        )
+            .into_response();
+    }
+
+    tags(query).await
+}
+
+async fn token(Query(query): Query<HashMap<String, String>>) -> Response {
+    if query.get("service").map(String::as_str) != Some("registry") {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    Json(json!({ "token": TOKEN })).into_response()
+}
+
+fn versions(
+    index: &HashMap<&str, stackable_cockpit::utils::chartsource::ChartSourceMetadata>,
+    repo_name: &str,
+) -> Vec<String> {
+    index[repo_name]
+        .entries
+        .get("airflow-operator")
+        .map(|entries| entries.iter().map(|e| e.version.clone()).collect())
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn list_paginated_distribution_tags() {
+    let address = start_registry(Router::new().route(TAGS_PATH, get(tags))).await;
+
+    let index = oci::get_oci_index(&registry(address), &RepositoryAuth::default())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        versions(&index, HELM_REPO_NAME_STABLE),
+        ["24.11.1", "25.3.0"]
+    );
+    assert_eq!(versions(&index, HELM_REPO_NAME_TEST), ["25.3.0-pr123"]);
+    assert_eq!(versions(&index, HELM_REPO_NAME_DEV), ["0.0.0-dev"]);
+
+    // Operators which are not mirrored are skipped
+    assert!(index.values().all(|repo| repo.entries.len() <= 1));
+}
+
+#[tokio::test]
+async fn list_paginated_distribution_catalog() {
+    let address = start_registry(Router::new().route(CATALOG_PATH, get(catalog))).await;
+
+    let operators = oci::get_operators(&registry(address), &RepositoryAuth::default())
+        .await
+        .unwrap();
+
+    // Only the operator charts of the project are returned
+    assert_eq!(operators, ["airflow", "trino"]);
+}
+
+#[tokio::test]
+async fn request_bearer_token_on_challenge() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let realm = format!("http://{address}/token");
+
+    let router = Router::new()
+        .route(TAGS_PATH, get(authorized_tags))
+        .route("/token", get(token))
+        .with_state(realm);
+
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    let index = oci::get_oci_index(&registry(address), &RepositoryAuth::default())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        versions(&index, HELM_REPO_NAME_STABLE),
+        ["24.11.1", "25.3.0"]
+    );
+}
@@ -0,0 +1,263 @@
+use serde_yaml::Mapping;
+use stackable_cockpit::{
+    helm::{self, FakeBackend, FakeCall},
+    oci::OciRegistry,
+    platform::{
+        operator::{ChartSourceType, OperatorSpec},
+        release::{
+            CrdSource, CrdStatus, ReleaseInstallParameters, ReleaseSpec, resolve_operator_crds,
+        },
+    },
+    xfer::{self, cache::Settings},
+};
+
+const OPERATOR_NAMESPACE: &str = "stackable-operators";
+
+fn install_parameters(force: bool) -> ReleaseInstallParameters {
+    ReleaseInstallParameters {
+        operator_namespace: OPERATOR_NAMESPACE.to_owned(),
+        chart_source: ChartSourceType::OCI(OciRegistry::default()),
+        operator_values: Mapping::new(),
+        force,
+    }
+}
+
+fn release() -> ReleaseSpec {
+    serde_yaml::from_str(
+        r#"
+releaseDate: "2025-11-12"
+description: Test release
+products:
+  airflow:
+    operatorVersion: 25.11.0
+  zookeeper:
+    operatorVersion: 25.11.0
+"#,
+    )
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn install_and_uninstall_release() {
+    let backend = FakeBackend::default();
+    let helm_client = helm::Client::new(backend.clone());
+    let release = release();
+
+    release
+        .install(
+            &[],
+            &["zookeeper".to_owned()],
+            &install_parameters(false),
+            &helm_client,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        backend
+            .release_version(OPERATOR_NAMESPACE, "airflow-operator")
+            .as_deref(),
+        Some("25.11.0")
+    );
+    assert_eq!(
+        backend.release_version(OPERATOR_NAMESPACE, "zookeeper-operator"),
+        None
+    );
+    assert!(backend.calls().contains(&FakeCall::InstallRelease {
+        release_name: "airflow-operator".to_owned(),
+        chart_name: "oci://oci.stackable.tech/sdp-charts/airflow-operator".to_owned(),
+        chart_version: "25.11.0".to_owned(),
+        namespace: OPERATOR_NAMESPACE.to_owned(),
+    }));
+
+    release
+        .uninstall(&[], &[], OPERATOR_NAMESPACE, &helm_client)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        backend.release_version(OPERATOR_NAMESPACE, "airflow-operator"),
+        None
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn forced_install_upgrades_installed_operators() {
+    let backend =
+        FakeBackend::default().with_release(OPERATOR_NAMESPACE, "airflow-operator", "25.7.0");
+    let helm_client = helm::Client::new(backend.clone());
+    let release = release();
+
+    release
+        .install(
+            &["airflow".to_owned()],
+            &[],
+            &install_parameters(true),
+            &helm_client,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        backend
+            .release_version(OPERATOR_NAMESPACE, "airflow-operator")
+            .as_deref(),
+        Some("25.11.0")
+    );
+    assert!(backend.calls().contains(&FakeCall::UpgradeRelease {
+        release_name: "airflow-operator".to_owned(),
+        chart_name: "oci://oci.stackable.tech/sdp-charts/airflow-operator".to_owned(),
+        chart_version: "25.11.0".to_owned(),
+        namespace: OPERATOR_NAMESPACE.to_owned(),
+        force: true,
+    }));
+}
+
+#[test]
+fn override_product_versions() {
+    let release = release()
+        .with_version_overrides(&[
+            "airflow=25.11.1".parse().unwrap(),
+            "zookeeper".parse().unwrap(),
+        ])
+        .unwrap();
+
+    assert_eq!(release.products["airflow"].version.to_string(), "25.11.1");
+    assert_eq!(release.products["zookeeper"].version.to_string(), "25.11.0");
+    assert!(
+        release
+            .with_version_overrides(&["trino=0.0.0-dev".parse().unwrap()])
+            .is_err()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn install_dependencies_in_waves() {
+    let backend = FakeBackend::default();
+    let helm_client = helm::Client::new(backend.clone());
+    let release: ReleaseSpec = serde_yaml::from_str(
+        r#"
+releaseDate: "2025-11-12"
+description: Test release
+products:
+  airflow:
+    operatorVersion: 25.11.0
+  commons:
+    operatorVersion: 25.11.0
+  listener:
+    operatorVersion: 25.11.0
+  secret:
+    operatorVersion: 25.11.0
+  trino:
+    operatorVersion: 25.11.0
+    dependsOn: [commons, secret, listener, opa]
+"#,
+    )
+    .unwrap();
+
+    release
+        .install(
+            &["trino".to_owned()],
+            &[],
+            &install_parameters(false),
+            &helm_client,
+        )
+        .await
+        .unwrap();
+
+    let installed: Vec<String> = backend
+        .calls()
+        .into_iter()
+        .filter_map(|call| match call {
+            FakeCall::InstallRelease { release_name, .. } => Some(release_name),
+            _ => None,
+        })
+        .collect();
+
+    // The dependencies are installed before trino, the unrelated airflow
+    // operator and opa (which is not part of the release) are skipped
+    assert_eq!(installed.len(), 4);
+    assert_eq!(installed.last().map(String::as_str), Some("trino-operator"));
+    assert_eq!(
+        backend.release_version(OPERATOR_NAMESPACE, "airflow-operator"),
+        None
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resolve_crds_from_chart() {
+    let backend = FakeBackend::default()
+        .with_manifests(
+            "oci://oci.stackable.tech/sdp-charts/airflow-operator",
+            r#"
+---
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  name: airflow-operator-serviceaccount
+---
+apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: airflowclusters.airflow.stackable.tech
+"#,
+        )
+        .with_manifests(
+            "oci://oci.stackable.tech/sdp-charts/zookeeper-operator",
+            r#"
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: zookeeper-operator-clusterrole
+rules:
+  - apiGroups:
+      - apiextensions.k8s.io
+    resources:
+      - customresourcedefinitions
+    verbs:
+      - create
+      - patch
+"#,
+        );
+    let helm_client = helm::Client::new(backend);
+    let transfer_client = xfer::Client::new(Settings::disabled()).await.unwrap();
+    let chart_source = ChartSourceType::OCI(OciRegistry::default());
+
+    let resolve = |name: &str| {
+        let operator = OperatorSpec::new(name, Some("25.11.0".parse().unwrap())).unwrap();
+        let (helm_client, transfer_client, chart_source) =
+            (&helm_client, &transfer_client, &chart_source);
+
+        async move {
+            resolve_operator_crds(
+                &operator,
+                OPERATOR_NAMESPACE,
+                chart_source,
+                &Mapping::new(),
+                false,
+                helm_client,
+                transfer_client,
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let airflow = resolve("airflow").await;
+    assert_eq!(
+        airflow.status,
+        CrdStatus::Replace {
+            source: CrdSource::Chart,
+            names: vec!["airflowclusters.airflow.stackable.tech".to_owned()],
+        }
+    );
+    assert!(airflow.manifests.contains("kind: CustomResourceDefinition"));
+    assert!(!airflow.manifests.contains("kind: ServiceAccount"));
+
+    assert_eq!(
+        resolve("zookeeper").await.status,
+        CrdStatus::OperatorManaged
+    );
+    assert_eq!(resolve("hive").await.status, CrdStatus::Missing);
+}
@@ -43,10 +43,10 @@ Use \"stackablectl [OPTIONS] <COMMAND> -r path/to/releases1.yaml -r path/to/rele
 to provide multiple additional release files.")]
     pub release_files: Vec<String>,
 
-    /// Path to a Helm values file that will be used for the installation of operators
+    /// Provide one or more Helm values file(s) used for the installation of operators
     #[arg(short = 'f', long, value_name = "VALUES_FILE", value_hint = ValueHint::FilePath, global = true)]
     #[arg(
-        long_help = "Path to a Helm values file that will be used for the installation of operators
+        long_help = "Provide one or more Helm values file(s) used for the installation of operators
 
 The file is a YAML file containing Helm values used to deploy operators.
 Operator-specific keys (e.g. 'airflow-operator', 'zookeeper-operator') map
@@ -67,8 +67,27 @@ Example values file:
     podAnnotations:
       example.com/team: \"platform\"
 
-Use \"stackablectl [OPTIONS] <COMMAND> -f path/to/values.yaml\" to provide a
-values file."
+Multiple values files are deep-merged in the order they are provided, so later
+files take precedence. Nested mappings are merged, all other values (including
+lists) are replaced.
+
+Use \"stackablectl [OPTIONS] <COMMAND> -f path/to/baseline.yaml -f path/to/prod.yaml\"
+to provide multiple values files."
     )]
-    pub operator_values: Option<String>,
+    pub operator_values: Vec<String>,
+
+    /// Override a single Helm value of an operator, e.g. airflow-operator.image.repository=<REPO>
+    #[arg(long, value_name = "KEY=VALUE", global = true)]
+    #[arg(long_help = "Override a single Helm value of an operator
+
+The key is the dot-separated path of the value, starting with the operator
+(e.g. 'airflow-operator'). Dots in keys can be escaped using a backslash.
+Values are parsed as YAML scalars, so 'true' and '2' result in a boolean and
+an integer. Overrides are applied after all values files were merged and take
+precedence over them.
+
+Use \"stackablectl [OPTIONS] <COMMAND> --operator-set airflow-operator.replicas=2
+--operator-set 'airflow-operator.podAnnotations.example\\.com/team=data'\" to
+override multiple values.")]
+    pub operator_set: Vec<String>,
 }
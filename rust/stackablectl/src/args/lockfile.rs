@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueHint};
+use snafu::{ResultExt, Snafu};
+use stackable_cockpit::{
+    helm,
+    platform::{
+        lock::{self, LockedChart, Lockfile},
+        operator::OperatorSpec,
+        plan::InstallPlan,
+    },
+};
+
+use crate::cli::Cli;
+
+#[derive(Debug, Snafu)]
+pub enum CommonLockfileArgsError {
+    #[snafu(display("failed to read lockfile from {path:?}"))]
+    ReadLockfile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to parse lockfile at {path:?}"))]
+    ParseLockfile {
+        source: serde_yaml::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to resolve chart versions and digests"))]
+    LockCharts { source: lock::Error },
+
+    #[snafu(display("refusing to install charts which don't match the lockfile {path:?}"))]
+    VerifyLockfile {
+        source: lock::VerifyError,
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Lockfile options")]
+pub struct CommonLockfileArgs {
+    /// Refuse to install charts which don't match the provided lockfile
+    #[arg(
+        long,
+        long_help = "Refuse to install charts which don't match the provided lockfile
+
+Every chart which is about to be installed is resolved to its exact version
+and digest and compared against the lockfile. Charts are only installed if
+the pulled chart still has the locked digest. Use
+\"stackablectl release lock <RELEASE>\" to create a lockfile.",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath
+    )]
+    pub lockfile: Option<PathBuf>,
+}
+
+impl CommonLockfileArgs {
+    /// Verifies that all operators and Helm charts of the install `plan`
+    /// match the lockfile, if one is provided.
+    pub async fn verify_plan(
+        &self,
+        plan: &InstallPlan,
+        cli: &Cli,
+    ) -> Result<(), CommonLockfileArgsError> {
+        let Some(path) = &self.lockfile else {
+            return Ok(());
+        };
+
+        let charts = cli
+            .chart_locker()
+            .lock_plan(plan)
+            .await
+            .context(LockChartsSnafu)?;
+
+        verify(path, &charts)
+    }
+
+    /// Verifies that all `operators` match the lockfile, if one is provided.
+    pub async fn verify_operators(
+        &self,
+        operators: &[&OperatorSpec],
+        cli: &Cli,
+    ) -> Result<(), CommonLockfileArgsError> {
+        let Some(path) = &self.lockfile else {
+            return Ok(());
+        };
+
+        let chart_source = cli.chart_source();
+        let mut chart_locker = cli.chart_locker();
+        let mut charts = Vec::new();
+
+        for operator in operators {
+            charts.push(
+                chart_locker
+                    .lock_operator(operator, &chart_source)
+                    .await
+                    .context(LockChartsSnafu)?,
+            );
+        }
+
+        verify(path, &charts)
+    }
+
+    /// Returns the Helm client used to install the charts. If a lockfile is
+    /// provided, the charts are pinned to the locked digests, so that charts
+    /// which are re-pushed after they were verified are not installed.
+    pub fn helm_client(&self, cli: &Cli) -> Result<helm::Client, CommonLockfileArgsError> {
+        let helm_client = cli.helm_client();
+
+        match &self.lockfile {
+            Some(path) => Ok(read_lockfile(path)?.pin(helm_client)),
+            None => Ok(helm_client),
+        }
+    }
+}
+
+/// Reads a lockfile from `path`.
+pub fn read_lockfile(path: &Path) -> Result<Lockfile, CommonLockfileArgsError> {
+    let contents = std::fs::read_to_string(path).context(ReadLockfileSnafu { path })?;
+    serde_yaml::from_str(&contents).context(ParseLockfileSnafu { path })
+}
+
+fn verify(path: &Path, charts: &[LockedChart]) -> Result<(), CommonLockfileArgsError> {
+    read_lockfile(path)?
+        .verify(charts)
+        .context(VerifyLockfileSnafu { path })
+}
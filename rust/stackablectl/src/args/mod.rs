@@ -1,13 +1,17 @@
 mod cluster;
 mod file;
+mod lockfile;
 mod namespace;
 mod operator_configs;
 mod prompt;
 mod repo;
+mod values;
 
 pub use cluster::*;
 pub use file::*;
+pub use lockfile::*;
 pub use namespace::*;
 pub use operator_configs::*;
 pub use prompt::*;
 pub use repo::*;
+pub use values::*;
@@ -1,10 +1,32 @@
+use std::path::PathBuf;
+
 use clap::{Args, ValueHint};
+use snafu::{ResultExt, Snafu};
+use stackable_cockpit::{
+    constants::{HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST},
+    helm::RepositoryAuth,
+    oci::OciRegistry,
+};
 
 use crate::{
-    cli::ChartSourceTypeArg,
-    constants::{HELM_REPO_URL_DEV, HELM_REPO_URL_STABLE, HELM_REPO_URL_TEST},
+    cli::{ChartSourceTypeArg, RegistryApiArg},
+    config::RepositoryOptions,
+    constants::{
+        ENV_KEY_REPO_CA_FILE, ENV_KEY_REPO_PASSWORD, ENV_KEY_REPO_USERNAME, HELM_OCI_REGISTRY,
+        HELM_REPO_URL_DEV, HELM_REPO_URL_STABLE, HELM_REPO_URL_TEST,
+    },
+    utils::InvalidRepoNameError,
 };
 
+#[derive(Debug, Snafu)]
+pub enum CommonRepoArgsError {
+    #[snafu(display("failed to read repository password from {path:?}"))]
+    ReadPasswordFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+}
+
 #[derive(Debug, Args)]
 #[command(next_help_heading = "Helm repository options")]
 pub struct CommonRepoArgs {
@@ -45,4 +67,154 @@ pub struct CommonRepoArgs {
         global = true
     )]
     pub chart_source: ChartSourceTypeArg,
+
+    /// Provide a directory containing operator charts, used with '--chart-source local'
+    #[arg(
+        long,
+        long_help = "Provide a directory containing operator charts, used with '--chart-source local'
+
+The directory can either contain chart directories named after the operator
+chart (e.g. 'airflow-operator') or packaged charts named
+'<CHART>-<VERSION>.tgz'. Defaults to the current directory.",
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        global = true
+    )]
+    pub chart_dir: Option<PathBuf>,
+
+    /// Provide a custom OCI registry (and project) to source the operator charts from, used with '--chart-source oci'
+    #[arg(
+        long,
+        long_help = "Provide a custom OCI registry (and project) to source the operator charts from, used with '--chart-source oci'
+
+The registry is specified as '<HOST>/<PROJECT>', e.g. 'ghcr.io/acme/sdp-charts'
+for a mirror of the Stackable charts.",
+        value_name = "REGISTRY",
+        value_parser = |reference: &str| OciRegistry::parse(reference, None),
+        default_value = HELM_OCI_REGISTRY,
+        global = true
+    )]
+    pub oci_registry: OciRegistry,
+
+    /// The API used to list the operator charts of the OCI registry
+    #[arg(
+        long,
+        long_help = "The API used to list the operator charts of the OCI registry
+
+Defaults to the Harbor API for the Stackable registry and to the
+registry-agnostic OCI distribution API for every other registry.",
+        value_enum,
+        global = true
+    )]
+    pub oci_registry_api: Option<RegistryApiArg>,
+
+    /// Access the OCI registry using plain HTTP instead of HTTPS
+    #[arg(
+        long,
+        long_help = "Access the OCI registry using plain HTTP instead of HTTPS
+
+This is only intended for local registries, e.g. 'localhost:5000/sdp-charts'
+used for testing. Credentials are sent unencrypted.",
+        global = true
+    )]
+    pub oci_registry_plain_http: bool,
+
+    /// Username used to authenticate against the Helm repositories and OCI registry
+    #[arg(
+        long,
+        long_help = "Username used to authenticate against the Helm repositories and OCI registry
+
+The credentials are used for the Stackable operator charts only, charts of
+demos and stacks are always retrieved anonymously. This takes precedence
+over the 'repository.username' setting of the user config.",
+        value_name = "USERNAME",
+        env = ENV_KEY_REPO_USERNAME,
+        global = true
+    )]
+    pub repo_username: Option<String>,
+
+    /// Password used to authenticate against the Helm repositories and OCI registry
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = ENV_KEY_REPO_PASSWORD,
+        hide_env_values = true,
+        conflicts_with = "repo_password_file",
+        global = true
+    )]
+    pub repo_password: Option<String>,
+
+    /// Read the password used to authenticate against the Helm repositories and OCI registry from a file
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        global = true
+    )]
+    pub repo_password_file: Option<PathBuf>,
+
+    /// Provide a PEM-encoded CA bundle used to verify the Helm repositories and OCI registry
+    #[arg(
+        long,
+        long_help = "Provide a PEM-encoded CA bundle used to verify the Helm repositories and OCI registry
+
+The CA bundle is trusted in addition to the system root certificates. This
+takes precedence over the 'repository.ca_file' setting of the user config.",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        env = ENV_KEY_REPO_CA_FILE,
+        global = true
+    )]
+    pub repo_ca_file: Option<PathBuf>,
+}
+
+impl CommonRepoArgs {
+    /// Returns the (custom) Helm repository URL of the repository with
+    /// `repo_name`.
+    pub fn helm_repo_url(&self, repo_name: &str) -> Result<&str, InvalidRepoNameError> {
+        match repo_name {
+            HELM_REPO_NAME_STABLE => Ok(&self.helm_repo_stable),
+            HELM_REPO_NAME_TEST => Ok(&self.helm_repo_test),
+            HELM_REPO_NAME_DEV => Ok(&self.helm_repo_dev),
+            _ => Err(InvalidRepoNameError {
+                name: repo_name.to_owned(),
+            }),
+        }
+    }
+
+    /// Resolves the credentials and CA bundle used to access the Helm
+    /// repositories and OCI registry. Values provided via CLI arguments (or
+    /// env vars) take precedence over the ones of the user config.
+    pub fn repository_auth(
+        &self,
+        options: &RepositoryOptions,
+    ) -> Result<RepositoryAuth, CommonRepoArgsError> {
+        let password_file = self
+            .repo_password_file
+            .as_ref()
+            .or(options.password_file.as_ref());
+
+        let password = match (&self.repo_password, password_file) {
+            (Some(password), _) => Some(password.clone()),
+            (None, Some(path)) => Some(
+                std::fs::read_to_string(path)
+                    .context(ReadPasswordFileSnafu { path })?
+                    .trim_end()
+                    .to_owned(),
+            ),
+            (None, None) => options.password.clone(),
+        };
+
+        Ok(RepositoryAuth {
+            username: self
+                .repo_username
+                .clone()
+                .or_else(|| options.username.clone()),
+            password,
+            ca_file: self
+                .repo_ca_file
+                .clone()
+                .or_else(|| options.ca_file.clone()),
+        })
+    }
 }
@@ -0,0 +1,162 @@
+use clap::Args;
+use semver::Version;
+use serde_yaml::Mapping;
+use snafu::{ResultExt, Snafu, ensure};
+use stackable_cockpit::{
+    platform::{
+        operator::{self, ChartSourceType, OperatorSpec, ValuesIssue},
+        plan::InstallPlan,
+    },
+    utils::yaml::values_for_operator,
+    xfer,
+};
+use tracing_indicatif::indicatif_eprintln;
+
+use crate::cli::Cli;
+
+#[derive(Debug, Snafu)]
+pub enum CommonValuesArgsError {
+    #[snafu(display("failed to parse version {version:?} of {name}-operator"))]
+    ParseOperatorVersion {
+        source: semver::Error,
+        name: String,
+        version: String,
+    },
+
+    #[snafu(display("failed to parse operator spec"))]
+    OperatorSpecParse { source: operator::SpecParseError },
+
+    #[snafu(display("failed to validate the operator values"))]
+    ValidateValues {
+        source: operator::ValuesValidationError,
+    },
+
+    #[snafu(display(
+        "refusing to install operators, because the operator values file contains {count} problem(s)"
+    ))]
+    InvalidValues { count: usize },
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Operator values options")]
+pub struct CommonValuesArgs {
+    /// Fail if the operator values file contains unknown keys or type mismatches
+    #[arg(
+        long,
+        long_help = "Fail if the operator values file contains unknown keys or type mismatches
+
+The values of every operator which is about to be installed are validated
+against the values schema (values.schema.json) of its chart. Charts without a
+schema are validated against their default values (values.yaml). Top-level
+sections which don't belong to any known operator, e.g. 'airfow-operator', are
+reported as well. Problems are reported as warnings, unless this flag is set."
+    )]
+    pub strict: bool,
+}
+
+impl CommonValuesArgs {
+    /// Validates the operator values of all operators of the install `plan`.
+    pub async fn validate_plan(
+        &self,
+        plan: &InstallPlan,
+        operator_values: &Mapping,
+        cli: &Cli,
+        transfer_client: &xfer::Client,
+    ) -> Result<(), CommonValuesArgsError> {
+        let mut operators = Vec::new();
+
+        for operator in &plan.operators {
+            let version = Version::parse(&operator.version).context(ParseOperatorVersionSnafu {
+                name: &operator.name,
+                version: &operator.version,
+            })?;
+            let operator_spec =
+                OperatorSpec::new(&operator.name, Some(version)).context(OperatorSpecParseSnafu)?;
+
+            operators.push((operator_spec, operator.chart_source.clone()));
+        }
+
+        self.validate(operators, operator_values, cli, transfer_client)
+            .await
+    }
+
+    /// Validates the operator values of all `operators`.
+    pub async fn validate_operators(
+        &self,
+        operators: &[&OperatorSpec],
+        operator_values: &Mapping,
+        cli: &Cli,
+        transfer_client: &xfer::Client,
+    ) -> Result<(), CommonValuesArgsError> {
+        let operators = operators
+            .iter()
+            .map(|operator| ((*operator).clone(), cli.chart_source()))
+            .collect();
+
+        self.validate(operators, operator_values, cli, transfer_client)
+            .await
+    }
+
+    /// Prints all problems found in the operator values as warnings, which
+    /// are turned into an error if `--strict` is set.
+    async fn validate(
+        &self,
+        operators: Vec<(OperatorSpec, ChartSourceType)>,
+        operator_values: &Mapping,
+        cli: &Cli,
+        transfer_client: &xfer::Client,
+    ) -> Result<(), CommonValuesArgsError> {
+        let helm_client = cli.helm_client();
+        let mut count = 0;
+
+        if !operator_values.is_empty() {
+            let issues =
+                unknown_operator_sections(&operators, operator_values, cli, transfer_client).await;
+
+            for issue in &issues {
+                indicatif_eprintln!("Warning: operator values: {issue}");
+            }
+
+            count += issues.len();
+        }
+
+        for (operator, chart_source) in operators {
+            let values = values_for_operator(operator_values, &operator.name);
+            let issues = operator
+                .validate_values(&chart_source, &values, &helm_client)
+                .await
+                .context(ValidateValuesSnafu)?;
+
+            for issue in &issues {
+                indicatif_eprintln!(
+                    "Warning: operator values of {name}-operator: {issue}",
+                    name = operator.name
+                );
+            }
+
+            count += issues.len();
+        }
+
+        ensure!(!self.strict || count == 0, InvalidValuesSnafu { count });
+
+        Ok(())
+    }
+}
+
+/// Returns the top-level sections of the `operator_values` which belong
+/// neither to one of the `operators` nor to any other known operator.
+async fn unknown_operator_sections(
+    operators: &[(OperatorSpec, ChartSourceType)],
+    operator_values: &Mapping,
+    cli: &Cli,
+    transfer_client: &xfer::Client,
+) -> Vec<ValuesIssue> {
+    let known_operators = cli.known_operators(transfer_client).await;
+
+    operator::check_operator_sections(
+        operator_values,
+        known_operators
+            .iter()
+            .chain(operators.iter().map(|(operator, _)| operator.name.as_str())),
+    )
+}
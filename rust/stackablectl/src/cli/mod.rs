@@ -1,4 +1,9 @@
-use std::{env, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use clap::{Parser, Subcommand, ValueEnum};
 use directories::ProjectDirs;
@@ -6,14 +11,21 @@ use snafu::{OptionExt, ResultExt, Snafu};
 use stackable_cockpit::{
     constants::{HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST},
     helm,
-    platform::operator::{
-        ChartSourceType, listener_operator::determine_and_store_listener_class_preset,
+    oci::RegistryApi,
+    platform::{
+        lock::ChartLocker,
+        operator::{
+            ChartSourceType, KnownOperators,
+            listener_operator::determine_and_store_listener_class_preset,
+        },
+        release::ReleaseList,
     },
     utils::path::{
         IntoPathOrUrl, IntoPathsOrUrls, ParsePathsOrUrls, PathOrUrl, PathOrUrlParseError,
     },
     xfer::{self, cache::Settings},
 };
+use tokio_util::sync::CancellationToken;
 use tracing::{Level, instrument};
 use tracing_indicatif::indicatif_eprintln;
 
@@ -62,6 +74,11 @@ pub enum Error {
     #[snafu(display("failed to add Helm repositories"))]
     AddHelmRepos { source: helm::Error },
 
+    #[snafu(display("failed to resolve Helm repository credentials"))]
+    ResolveRepositoryAuth {
+        source: crate::args::CommonRepoArgsError,
+    },
+
     #[snafu(display("failed to retrieve cache settings"))]
     RetrieveCacheSettings { source: CacheSettingsError },
 
@@ -111,6 +128,19 @@ Cached files are saved at '$XDG_CACHE_HOME/stackablectl', which is usually
 
     #[command(subcommand)]
     pub subcommand: Command,
+
+    /// Cancelled when the user hits Ctrl-C, see [`Cli::helm_client`].
+    #[arg(skip)]
+    pub cancellation_token: CancellationToken,
+
+    /// Resolved from the CLI arguments and the user config in [`Cli::run`].
+    #[arg(skip)]
+    pub repository_auth: helm::RepositoryAuth,
+
+    /// The directory persistent state (like the progress of release upgrades)
+    /// is stored in, set in [`Cli::run`].
+    #[arg(skip)]
+    pub data_directory: PathBuf,
 }
 
 impl Cli {
@@ -156,31 +186,134 @@ impl Cli {
         Ok(files)
     }
 
-    pub fn get_values_file(&self) -> Result<Option<PathOrUrl>, PathOrUrlParseError> {
-        if let Some(operator_values) = self.files.operator_values.clone() {
-            Ok(Some(operator_values.into_path_or_url()?))
-        } else {
-            Ok(None)
-        }
+    pub fn get_values_files(&self) -> Result<Vec<PathOrUrl>, PathOrUrlParseError> {
+        self.files.operator_values.clone().into_paths_or_urls()
     }
 
     /// Adds the default (or custom) Helm repository URLs. Internally this calls the Helm SDK written in Go through the
-    /// `go-helm-wrapper`.
-    pub fn add_helm_repos(&self) -> Result<(), helm::Error> {
+    /// `go-helm-wrapper`. If credentials are configured, they are also used for the OCI registry, as well as for charts
+    /// of stacks and demos which are pulled from the same repositories or registry.
+    pub async fn add_helm_repos(&self) -> Result<(), helm::Error> {
         tracing::info!("Add Helm repos");
+        let helm_client = self.helm_client();
+        let auth = &self.repository_auth;
 
         // Stable repository
-        helm::add_repo(HELM_REPO_NAME_STABLE, &self.repos.helm_repo_stable)?;
+        helm_client
+            .add_repo(HELM_REPO_NAME_STABLE, &self.repos.helm_repo_stable, auth)
+            .await?;
 
         // Test repository
-        helm::add_repo(HELM_REPO_NAME_TEST, &self.repos.helm_repo_test)?;
+        helm_client
+            .add_repo(HELM_REPO_NAME_TEST, &self.repos.helm_repo_test, auth)
+            .await?;
 
         // Dev repository
-        helm::add_repo(HELM_REPO_NAME_DEV, &self.repos.helm_repo_dev)?;
+        helm_client
+            .add_repo(HELM_REPO_NAME_DEV, &self.repos.helm_repo_dev, auth)
+            .await?;
+
+        // OCI registry, anonymous access via HTTPS doesn't need any
+        // configuration. It is configured regardless of the chart source, as
+        // stacks and demos can pull charts from it as well.
+        if *auth != helm::RepositoryAuth::default() || self.repos.oci_registry_plain_http {
+            helm_client
+                .configure_registry(
+                    &self.repos.oci_registry.host,
+                    auth,
+                    self.repos.oci_registry_plain_http,
+                )
+                .await?;
+        }
 
         Ok(())
     }
 
+    /// Returns a [`ChartLocker`] which resolves charts using the configured
+    /// (custom) Helm repository URLs and credentials.
+    pub fn chart_locker(&self) -> ChartLocker<'_> {
+        ChartLocker::new(&self.repository_auth, self.helm_repo_urls())
+    }
+
+    /// Returns the URLs of the Stackable Helm repositories, keyed by
+    /// repository name.
+    pub fn helm_repo_urls(&self) -> HashMap<String, String> {
+        [
+            (HELM_REPO_NAME_STABLE, &self.repos.helm_repo_stable),
+            (HELM_REPO_NAME_TEST, &self.repos.helm_repo_test),
+            (HELM_REPO_NAME_DEV, &self.repos.helm_repo_dev),
+        ]
+        .into_iter()
+        .map(|(repo_name, repo_url)| (repo_name.to_owned(), repo_url.clone()))
+        .collect()
+    }
+
+    /// Discovers the operators which can be installed from the loaded release
+    /// files and the index of the chart source, which is accessed using the
+    /// configured credentials and cached using the `transfer_client`. Failures
+    /// to load the release files or to reach the chart source are only logged,
+    /// in which case the built-in list of operators is used as a fallback.
+    #[instrument(skip_all)]
+    pub async fn known_operators(&self, transfer_client: &xfer::Client) -> KnownOperators {
+        let release_list = match self.get_release_files() {
+            Ok(files) => ReleaseList::build(&files, transfer_client)
+                .await
+                .inspect_err(|err| {
+                    tracing::warn!(
+                        error = %err,
+                        "Failed to discover operators from release files"
+                    )
+                })
+                .ok(),
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    "Failed to parse release files"
+                );
+                None
+            }
+        };
+
+        // Newly added operators are only available in the dev repository
+        let helm_repo_urls = [
+            self.repos.helm_repo_stable.as_str(),
+            self.repos.helm_repo_dev.as_str(),
+        ];
+
+        KnownOperators::discover(
+            release_list.as_ref(),
+            &self.chart_source(),
+            &helm_repo_urls,
+            &self.repository_auth,
+            transfer_client,
+        )
+        .await
+    }
+
+    /// Returns the path of the file the progress of release upgrades of
+    /// operators in `namespace` is stored in.
+    pub fn release_upgrade_state_file(&self, namespace: &str) -> PathBuf {
+        self.data_directory
+            .join(format!("release-upgrade-{namespace}.yaml"))
+    }
+
+    /// Returns the Helm client used by all commands, which calls into the
+    /// Helm SDK written in Go through the `go-helm-wrapper`. Pending Helm
+    /// operations are cancelled once the [`Cli::cancellation_token`] is
+    /// cancelled. The URLs of the Stackable Helm repositories, which are added
+    /// by [`Cli::add_helm_repos`], are registered to match operator charts
+    /// against pinned charts.
+    pub fn helm_client(&self) -> helm::Client {
+        let helm_client =
+            helm::Client::default().with_cancellation_token(self.cancellation_token.clone());
+
+        self.helm_repo_urls()
+            .into_iter()
+            .fold(helm_client, |helm_client, (repo_name, repo_url)| {
+                helm_client.with_repo_url(repo_name, repo_url)
+            })
+    }
+
     fn cache_settings(&self, cache_directory: &Path) -> Result<Settings, CacheSettingsError> {
         if self.no_cache {
             tracing::debug!("Cache disabled");
@@ -205,7 +338,19 @@ impl Cli {
     }
 
     #[instrument(skip_all)]
-    pub async fn run(self) -> Result<String, Error> {
+    pub async fn run(mut self) -> Result<String, Error> {
+        let xdg_directories = Cli::xdg_directories()?;
+        // TODO (@Techassi): Move this file name to a constant
+        let user_config_path = xdg_directories.config_dir().join("config.toml");
+
+        let user_config = UserConfig::from_file_or_default(user_config_path).unwrap();
+        self.data_directory = xdg_directories.data_dir().to_path_buf();
+
+        self.repository_auth = self
+            .repos
+            .repository_auth(&user_config.repository)
+            .context(ResolveRepositoryAuthSnafu)?;
+
         // FIXME (Techassi): There might be a better way to handle this with
         // the match later in this function.
 
@@ -213,15 +358,9 @@ impl Cli {
         match &self.subcommand {
             Command::Completions(_) => (),
             Command::Cache(_) => (),
-            _ => self.add_helm_repos().context(AddHelmReposSnafu)?,
+            _ => self.add_helm_repos().await.context(AddHelmReposSnafu)?,
         }
 
-        let xdg_directories = Cli::xdg_directories()?;
-        // TODO (@Techassi): Move this file name to a constant
-        let user_config_path = xdg_directories.config_dir().join("config.toml");
-
-        let user_config = UserConfig::from_file_or_default(user_config_path).unwrap();
-
         let cache_settings = self
             .cache_settings(xdg_directories.cache_dir())
             .context(RetrieveCacheSettingsSnafu)?;
@@ -312,8 +451,30 @@ impl Cli {
         Output::new(ErrorContext::default(), true).expect("Failed to create output renderer")
     }
 
-    pub fn chart_type(&self) -> ChartSourceTypeArg {
-        self.repos.chart_source.clone()
+    /// Resolves the chart source argument used by clap to the core type used
+    /// in stackable-cockpit. For the (index.yaml-based) repo case this core
+    /// type cannot be decorated with meaningful information as that would be
+    /// operator-specific i.e. we cannot resolve *which* (index.yaml-based) repo
+    /// to use until we have inspected the operator version. Local chart sources
+    /// fall back to the current directory if no chart directory is provided.
+    pub fn chart_source(&self) -> ChartSourceType {
+        match self.repos.chart_source {
+            ChartSourceTypeArg::OCI => {
+                let mut registry = self.repos.oci_registry.clone();
+                if let Some(api) = &self.repos.oci_registry_api {
+                    registry.api = api.clone().into();
+                }
+                registry.plain_http = self.repos.oci_registry_plain_http;
+                ChartSourceType::OCI(registry)
+            }
+            ChartSourceTypeArg::Repo => ChartSourceType::Repo,
+            ChartSourceTypeArg::Local => ChartSourceType::Local(
+                self.repos
+                    .chart_dir
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(".")),
+            ),
+        }
     }
 }
 
@@ -395,7 +556,7 @@ fn get_files(default_file: &str, env_key: &str) -> Result<Vec<PathOrUrl>, PathOr
 }
 
 /// Enum used for resolving the argument for chart source type. This will be
-/// mapped to ChartSourceType (see below): the reason why we don't have one
+/// mapped to ChartSourceType (see [`Cli::chart_source`]): the reason why we don't have one
 /// enum is to avoid having to add clap dependencies to stackable-cockpit
 /// for the ValueEnum macro.
 #[derive(Clone, Debug, Default, ValueEnum)]
@@ -406,18 +567,27 @@ pub enum ChartSourceTypeArg {
 
     /// index.yaml-based repositories: resolution (dev, test, stable) is based on the version and thus will be operator-specific
     Repo,
+
+    /// Local directory containing chart directories or packaged charts (.tgz), see --chart-dir
+    Local,
 }
 
-impl From<ChartSourceTypeArg> for ChartSourceType {
-    /// Resolves the enum used by clap/arg-resolution to the core type used in
-    /// stackable-cockpit. For the (index.yaml-based) repo case this core type cannot be
-    /// decorated with meaningful information as that would be operator-specific
-    /// i.e. we cannot resolve *which* (index.yaml-based) repo to use until we have inspected
-    /// the operator version. Hence just a simple mapping.
-    fn from(cli_enum: ChartSourceTypeArg) -> Self {
-        match cli_enum {
-            ChartSourceTypeArg::OCI => ChartSourceType::OCI,
-            ChartSourceTypeArg::Repo => ChartSourceType::Repo,
+/// Enum used for resolving the argument for the OCI registry API, see
+/// [`ChartSourceTypeArg`] for why this is separate from [`RegistryApi`].
+#[derive(Clone, Debug, ValueEnum)]
+pub enum RegistryApiArg {
+    /// Harbor-specific API, used by the Stackable registry
+    Harbor,
+
+    /// Registry-agnostic OCI distribution API, e.g. for registry:2, Zot, GHCR or Artifactory
+    Distribution,
+}
+
+impl From<RegistryApiArg> for RegistryApi {
+    fn from(value: RegistryApiArg) -> Self {
+        match value {
+            RegistryApiArg::Harbor => Self::Harbor,
+            RegistryApiArg::Distribution => Self::Distribution,
         }
     }
 }
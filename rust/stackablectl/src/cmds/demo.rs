@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueHint};
 use comfy_table::{
     ContentArrangement, Row, Table,
     presets::{NOTHING, UTF8_FULL},
@@ -12,7 +12,7 @@ use stackable_cockpit::{
     constants::{DEFAULT_NAMESPACE, DEFAULT_OPERATOR_NAMESPACE},
     platform::{
         demo::{self, DemoInstallParameters, DemoUninstallParameters},
-        operator::ChartSourceType,
+        export::{self, ExportParameters},
         release, stack,
     },
     utils::{
@@ -26,9 +26,12 @@ use tracing::{Span, debug, info, instrument};
 use tracing_indicatif::{self, span_ext::IndicatifSpanExt as _};
 
 use crate::{
-    args::{CommonClusterArgs, CommonClusterArgsError, CommonNamespaceArgs, CommonPromptArgs},
+    args::{
+        CommonClusterArgs, CommonClusterArgsError, CommonLockfileArgs, CommonLockfileArgsError,
+        CommonNamespaceArgs, CommonPromptArgs, CommonValuesArgs, CommonValuesArgsError,
+    },
     cli::{Cli, OutputType},
-    utils::load_operator_values,
+    utils::{WriteExportError, load_operator_values, render_install_plan, write_export},
 };
 
 #[derive(Debug, Args)]
@@ -59,6 +62,9 @@ pub enum DemoCommands {
     /// the operators and provided operator namespace, and all Stackable CRDs
     #[command(aliases(["u", "un"]))]
     Uninstall(DemoUninstallArgs),
+
+    /// Export a specific demo as a bundle of manifests for GitOps tools like Argo CD
+    Export(DemoExportArgs),
 }
 
 #[derive(Debug, Args)]
@@ -108,6 +114,10 @@ to specify operator versions."
     )]
     skip_release: bool,
 
+    /// Only warn instead of failing if the demo, stack or release doesn't support the Kubernetes version of the cluster
+    #[arg(long)]
+    skip_kubernetes_version_check: bool,
+
     /// List of parameters to use when installing the stack
     #[arg(long)]
     stack_parameters: Vec<String>,
@@ -116,6 +126,18 @@ to specify operator versions."
     #[arg(long)]
     parameters: Vec<String>,
 
+    /// Print what would be installed without touching the cluster
+    #[arg(long)]
+    #[arg(
+        long_help = "Print what would be installed without touching the cluster
+
+The release is resolved and all stack and demo manifests are rendered, but no
+local cluster, namespace, operator or object is created. The printed plan lists
+the operators to install, the namespaces to create, the objects to apply and
+the Helm releases to install or upgrade."
+    )]
+    dry_run: bool,
+
     #[command(flatten)]
     local_cluster: CommonClusterArgs,
 
@@ -124,6 +146,12 @@ to specify operator versions."
 
     #[command(flatten)]
     prompt_args: CommonPromptArgs,
+
+    #[command(flatten)]
+    lockfile: CommonLockfileArgs,
+
+    #[command(flatten)]
+    values: CommonValuesArgs,
 }
 
 #[derive(Debug, Args)]
@@ -142,6 +170,51 @@ pub struct DemoUninstallArgs {
     prompt_args: CommonPromptArgs,
 }
 
+#[derive(Debug, Args)]
+pub struct DemoExportArgs {
+    /// Demo to export
+    #[arg(
+        name = "DEMO",
+        long_help = "Demo to export
+
+Use \"stackablectl demo list\" to display a list of available demos.
+Use \"stackablectl demo describe <DEMO>\" to display details about the specified demo."
+    )]
+    demo_name: String,
+
+    /// Directory the bundle is written to, defaults to the name of the demo
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    #[arg(
+        long_help = "Directory the bundle is written to, defaults to the name of the demo
+
+The bundle contains a directory per namespace with the rendered manifests, the
+values files of the operators and Helm charts and a kustomization.yaml, which
+references the operators and Helm charts in its 'helmCharts' section. A
+top-level kustomization.yaml references the namespace directories. Build the
+bundle using \"kustomize build --enable-helm <DIR>\"."
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// Overwrite existing files if the output directory is not empty
+    #[arg(long)]
+    force: bool,
+
+    /// Skip the operators of the release during the demo export
+    #[arg(long)]
+    skip_release: bool,
+
+    /// List of parameters to use when exporting the stack
+    #[arg(long)]
+    stack_parameters: Vec<String>,
+
+    /// List of parameters to use when exporting the demo
+    #[arg(long)]
+    parameters: Vec<String>,
+
+    #[command(flatten)]
+    namespaces: CommonNamespaceArgs,
+}
+
 #[derive(Debug, Snafu)]
 pub enum CmdError {
     #[snafu(display("failed to serialize YAML output"))]
@@ -174,6 +247,12 @@ pub enum CmdError {
         demo_name: String,
     },
 
+    #[snafu(display("failed to build install plan for demo {demo_name:?}"))]
+    PlanDemo {
+        source: demo::Error,
+        demo_name: String,
+    },
+
     #[snafu(display("failed to confirm user input"))]
     ConfirmDialog { source: dialoguer::Error },
 
@@ -191,6 +270,24 @@ pub enum CmdError {
 
     #[snafu(display("failed to load operator values"))]
     LoadOperatorValues { source: crate::utils::Error },
+
+    #[snafu(display("lockfile argument error"))]
+    CommonLockfileArgs { source: CommonLockfileArgsError },
+
+    #[snafu(display("operator values argument error"))]
+    CommonValuesArgs { source: CommonValuesArgsError },
+
+    #[snafu(display("failed to export demo {demo_name:?}"))]
+    ExportDemo {
+        source: export::Error,
+        demo_name: String,
+    },
+
+    #[snafu(display("failed to write export of demo {demo_name:?}"))]
+    WriteExport {
+        source: WriteExportError,
+        demo_name: String,
+    },
 }
 
 impl DemoArgs {
@@ -245,6 +342,9 @@ impl DemoArgs {
             DemoCommands::Uninstall(args) => {
                 uninstall_cmd(args, cli, list, &transfer_client, &release_branch).await
             }
+            DemoCommands::Export(args) => {
+                export_cmd(args, cli, list, &transfer_client, &release_branch).await
+            }
         }
     }
 }
@@ -394,14 +494,6 @@ async fn install_cmd(
         .await
         .context(BuildListSnafu)?;
 
-    // Install local cluster if needed
-    args.local_cluster
-        .install_if_needed()
-        .await
-        .context(InstallClusterSnafu)?;
-
-    let client = Client::new().await.context(KubeClientCreateSnafu)?;
-
     // Construct labels which get attached to all dynamic objects which
     // are part of the demo and stack.
     let labels = Labels::try_from([
@@ -432,8 +524,11 @@ async fn install_cmd(
         .context(ConfirmDialogSnafu)
     };
 
+    // A dry run doesn't install anything, so it is planned with the suggested
+    // namespace without asking
     let demo_namespace = if args.namespaces.namespace == DEFAULT_NAMESPACE {
         if args.prompt_args.assume_yes
+            || args.dry_run
             || tracing_indicatif::suspend_tracing_indicatif(non_default_namespace_confirmation)?
         {
             // User selected to install in suggested namespace
@@ -447,10 +542,11 @@ async fn install_cmd(
         args.namespaces.namespace.clone()
     };
 
-    let values_file = cli.get_values_file().context(PathOrUrlParseSnafu)?;
-    let operator_values = load_operator_values(values_file.as_ref(), transfer_client)
-        .await
-        .context(LoadOperatorValuesSnafu)?;
+    let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+    let operator_values =
+        load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+            .await
+            .context(LoadOperatorValuesSnafu)?;
 
     let install_parameters = DemoInstallParameters {
         stack_name: demo.stack.clone(),
@@ -460,17 +556,78 @@ async fn install_cmd(
         stack_parameters: args.stack_parameters.clone(),
         parameters: args.parameters.clone(),
         skip_release: args.skip_release,
+        skip_kubernetes_version_check: args.skip_kubernetes_version_check,
         stack_labels,
         labels,
-        chart_source: ChartSourceType::from(cli.chart_type()),
+        chart_source: cli.chart_source(),
         operator_values,
     };
 
+    // Verify the demo against the lockfile and validate the operator values
+    // before touching the cluster
+    if args.dry_run
+        || args.lockfile.lockfile.is_some()
+        || !install_parameters.operator_values.is_empty()
+    {
+        let plan = demo
+            .plan(
+                &stack_list,
+                &release_list,
+                &install_parameters,
+                transfer_client,
+            )
+            .await
+            .context(PlanDemoSnafu {
+                demo_name: args.demo_name.clone(),
+            })?;
+
+        args.lockfile
+            .verify_plan(&plan, cli)
+            .await
+            .context(CommonLockfileArgsSnafu)?;
+
+        args.values
+            .validate_plan(
+                &plan,
+                &install_parameters.operator_values,
+                cli,
+                transfer_client,
+            )
+            .await
+            .context(CommonValuesArgsSnafu)?;
+
+        if args.dry_run {
+            output
+                .with_command_hint(
+                    format!(
+                        "stackablectl demo install {demo_name}",
+                        demo_name = args.demo_name
+                    ),
+                    "install the demo",
+                )
+                .with_output(render_install_plan(&plan));
+
+            return Ok(output.render());
+        }
+    }
+
+    // Install local cluster if needed
+    args.local_cluster
+        .install_if_needed()
+        .await
+        .context(InstallClusterSnafu)?;
+
+    let client = Client::new().await.context(KubeClientCreateSnafu)?;
+
     demo.install(
         stack_list,
         release_list,
         install_parameters,
         &client,
+        &args
+            .lockfile
+            .helm_client(cli)
+            .context(CommonLockfileArgsSnafu)?,
         transfer_client,
     )
     .await
@@ -585,6 +742,7 @@ async fn uninstall_cmd(
             skip_crds: args.skip_operators_and_crds,
         },
         &client,
+        &cli.helm_client(),
         transfer_client,
     )
     .await
@@ -599,3 +757,116 @@ async fn uninstall_cmd(
 
     Ok(output.render())
 }
+
+#[instrument(skip(cli, list, transfer_client), fields(indicatif.pb_show = true))]
+async fn export_cmd(
+    args: &DemoExportArgs,
+    cli: &Cli,
+    list: demo::List,
+    transfer_client: &xfer::Client,
+    release_branch: &str,
+) -> Result<String, CmdError> {
+    info!(demo_name = %args.demo_name, "Exporting demo");
+    Span::current().pb_set_message(&format!(
+        "Exporting demo {demo_name}",
+        demo_name = args.demo_name
+    ));
+
+    let demo = list.get(&args.demo_name).ok_or(CmdError::NoSuchDemo {
+        name: args.demo_name.clone(),
+    })?;
+
+    let files = cli
+        .get_stack_files(release_branch)
+        .context(PathOrUrlParseSnafu)?;
+    let stack_list = stack::StackList::build(&files, transfer_client)
+        .await
+        .context(BuildListSnafu)?;
+
+    let files = cli.get_release_files().context(PathOrUrlParseSnafu)?;
+    let release_list = release::ReleaseList::build(&files, transfer_client)
+        .await
+        .context(BuildListSnafu)?;
+
+    // The same labels are attached as when installing the demo. The plan
+    // doesn't tell stack and demo objects apart, so all objects get the
+    // stack labels, which include the demo labels.
+    let labels = Labels::try_from([
+        ("stackable.tech/managed-by", "stackablectl"),
+        ("stackable.tech/demo", &args.demo_name),
+        ("stackable.tech/vendor", "Stackable"),
+    ])
+    .context(BuildLabelsSnafu)?;
+
+    let mut stack_labels = labels.clone();
+    stack_labels
+        .parse_insert(("stackable.tech/stack", &demo.stack))
+        .context(BuildLabelsSnafu)?;
+
+    let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+    let operator_values =
+        load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+            .await
+            .context(LoadOperatorValuesSnafu)?;
+
+    let install_parameters = DemoInstallParameters {
+        stack_name: demo.stack.clone(),
+        demo_name: args.demo_name.clone(),
+        operator_namespace: args.namespaces.operator_namespace.clone(),
+        demo_namespace: args.namespaces.namespace.clone(),
+        stack_parameters: args.stack_parameters.clone(),
+        parameters: args.parameters.clone(),
+        skip_release: args.skip_release,
+        skip_kubernetes_version_check: true,
+        stack_labels: stack_labels.clone(),
+        labels,
+        chart_source: cli.chart_source(),
+        operator_values,
+    };
+
+    let plan = demo
+        .plan(
+            &stack_list,
+            &release_list,
+            &install_parameters,
+            transfer_client,
+        )
+        .await
+        .context(PlanDemoSnafu {
+            demo_name: &args.demo_name,
+        })?;
+
+    let export_parameters = ExportParameters {
+        helm_repo_urls: cli.helm_repo_urls(),
+        operator_values: install_parameters.operator_values,
+        labels: stack_labels,
+    };
+    let files = export::export_plan(&plan, &export_parameters).context(ExportDemoSnafu {
+        demo_name: &args.demo_name,
+    })?;
+
+    let output_dir = args
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(&args.demo_name));
+    write_export(&output_dir, &files, args.force).context(WriteExportSnafu {
+        demo_name: &args.demo_name,
+    })?;
+
+    let mut output = Cli::result();
+    output
+        .with_command_hint(
+            format!(
+                "kustomize build --enable-helm {output_dir}",
+                output_dir = output_dir.display()
+            ),
+            "render the exported demo",
+        )
+        .with_output(format!(
+            "Exported demo {demo_name:?} to {output_dir:?} ({count} files)",
+            demo_name = args.demo_name,
+            count = files.len()
+        ));
+
+    Ok(output.render())
+}
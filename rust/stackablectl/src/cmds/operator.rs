@@ -1,40 +1,53 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use clap::{Args, Subcommand};
 use comfy_table::{
     ContentArrangement, Table,
     presets::{NOTHING, UTF8_FULL},
 };
+use futures::StreamExt as _;
 use indexmap::IndexMap;
 use semver::Version;
 use serde::Serialize;
-use snafu::{ResultExt, Snafu};
+use snafu::{ResultExt, Snafu, ensure};
 use stackable_cockpit::{
     constants::{
         DEFAULT_OPERATOR_NAMESPACE, HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST,
     },
-    helm::{self, Release},
+    helm::{self, InstallReleaseStatus, Release},
     oci,
     platform::{
         namespace,
-        operator::{self, ChartSourceType},
+        operator::{self, ChartSourceType, DependencyGraph},
+        release::{self, CrdStatus},
     },
     utils::{
         self,
         chartsource::ChartSourceMetadata,
-        k8s::{self, Client},
+        k8s::{self, Client, ListParamsExt},
         path::PathOrUrlParseError,
         yaml::values_for_operator,
     },
     xfer,
 };
-use tracing::{Span, debug, info, instrument};
+use stackable_operator::kube::{
+    ResourceExt,
+    api::{ListParams, LogParams},
+};
+use tracing::{Level, Span, debug, info, instrument, warn};
 use tracing_indicatif::{indicatif_println, span_ext::IndicatifSpanExt};
 
 use crate::{
-    args::{CommonClusterArgs, CommonClusterArgsError},
+    args::{
+        CommonClusterArgs, CommonClusterArgsError, CommonLockfileArgs, CommonLockfileArgsError,
+        CommonValuesArgs, CommonValuesArgsError,
+    },
     cli::{Cli, OutputType},
-    utils::{InvalidRepoNameError, helm_repo_name_to_repo_url, load_operator_values},
+    utils::{InvalidRepoNameError, load_operator_values},
 };
 
 const INSTALL_AFTER_HELP_TEXT: &str = "Examples:
@@ -67,6 +80,19 @@ pub enum OperatorCommands {
 
     /// List installed operators
     Installed(OperatorInstalledArgs),
+
+    /// Upgrade or downgrade one or more installed operators in place
+    #[command(aliases(["up"]))]
+    Upgrade(OperatorUpgradeArgs),
+
+    /// Display the Helm revision history of an installed operator
+    History(OperatorHistoryArgs),
+
+    /// Roll back an installed operator to a previous Helm revision
+    Rollback(OperatorRollbackArgs),
+
+    /// Print or stream the logs of an installed operator
+    Logs(OperatorLogsArgs),
 }
 
 #[derive(Debug, Args)]
@@ -109,6 +135,12 @@ Use \"stackablectl operator describe <OPERATOR>\" to get available versions for
 
     #[command(flatten)]
     local_cluster: CommonClusterArgs,
+
+    #[command(flatten)]
+    lockfile: CommonLockfileArgs,
+
+    #[command(flatten)]
+    values: CommonValuesArgs,
 }
 
 #[derive(Debug, Args)]
@@ -122,11 +154,109 @@ pub struct OperatorUninstallArgs {
     pub operator_namespace: String,
 }
 
+#[derive(Debug, Args)]
+pub struct OperatorUpgradeArgs {
+    /// Operator(s) to upgrade
+    #[arg(name = "OPERATORS", required = true)]
+    #[arg(long_help = "Operator(s) to upgrade (space separated)
+
+Each must have the form 'name=version' and needs to be installed already.
+The Helm values supplied when the operator was installed are preserved,
+values from the operator values file take precedence.")]
+    operators: Vec<operator::OperatorSpec>,
+
+    /// Downgrade operators even if the storage version of their CRDs changes or can't be checked
+    #[arg(long)]
+    force_downgrade: bool,
+
+    /// Replace conflicting resources instead of patching them when upgrading the Helm releases
+    #[arg(long)]
+    force: bool,
+
+    /// Retrieve CRDs from the upstream GitHub repositories if the operator chart doesn't contain them
+    #[arg(long)]
+    crd_github_fallback: bool,
+
+    /// Namespace in the cluster used to deploy the operators
+    #[arg(long, default_value = DEFAULT_OPERATOR_NAMESPACE, visible_aliases(["operator-ns"]))]
+    pub operator_namespace: String,
+
+    #[command(flatten)]
+    values: CommonValuesArgs,
+}
+
 #[derive(Debug, Args)]
 pub struct OperatorInstalledArgs {
     #[arg(short, long = "output", value_enum, default_value_t = Default::default())]
     output_type: OutputType,
 
+    /// Exit with a non-zero exit code if any installed operator is unhealthy
+    #[arg(long)]
+    fail_if_unhealthy: bool,
+
+    /// Namespace in the cluster used to deploy the operators
+    #[arg(long, default_value = DEFAULT_OPERATOR_NAMESPACE, visible_aliases(["operator-ns"]))]
+    pub operator_namespace: String,
+}
+
+#[derive(Debug, Args)]
+pub struct OperatorHistoryArgs {
+    /// Operator to display the history for
+    #[arg(name = "OPERATOR")]
+    operator: operator::OperatorSpec,
+
+    #[arg(short, long = "output", value_enum, default_value_t = Default::default())]
+    output_type: OutputType,
+
+    /// Namespace in the cluster used to deploy the operators
+    #[arg(long, default_value = DEFAULT_OPERATOR_NAMESPACE, visible_aliases(["operator-ns"]))]
+    pub operator_namespace: String,
+}
+
+#[derive(Debug, Args)]
+pub struct OperatorRollbackArgs {
+    /// Operator to roll back
+    #[arg(name = "OPERATOR")]
+    operator: operator::OperatorSpec,
+
+    /// Revision to roll back to
+    #[arg(name = "REVISION")]
+    #[arg(long_help = "Revision to roll back to
+
+If no revision is specified, the operator is rolled back to the previous revision.
+Use \"stackablectl operator history <OPERATOR>\" to list available revisions.")]
+    revision: Option<u32>,
+
+    /// Namespace in the cluster used to deploy the operators
+    #[arg(long, default_value = DEFAULT_OPERATOR_NAMESPACE, visible_aliases(["operator-ns"]))]
+    pub operator_namespace: String,
+}
+
+#[derive(Debug, Args)]
+pub struct OperatorLogsArgs {
+    /// Operator to display the logs of
+    #[arg(name = "OPERATOR")]
+    operator: operator::OperatorSpec,
+
+    /// Stream new log lines until interrupted
+    #[arg(short, long)]
+    follow: bool,
+
+    /// Only show log lines which are newer than this duration, e.g. 5m or 1h
+    #[arg(long)]
+    since: Option<stackable_operator::time::Duration>,
+
+    /// Number of lines to show from the end of the logs of every container
+    #[arg(long)]
+    tail: Option<i64>,
+
+    /// Only show log lines with this level or a more severe one
+    #[arg(long, value_name = "LEVEL")]
+    #[arg(long_help = "Only show log lines with this level or a more severe one
+
+Only JSON formatted log lines can be filtered, other lines are always shown.")]
+    level: Option<Level>,
+
     /// Namespace in the cluster used to deploy the operators
     #[arg(long, default_value = DEFAULT_OPERATOR_NAMESPACE, visible_aliases(["operator-ns"]))]
     pub operator_namespace: String,
@@ -152,6 +282,12 @@ pub enum CmdError {
     #[snafu(display("cluster argument error"))]
     CommonClusterArgs { source: CommonClusterArgsError },
 
+    #[snafu(display("lockfile argument error"))]
+    CommonLockfileArgs { source: CommonLockfileArgsError },
+
+    #[snafu(display("operator values argument error"))]
+    CommonValuesArgs { source: CommonValuesArgsError },
+
     #[snafu(display("failed to serialize YAML output"))]
     SerializeYamlOutput { source: serde_yaml::Error },
 
@@ -173,11 +309,77 @@ pub enum CmdError {
     #[snafu(display("OCI error"))]
     OciError { source: oci::Error },
 
+    #[snafu(display("listing operator versions is not supported for local chart source {path:?}"))]
+    UnsupportedLocalChartSource { path: PathBuf },
+
     #[snafu(display("path/url parse error"))]
     PathOrUrlParse { source: PathOrUrlParseError },
 
     #[snafu(display("failed to load operator values"))]
     LoadOperatorValues { source: crate::utils::Error },
+
+    #[snafu(display("failed to resolve operator dependencies"))]
+    ResolveDependencies { source: operator::DependencyError },
+
+    #[snafu(display("unknown operator"))]
+    UnknownOperator { source: operator::DiscoveryError },
+
+    #[snafu(display("the operator(s) {operators} are unhealthy", operators = operators.join(", ")))]
+    UnhealthyOperators { operators: Vec<String> },
+
+    #[snafu(display("no version specified for {operator}-operator, use {operator}=<VERSION>"))]
+    MissingUpgradeVersion { operator: String },
+
+    #[snafu(display("failed to upgrade {operator}-operator"))]
+    UpgradeOperator {
+        #[snafu(source(from(operator::OperatorUpgradeError, Box::new)))]
+        source: Box<operator::OperatorUpgradeError>,
+        operator: String,
+    },
+
+    #[snafu(display("upgrading {operator}-operator failed and was rolled back ({error})"))]
+    UpgradeRolledBack { operator: String, error: String },
+
+    #[snafu(display("failed to list pods of {operator}-operator"))]
+    ListOperatorPods {
+        #[snafu(source(from(k8s::Error, Box::new)))]
+        source: Box<k8s::Error>,
+        operator: String,
+    },
+
+    #[snafu(display("no pods of {operator}-operator found in namespace {namespace:?}"))]
+    NoOperatorPods { operator: String, namespace: String },
+
+    #[snafu(display("failed to stream logs of container {container:?} of pod {pod:?}"))]
+    StreamLogs {
+        #[snafu(source(from(k8s::Error, Box::new)))]
+        source: Box<k8s::Error>,
+        pod: String,
+        container: String,
+    },
+
+    #[snafu(display("failed to read logs"))]
+    ReadLogs { source: std::io::Error },
+
+    #[snafu(display(
+        "refusing to uninstall {operator}-operator, because the installed operator(s) {dependents} depend on it",
+        dependents = dependents.join(", ")
+    ))]
+    OperatorRequired {
+        operator: String,
+        dependents: Vec<String>,
+    },
+}
+
+/// An installed operator with its Helm release and health.
+#[derive(Debug, Serialize)]
+pub struct InstalledOperator {
+    #[serde(flatten)]
+    release: Release,
+
+    /// The health is unknown if the CRDs of the cluster can't be listed, e.g.
+    /// because of missing cluster-wide RBAC permissions.
+    health: Option<operator::OperatorHealth>,
 }
 
 /// This list contains a list of operator version grouped by stable, test and
@@ -196,8 +398,12 @@ impl OperatorArgs {
             OperatorCommands::List(args) => list_cmd(args, cli).await,
             OperatorCommands::Describe(args) => describe_cmd(args, cli).await,
             OperatorCommands::Install(args) => install_cmd(args, cli, transfer_client).await,
-            OperatorCommands::Uninstall(args) => uninstall_cmd(args),
-            OperatorCommands::Installed(args) => installed_cmd(args),
+            OperatorCommands::Uninstall(args) => uninstall_cmd(args, cli, &transfer_client).await,
+            OperatorCommands::Installed(args) => installed_cmd(args, cli, &transfer_client).await,
+            OperatorCommands::Upgrade(args) => upgrade_cmd(args, cli, &transfer_client).await,
+            OperatorCommands::History(args) => history_cmd(args, cli).await,
+            OperatorCommands::Rollback(args) => rollback_cmd(args, cli).await,
+            OperatorCommands::Logs(args) => logs_cmd(args, cli).await,
         }
     }
 }
@@ -208,10 +414,9 @@ async fn list_cmd(args: &OperatorListArgs, cli: &Cli) -> Result<String, CmdError
     Span::current().pb_set_message("Fetching operator information");
 
     // Build map which maps artifacts to a chart source
-    let source_index_files =
-        build_source_index_file_list(&ChartSourceType::from(cli.chart_type())).await?;
+    let source_index_files = build_source_index_file_list(cli).await?;
 
-    // Iterate over all valid operators and create a list of versions grouped
+    // Iterate over all available operators and create a list of versions grouped
     // by stable, test and dev lines
     let versions_list = build_versions_list(&source_index_files)?;
 
@@ -266,8 +471,7 @@ async fn describe_cmd(args: &OperatorDescribeArgs, cli: &Cli) -> Result<String,
     Span::current().pb_set_message("Fetching operator information");
 
     // Build map which maps artifacts to a chart source
-    let source_index_files =
-        build_source_index_file_list(&ChartSourceType::from(cli.chart_type())).await?;
+    let source_index_files = build_source_index_file_list(cli).await?;
 
     // Create a list of versions for this operator
     let versions_list = build_versions_list_for_operator(&args.operator_name, &source_index_files)?;
@@ -350,6 +554,41 @@ async fn install_cmd(
         return Ok(String::new());
     }
 
+    let requested_operators: Vec<operator::OperatorSpec> = operators.into_iter().cloned().collect();
+
+    let known_operators = cli.known_operators(&transfer_client).await;
+    for operator in &requested_operators {
+        known_operators
+            .validate(operator)
+            .context(UnknownOperatorSnafu)?;
+    }
+
+    // Pull in the operators the requested ones depend on
+    let dependency_graph = DependencyGraph::builtin(known_operators.iter());
+    let operators = dependency_graph.resolve_specs(&requested_operators);
+
+    // Verify the operators against the lockfile before touching the cluster
+    args.lockfile
+        .verify_operators(&operators.iter().collect::<Vec<_>>(), cli)
+        .await
+        .context(CommonLockfileArgsSnafu)?;
+
+    let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+    let operator_values =
+        load_operator_values(&values_files, &cli.files.operator_set, &transfer_client)
+            .await
+            .context(LoadOperatorValuesSnafu)?;
+
+    args.values
+        .validate_operators(
+            &operators.iter().collect::<Vec<_>>(),
+            &operator_values,
+            cli,
+            &transfer_client,
+        )
+        .await
+        .context(CommonValuesArgsSnafu)?;
+
     args.local_cluster
         .install_if_needed()
         .await
@@ -363,24 +602,60 @@ async fn install_cmd(
             namespace: args.operator_namespace.clone(),
         })?;
 
-    let values_file = cli.get_values_file().context(PathOrUrlParseSnafu)?;
-    let operator_values = load_operator_values(values_file.as_ref(), &transfer_client)
-        .await
-        .context(LoadOperatorValuesSnafu)?;
+    let helm_client = args
+        .lockfile
+        .helm_client(cli)
+        .context(CommonLockfileArgsSnafu)?;
 
-    for operator in &operators {
-        let operator_helm_values = values_for_operator(&operator_values, &operator.name);
+    // Dependencies which are already installed are kept as is, regardless of
+    // their version
+    let installed_releases = helm_client
+        .list_releases(&args.operator_namespace)
+        .await
+        .context(HelmSnafu)?;
+    let operators: Vec<operator::OperatorSpec> = operators
+        .into_iter()
+        .filter(|operator| {
+            requested_operators
+                .iter()
+                .any(|requested| requested.name == operator.name)
+                || !installed_releases
+                    .iter()
+                    .any(|release| release.name == operator.helm_name())
+        })
+        .collect();
 
-        operator
-            .install(
-                &args.operator_namespace,
-                &ChartSourceType::from(cli.chart_type()),
-                &operator_helm_values,
-            )
-            .context(HelmSnafu)?;
+    let operator_names: Vec<String> = operators
+        .iter()
+        .map(|operator| operator.name.clone())
+        .collect();
+    let waves = dependency_graph
+        .waves(&operator_names)
+        .context(ResolveDependenciesSnafu)?;
+
+    // Operators are installed in waves, Helm waits for each operator to be
+    // ready before the next one is installed
+    for wave in waves {
+        for operator in operators
+            .iter()
+            .filter(|operator| wave.contains(&operator.name))
+        {
+            let operator_helm_values = values_for_operator(&operator_values, &operator.name);
+
+            operator
+                .install(
+                    &args.operator_namespace,
+                    &cli.chart_source(),
+                    &operator_helm_values,
+                    false,
+                    &helm_client,
+                )
+                .await
+                .context(HelmSnafu)?;
 
-        info!(%operator, "Installed operator");
-        indicatif_println!("Installed {operator} operator");
+            info!(%operator, "Installed operator");
+            indicatif_println!("Installed {operator} operator");
+        }
     }
 
     let mut result = Cli::result();
@@ -404,13 +679,57 @@ async fn install_cmd(
 }
 
 #[instrument(skip_all, fields(indicatif.pb_show = true))]
-fn uninstall_cmd(args: &OperatorUninstallArgs) -> Result<String, CmdError> {
+async fn uninstall_cmd(
+    args: &OperatorUninstallArgs,
+    cli: &Cli,
+    transfer_client: &xfer::Client,
+) -> Result<String, CmdError> {
     info!("Uninstalling operator(s)");
     Span::current().pb_set_message("Uninstalling operator(s)");
 
+    let helm_client = cli.helm_client();
+    let known_operators = cli.known_operators(transfer_client).await;
+
+    // Refuse to uninstall operators which remaining operators depend on
+    let remaining_operators: Vec<String> = helm_client
+        .list_releases(&args.operator_namespace)
+        .await
+        .context(HelmSnafu)?
+        .into_iter()
+        .filter_map(|release| {
+            known_operators
+                .iter()
+                .find(|known| release.name == utils::operator_chart_name(known))
+                .map(ToOwned::to_owned)
+        })
+        .filter(|installed| {
+            !args
+                .operators
+                .iter()
+                .any(|operator| &operator.name == installed)
+        })
+        .collect();
+
+    let dependency_graph = DependencyGraph::builtin(known_operators.iter());
+    for operator in &args.operators {
+        let dependents = dependency_graph.dependents(&operator.name, &remaining_operators);
+
+        if !dependents.is_empty() {
+            return OperatorRequiredSnafu {
+                operator: &operator.name,
+                dependents: dependents
+                    .into_iter()
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>(),
+            }
+            .fail();
+        }
+    }
+
     for operator in &args.operators {
         operator
-            .uninstall(&args.operator_namespace)
+            .uninstall(&args.operator_namespace, &helm_client)
+            .await
             .context(HelmSnafu)?;
     }
 
@@ -435,24 +754,199 @@ fn uninstall_cmd(args: &OperatorUninstallArgs) -> Result<String, CmdError> {
 }
 
 #[instrument(skip_all, fields(indicatif.pb_show = true))]
-fn installed_cmd(args: &OperatorInstalledArgs) -> Result<String, CmdError> {
+async fn upgrade_cmd(
+    args: &OperatorUpgradeArgs,
+    cli: &Cli,
+    transfer_client: &xfer::Client,
+) -> Result<String, CmdError> {
+    info!("Upgrading operator(s)");
+    Span::current().pb_set_message("Upgrading operator(s)");
+
+    // Fail early instead of after some operators were upgraded already
+    for operator in &args.operators {
+        ensure!(
+            operator.version.is_some(),
+            MissingUpgradeVersionSnafu {
+                operator: &operator.name,
+            }
+        );
+    }
+
+    let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+    let operator_values =
+        load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+            .await
+            .context(LoadOperatorValuesSnafu)?;
+
+    args.values
+        .validate_operators(
+            &args.operators.iter().collect::<Vec<_>>(),
+            &operator_values,
+            cli,
+            transfer_client,
+        )
+        .await
+        .context(CommonValuesArgsSnafu)?;
+
+    let client = Client::new().await.context(KubeClientCreateSnafu)?;
+    let helm_client = cli.helm_client();
+
+    for operator in &args.operators {
+        let parameters = operator::OperatorUpgradeParameters {
+            operator_namespace: args.operator_namespace.clone(),
+            chart_source: cli.chart_source(),
+            operator_values: values_for_operator(&operator_values, &operator.name),
+            crd_github_fallback: args.crd_github_fallback,
+            force_downgrade: args.force_downgrade,
+            force: args.force,
+        };
+
+        let report = operator
+            .upgrade(&parameters, &client, &helm_client, transfer_client)
+            .await
+            .context(UpgradeOperatorSnafu {
+                operator: &operator.name,
+            })?;
+
+        if let InstallReleaseStatus::RolledBack { error, .. } = report.status {
+            return UpgradeRolledBackSnafu {
+                operator: &operator.name,
+                error,
+            }
+            .fail();
+        }
+
+        indicatif_println!("{status}", status = report.status);
+        match report.crds {
+            CrdStatus::Replace { names, .. } => indicatif_println!(
+                "Replaced CRDs of {name}-operator: {names}",
+                name = operator.name,
+                names = names.join(", ")
+            ),
+            CrdStatus::OperatorManaged => indicatif_println!(
+                "The CRDs of {name}-operator are maintained by the operator itself",
+                name = operator.name
+            ),
+            CrdStatus::Missing => indicatif_println!(
+                "The chart of {name}-operator doesn't contain any CRDs",
+                name = operator.name
+            ),
+        }
+    }
+
+    let mut result = Cli::result();
+
+    result
+        .with_command_hint(
+            "stackablectl operator installed [OPTIONS]",
+            "list installed operators",
+        )
+        .with_output(format!(
+            "Upgraded {num_of_operators} {suffix}",
+            num_of_operators = args.operators.len(),
+            suffix = if args.operators.len() == 1 {
+                "operator"
+            } else {
+                "operators"
+            }
+        ));
+
+    Ok(result.render())
+}
+
+#[instrument(skip_all, fields(indicatif.pb_show = true))]
+async fn installed_cmd(
+    args: &OperatorInstalledArgs,
+    cli: &Cli,
+    transfer_client: &xfer::Client,
+) -> Result<String, CmdError> {
     info!("Listing installed operators");
     Span::current().pb_set_message("Fetching operator information");
 
-    type ReleaseList = IndexMap<String, Release>;
+    let known_operators = cli.known_operators(transfer_client).await;
 
-    let installed: ReleaseList = helm::list_releases(&args.operator_namespace)
+    let releases: Vec<(String, Release)> = cli
+        .helm_client()
+        .list_releases(&args.operator_namespace)
+        .await
         .context(HelmSnafu)?
         .into_iter()
-        .filter(|release| {
-            operator::VALID_OPERATORS
+        .filter_map(|release| {
+            known_operators
                 .iter()
-                .any(|valid| release.name == utils::operator_chart_name(valid))
+                .find(|known| release.name == utils::operator_chart_name(known))
+                .map(|known| (known.to_owned(), release))
         })
-        .map(|release| (release.name.clone(), release))
         .collect();
 
-    match args.output_type {
+    Span::current().pb_set_message("Checking operator health");
+
+    // The release files are only used to show which release an operator
+    // version belongs to, so failing to load them is not fatal
+    let release_list = match cli.get_release_files() {
+        Ok(files) => release::ReleaseList::build(&files, transfer_client)
+            .await
+            .inspect_err(|err| debug!(error = %err, "Failed to load release files"))
+            .ok(),
+        Err(err) => {
+            debug!(error = %err, "Failed to parse release files");
+            None
+        }
+    };
+
+    let client = Client::new().await.context(KubeClientCreateSnafu)?;
+
+    // Listing CRDs requires cluster-wide permissions, which users only allowed
+    // to list the releases in the operator namespace might not have. The
+    // releases are shown anyway, just without their health.
+    let crds = match client.list_crds().await {
+        Ok(crds) => Some(crds.items),
+        Err(err) => {
+            warn!(error = %err, "Failed to list CRDs, the operator health is unknown");
+            None
+        }
+    };
+
+    let mut installed = IndexMap::new();
+    for (operator_name, release) in releases {
+        let health = match &crds {
+            // Like the CRDs, the workloads and pods of an operator might not
+            // be accessible, which only makes its health unknown
+            Some(crds) => operator::OperatorHealth::collect(
+                &operator_name,
+                &release,
+                release_list.as_ref(),
+                crds,
+                &client,
+            )
+            .await
+            .inspect_err(|err| {
+                warn!(
+                    error = %err,
+                    operator = operator_name,
+                    "Failed to collect operator health, the operator health is unknown"
+                )
+            })
+            .ok(),
+            None => None,
+        };
+
+        installed.insert(release.name.clone(), InstalledOperator { release, health });
+    }
+
+    // Operators with unknown health can't be proven healthy
+    let unhealthy_operators: Vec<String> = installed
+        .iter()
+        .filter(|(_, operator)| {
+            !operator
+                .health
+                .as_ref()
+                .is_some_and(|health| health.healthy)
+        })
+        .map(|(release_name, _)| release_name.clone())
+        .collect();
+
+    let output = match args.output_type {
         OutputType::Plain | OutputType::Table => {
             if installed.is_empty() {
                 return Ok("No installed operators".into());
@@ -469,19 +963,50 @@ fn installed_cmd(args: &OperatorInstalledArgs) -> Result<String, CmdError> {
                 .set_header(vec![
                     "OPERATOR",
                     "VERSION",
+                    "RELEASE",
                     "NAMESPACE",
                     "STATUS",
+                    "READY",
+                    "RESTARTS",
+                    "IMAGES",
+                    "CRDS",
+                    "HEALTHY",
                     "LAST UPDATED",
                 ])
                 .set_content_arrangement(arrangement)
                 .load_preset(preset);
 
-            for (release_name, release) in installed {
+            for (release_name, InstalledOperator { release, health }) in installed {
+                let (sdp_release, replicas, restarts, images, crd_count, healthy) = match health {
+                    Some(health) => (
+                        health.release.unwrap_or_else(|| "-".into()),
+                        format!("{}/{}", health.ready_replicas, health.desired_replicas),
+                        health.restarts.to_string(),
+                        health.image_tags.join(", "),
+                        format!("{}/{}", health.established_crds, health.total_crds),
+                        if health.healthy { "yes" } else { "no" }.into(),
+                    ),
+                    None => (
+                        "-".into(),
+                        "-".into(),
+                        "-".into(),
+                        "-".into(),
+                        "-".into(),
+                        "unknown".into(),
+                    ),
+                };
+
                 table.add_row(vec![
                     release_name,
                     release.version,
+                    sdp_release,
                     release.namespace,
                     release.status,
+                    replicas,
+                    restarts,
+                    images,
+                    crd_count,
+                    healthy,
                     release.last_updated,
                 ]);
             }
@@ -499,25 +1024,226 @@ fn installed_cmd(args: &OperatorInstalledArgs) -> Result<String, CmdError> {
                 )
                 .with_output(table.to_string());
 
+            result.render()
+        }
+        OutputType::Json => serde_json::to_string(&installed).context(SerializeJsonOutputSnafu)?,
+        OutputType::Yaml => serde_yaml::to_string(&installed).context(SerializeYamlOutputSnafu)?,
+    };
+
+    // Print the output anyway, so that CI logs show why operators are unhealthy
+    if args.fail_if_unhealthy && !unhealthy_operators.is_empty() {
+        indicatif_println!("{output}");
+        return UnhealthyOperatorsSnafu {
+            operators: unhealthy_operators,
+        }
+        .fail();
+    }
+
+    Ok(output)
+}
+
+#[instrument(skip_all, fields(operator = %args.operator, indicatif.pb_show = true))]
+async fn history_cmd(args: &OperatorHistoryArgs, cli: &Cli) -> Result<String, CmdError> {
+    info!("Listing operator history");
+    Span::current().pb_set_message("Fetching operator history");
+
+    let history = cli
+        .helm_client()
+        .release_history(&args.operator.helm_name(), &args.operator_namespace)
+        .await
+        .context(HelmSnafu)?;
+
+    match args.output_type {
+        OutputType::Plain | OutputType::Table => {
+            let (arrangement, preset) = match args.output_type {
+                OutputType::Plain => (ContentArrangement::Disabled, NOTHING),
+                _ => (ContentArrangement::Dynamic, UTF8_FULL),
+            };
+
+            let mut table = Table::new();
+
+            table
+                .set_header(vec![
+                    "REVISION",
+                    "VERSION",
+                    "APP VERSION",
+                    "STATUS",
+                    "UPDATED",
+                    "DESCRIPTION",
+                ])
+                .set_content_arrangement(arrangement)
+                .load_preset(preset);
+
+            for revision in history {
+                table.add_row(vec![
+                    revision.revision.to_string(),
+                    revision.chart_version,
+                    revision.app_version,
+                    revision.status,
+                    revision.updated,
+                    revision.description,
+                ]);
+            }
+
+            let mut result = Cli::result();
+
+            result
+                .with_command_hint(
+                    format!(
+                        "stackablectl operator rollback {operator} [REVISION]",
+                        operator = args.operator.name
+                    ),
+                    "roll back the operator to a previous revision",
+                )
+                .with_output(table.to_string());
+
             Ok(result.render())
         }
-        OutputType::Json => serde_json::to_string(&installed).context(SerializeJsonOutputSnafu),
-        OutputType::Yaml => serde_yaml::to_string(&installed).context(SerializeYamlOutputSnafu),
+        OutputType::Json => serde_json::to_string(&history).context(SerializeJsonOutputSnafu),
+        OutputType::Yaml => serde_yaml::to_string(&history).context(SerializeYamlOutputSnafu),
     }
 }
 
+#[instrument(skip_all, fields(operator = %args.operator, indicatif.pb_show = true))]
+async fn rollback_cmd(args: &OperatorRollbackArgs, cli: &Cli) -> Result<String, CmdError> {
+    info!("Rolling back operator");
+    Span::current().pb_set_message("Rolling back operator");
+
+    cli.helm_client()
+        .rollback_release(
+            &args.operator.helm_name(),
+            &args.operator_namespace,
+            args.revision,
+        )
+        .await
+        .context(HelmSnafu)?;
+
+    let mut result = Cli::result();
+
+    result
+        .with_command_hint(
+            "stackablectl operator installed [OPTIONS]",
+            "list installed operators",
+        )
+        .with_output(match args.revision {
+            Some(revision) => format!(
+                "Rolled back {operator} operator to revision {revision}",
+                operator = args.operator.name
+            ),
+            None => format!(
+                "Rolled back {operator} operator to the previous revision",
+                operator = args.operator.name
+            ),
+        });
+
+    Ok(result.render())
+}
+
+#[instrument(skip_all, fields(operator = %args.operator))]
+async fn logs_cmd(args: &OperatorLogsArgs, cli: &Cli) -> Result<String, CmdError> {
+    info!("Streaming operator logs");
+
+    let client = Client::new().await.context(KubeClientCreateSnafu)?;
+
+    // The operator pods are matched by the Helm release label
+    let mut list_params = ListParams::default();
+    list_params.add_label(format!(
+        "app.kubernetes.io/instance={release_name}",
+        release_name = args.operator.helm_name()
+    ));
+
+    let pods = client
+        .list_pods(Some(&args.operator_namespace), &list_params)
+        .await
+        .context(ListOperatorPodsSnafu {
+            operator: &args.operator.name,
+        })?;
+
+    // Operator pods can run multiple containers, e.g. the CSI node driver of
+    // the secret operator, so the logs of every container are streamed
+    let containers: Vec<(String, LogParams)> = pods
+        .iter()
+        .flat_map(|pod| {
+            let pod_name = pod.name_any();
+            pod.spec
+                .iter()
+                .flat_map(|spec| &spec.containers)
+                .map(move |container| {
+                    let log_params = LogParams {
+                        container: Some(container.name.clone()),
+                        follow: args.follow,
+                        since_seconds: args.since.as_ref().map(|since| since.as_secs() as i64),
+                        tail_lines: args.tail,
+                        ..Default::default()
+                    };
+
+                    (pod_name.clone(), log_params)
+                })
+        })
+        .collect();
+
+    ensure!(
+        !containers.is_empty(),
+        NoOperatorPodsSnafu {
+            operator: &args.operator.name,
+            namespace: &args.operator_namespace,
+        }
+    );
+
+    let mut streams = Vec::new();
+    for (pod_name, log_params) in &containers {
+        let container_name = log_params.container.clone().unwrap_or_default();
+        let lines = client
+            .stream_pod_logs(&args.operator_namespace, pod_name, log_params)
+            .await
+            .with_context(|_| StreamLogsSnafu {
+                pod: pod_name,
+                container: &container_name,
+            })?;
+
+        // Lines are prefixed with their origin if multiple containers are
+        // streamed at once
+        let prefix = if containers.len() > 1 {
+            format!("[{pod_name}/{container_name}] ")
+        } else {
+            String::new()
+        };
+
+        streams.push(operator::filter_and_prefix_log_lines(lines, args.level, prefix).boxed());
+    }
+
+    let mut lines = futures::stream::select_all(streams);
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next() => line,
+            _ = cli.cancellation_token.cancelled() => break,
+        };
+
+        let Some(line) = line else {
+            break;
+        };
+        let line = line.context(ReadLogsSnafu)?;
+        indicatif_println!("{line}");
+    }
+
+    Ok(String::new())
+}
+
 /// Builds a map which maps artifact tags to a chart source.
-#[instrument]
+#[instrument(skip(cli))]
 async fn build_source_index_file_list<'a>(
-    chart_source: &ChartSourceType,
+    cli: &Cli,
 ) -> Result<HashMap<&'a str, ChartSourceMetadata>, CmdError> {
     debug!("Building source index file list");
 
     let mut source_index_files: HashMap<&str, ChartSourceMetadata> = HashMap::new();
 
-    match chart_source {
-        ChartSourceType::OCI => {
-            source_index_files = oci::get_oci_index().await.context(OciSnafu)?;
+    match cli.chart_source() {
+        ChartSourceType::OCI(registry) => {
+            source_index_files = oci::get_oci_index(&registry, &cli.repository_auth)
+                .await
+                .context(OciSnafu)?;
 
             debug!(count = source_index_files.len(), "OCI Repository entries");
 
@@ -540,12 +1266,14 @@ async fn build_source_index_file_list<'a>(
                 HELM_REPO_NAME_TEST,
                 HELM_REPO_NAME_DEV,
             ] {
-                let helm_repo_url =
-                    helm_repo_name_to_repo_url(helm_repo_name).context(InvalidRepoNameSnafu)?;
+                let helm_repo_url = cli
+                    .repos
+                    .helm_repo_url(helm_repo_name)
+                    .context(InvalidRepoNameSnafu)?;
 
                 source_index_files.insert(
                     helm_repo_name,
-                    helm::get_helm_index(helm_repo_url)
+                    helm::get_helm_index(helm_repo_url, &cli.repository_auth)
                         .await
                         .context(HelmSnafu)?,
                 );
@@ -553,13 +1281,16 @@ async fn build_source_index_file_list<'a>(
                 debug!("Helm Repository entries: {:?}", source_index_files);
             }
         }
+        ChartSourceType::Local(path) => {
+            return UnsupportedLocalChartSourceSnafu { path }.fail();
+        }
     };
 
     Ok(source_index_files)
 }
 
-/// Iterates over all valid operators and creates a list of versions grouped
-/// by stable, test and dev lines based on the list of Helm repo index files.
+/// Iterates over all operators with a chart in any of the Helm repo index files
+/// and creates a list of versions grouped by stable, test and dev lines.
 #[instrument(skip_all)]
 fn build_versions_list(
     helm_index_files: &HashMap<&str, ChartSourceMetadata>,
@@ -568,7 +1299,13 @@ fn build_versions_list(
 
     let mut versions_list = IndexMap::new();
 
-    for operator in operator::VALID_OPERATORS {
+    let operators: BTreeSet<&str> = helm_index_files
+        .values()
+        .flat_map(|index_file| index_file.entries.keys())
+        .filter_map(|chart_name| chart_name.strip_suffix("-operator"))
+        .collect();
+
+    for operator in operators {
         for (helm_repo_name, helm_repo_index_file) in helm_index_files {
             let span = tracing::info_span!(
                 "build_versions_list_iter",
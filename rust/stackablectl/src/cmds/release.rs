@@ -1,19 +1,32 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueHint};
 use comfy_table::{
     ContentArrangement, Table,
     presets::{NOTHING, UTF8_FULL},
 };
-use snafu::{ResultExt, Snafu};
+use dialoguer::Confirm;
+use serde_yaml::Mapping;
+use snafu::{OptionExt as _, ResultExt, Snafu};
 use stackable_cockpit::{
     common::list,
     constants::DEFAULT_OPERATOR_NAMESPACE,
-    helm::{self, Release},
+    helm,
     platform::{
-        namespace,
-        operator::{self, ChartSourceType},
-        release,
+        demo::{self, DemoInstallParameters},
+        lock::{self, Lockfile},
+        namespace, operator,
+        plan::InstallPlan,
+        release::{
+            self, CrdSource, CrdStatus, CrdUpgradeParameters, CrdUpgradeReport,
+            ReleaseInstallParameters,
+            diff::ReleaseDiff,
+            upgrade::{self, UpgradeParameters, UpgradePlan, UpgradeState, UpgradeStep},
+        },
+        stack::{self, StackInstallParameters},
     },
     utils::{
         self,
@@ -22,12 +35,17 @@ use stackable_cockpit::{
     },
     xfer,
 };
-use tracing::{Span, debug, info, instrument};
-use tracing_indicatif::span_ext::IndicatifSpanExt as _;
+use stackable_operator::kvp::Labels;
+use tracing::{Span, debug, info, instrument, warn};
+use tracing_indicatif::{indicatif_println, span_ext::IndicatifSpanExt as _};
 
 use crate::{
-    args::{CommonClusterArgs, CommonClusterArgsError},
+    args::{
+        CommonClusterArgs, CommonClusterArgsError, CommonLockfileArgs, CommonLockfileArgsError,
+        CommonPromptArgs, CommonValuesArgs, CommonValuesArgsError,
+    },
     cli::{Cli, OutputType},
+    constants::DEFAULT_LOCKFILE,
     utils::load_operator_values,
 };
 
@@ -47,6 +65,9 @@ pub enum ReleaseCommands {
     #[command(alias("desc"))]
     Describe(ReleaseDescribeArgs),
 
+    /// Show the differences between two releases
+    Diff(ReleaseDiffArgs),
+
     /// Install a specific release
     #[command(aliases(["i", "in"]))]
     Install(ReleaseInstallArgs),
@@ -57,6 +78,9 @@ pub enum ReleaseCommands {
 
     /// Upgrade a release
     Upgrade(ReleaseUpgradeArgs),
+
+    /// Lock the charts of a release to exact versions and digests
+    Lock(ReleaseLockArgs),
 }
 
 #[derive(Debug, Args)]
@@ -74,15 +98,48 @@ pub struct ReleaseDescribeArgs {
     output_type: OutputType,
 }
 
+#[derive(Debug, Args)]
+pub struct ReleaseDiffArgs {
+    /// Release to compare from
+    #[arg(name = "FROM")]
+    from: String,
+
+    /// Release to compare to
+    #[arg(name = "TO")]
+    to: String,
+
+    /// Also compare the CRDs of changed operators
+    ///
+    /// This takes the CRD manifests of both releases from the operator charts
+    /// (falling back to the upstream GitHub repositories if a chart contains
+    /// no CRDs) and shows added and removed CRD versions as well as schema
+    /// field changes.
+    #[arg(long)]
+    crds: bool,
+
+    #[arg(short, long = "output", value_enum, default_value_t = Default::default())]
+    output_type: OutputType,
+}
+
 #[derive(Debug, Args)]
 pub struct ReleaseInstallArgs {
     /// Release to install
     #[arg(name = "RELEASE")]
     release: String,
 
-    /// Whitelist of product operators to install
+    /// Whitelist of product operators to install, optionally with a version override
     #[arg(short, long = "include", group = "products")]
-    included_products: Vec<String>,
+    #[arg(
+        long_help = "Whitelist of product operators to install, optionally with a version override
+
+Products use the format '<PRODUCT>[=<VERSION>]'. If a version is provided, it
+overrides the version of the release, e.g. to validate a hotfix or development
+build before it is released. All other products follow the release.
+
+Use \"stackablectl release install 25.3 -i trino=25.3.1 -i opa=0.0.0-dev\" to
+install the trino and opa operators in the provided versions."
+    )]
+    included_products: Vec<operator::OperatorSpec>,
 
     /// Blacklist of product operators to install
     #[arg(short, long = "exclude", group = "products")]
@@ -92,8 +149,42 @@ pub struct ReleaseInstallArgs {
     #[arg(long, default_value = DEFAULT_OPERATOR_NAMESPACE, visible_aliases(["operator-ns"]))]
     pub operator_namespace: String,
 
+    /// Only warn instead of failing if the release doesn't support the Kubernetes version of the cluster
+    #[arg(long)]
+    skip_kubernetes_version_check: bool,
+
     #[command(flatten)]
     local_cluster: CommonClusterArgs,
+
+    #[command(flatten)]
+    lockfile: CommonLockfileArgs,
+
+    #[command(flatten)]
+    values: CommonValuesArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ReleaseLockArgs {
+    /// Release to lock
+    #[arg(name = "RELEASE")]
+    release: String,
+
+    /// Also lock the Helm charts of the provided stack
+    #[arg(long, conflicts_with = "demo")]
+    stack: Option<String>,
+
+    /// Also lock the Helm charts of the provided demo and its stack
+    #[arg(long)]
+    demo: Option<String>,
+
+    /// Path the lockfile is written to
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        default_value = DEFAULT_LOCKFILE
+    )]
+    lockfile: PathBuf,
 }
 
 #[derive(Debug, Args)]
@@ -113,6 +204,36 @@ pub struct ReleaseUpgradeArgs {
     /// Namespace in the cluster used to deploy the operators
     #[arg(long, default_value = DEFAULT_OPERATOR_NAMESPACE, visible_aliases(["operator-ns"]))]
     pub operator_namespace: String,
+
+    /// Only print the upgrade plan without touching the cluster
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Discard the progress of a previously failed upgrade instead of resuming it
+    #[arg(long)]
+    restart: bool,
+
+    /// Retrieve CRDs from the upstream GitHub repositories if the operator chart doesn't contain them
+    ///
+    /// By default, CRDs are taken from the same chart which is installed. Operators which maintain
+    /// their CRDs themselves don't ship CRDs in their chart and are always skipped.
+    #[arg(long)]
+    crd_github_fallback: bool,
+
+    /// Replace conflicting resources instead of patching them if an operator is still installed
+    ///
+    /// Operators which are still installed in another version when the new operator releases are
+    /// installed (e.g. because they were re-installed in the meantime) are upgraded in place
+    /// instead of failing the upgrade.
+    #[arg(long)]
+    force: bool,
+
+    /// Only warn instead of failing if the release doesn't support the Kubernetes version of the cluster
+    #[arg(long)]
+    skip_kubernetes_version_check: bool,
+
+    #[command(flatten)]
+    prompt_args: CommonPromptArgs,
 }
 
 #[derive(Debug, Args)]
@@ -146,12 +267,48 @@ pub enum CmdError {
     #[snafu(display("no release {release:?}"))]
     NoSuchRelease { release: String },
 
+    #[snafu(display("failed to compare CRDs of releases"))]
+    DiffCrds { source: release::diff::Error },
+
     #[snafu(display("failed to install release"))]
     ReleaseInstall { source: release::Error },
 
+    #[snafu(display("failed to override product versions of release {release:?}"))]
+    OverrideVersions {
+        source: release::Error,
+        release: String,
+    },
+
+    #[snafu(display("release prerequisites are not met"))]
+    ReleasePrerequisites { source: release::Error },
+
     #[snafu(display("failed to upgrade CRDs for release"))]
     CrdUpgrade { source: release::Error },
 
+    #[snafu(display("failed to plan release upgrade"))]
+    PlanUpgrade { source: upgrade::Error },
+
+    #[snafu(display("failed to access release upgrade state"))]
+    UpgradeState { source: upgrade::Error },
+
+    #[snafu(display(
+        "an upgrade to release {release:?} of operators in namespace {namespace:?} is in progress, re-run it to resume or pass --restart"
+    ))]
+    UpgradeInProgress { release: String, namespace: String },
+
+    #[snafu(display(
+        "failed to {step} while upgrading to release {release:?}, re-run the command to resume the upgrade"
+    ))]
+    UpgradeStep {
+        #[snafu(source(from(CmdError, Box::new)))]
+        source: Box<CmdError>,
+        step: UpgradeStep,
+        release: String,
+    },
+
+    #[snafu(display("failed to confirm user input"))]
+    ConfirmDialog { source: dialoguer::Error },
+
     #[snafu(display("failed to uninstall release"))]
     ReleaseUninstall { source: release::Error },
 
@@ -169,6 +326,45 @@ pub enum CmdError {
 
     #[snafu(display("failed to load operator values"))]
     LoadOperatorValues { source: crate::utils::Error },
+
+    #[snafu(display("lockfile argument error"))]
+    CommonLockfileArgs { source: CommonLockfileArgsError },
+
+    #[snafu(display("operator values argument error"))]
+    CommonValuesArgs { source: CommonValuesArgsError },
+
+    #[snafu(display("no stack {name:?}"))]
+    NoSuchStack { name: String },
+
+    #[snafu(display("no demo {name:?}"))]
+    NoSuchDemo { name: String },
+
+    #[snafu(display("failed to build install plan for stack {stack_name:?}"))]
+    PlanStack {
+        source: stack::Error,
+        stack_name: String,
+    },
+
+    #[snafu(display("failed to build install plan for demo {demo_name:?}"))]
+    PlanDemo {
+        source: demo::Error,
+        demo_name: String,
+    },
+
+    #[snafu(display("failed to lock charts of release {release:?}"))]
+    LockCharts {
+        source: lock::Error,
+        release: String,
+    },
+
+    #[snafu(display("failed to serialize lockfile"))]
+    SerializeLockfile { source: serde_yaml::Error },
+
+    #[snafu(display("failed to write lockfile to {path:?}"))]
+    WriteLockfile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
 }
 
 impl ReleaseArgs {
@@ -191,13 +387,17 @@ impl ReleaseArgs {
         match &self.subcommand {
             ReleaseCommands::List(args) => list_cmd(args, release_list).await,
             ReleaseCommands::Describe(args) => describe_cmd(args, release_list).await,
+            ReleaseCommands::Diff(args) => diff_cmd(args, cli, release_list, &transfer_client).await,
             ReleaseCommands::Install(args) => {
                 install_cmd(args, cli, release_list, &transfer_client).await
             }
-            ReleaseCommands::Uninstall(args) => uninstall_cmd(args, release_list).await,
+            ReleaseCommands::Uninstall(args) => uninstall_cmd(args, cli, release_list).await,
             ReleaseCommands::Upgrade(args) => {
                 upgrade_cmd(args, cli, release_list, &transfer_client).await
             }
+            ReleaseCommands::Lock(args) => {
+                lock_cmd(args, cli, release_list, &transfer_client).await
+            }
         }
     }
 }
@@ -293,6 +493,13 @@ async fn describe_cmd(
                     .add_row(vec!["RELEASE", &args.release])
                     .add_row(vec!["RELEASE DATE", release.date.as_str()])
                     .add_row(vec!["DESCRIPTION", release.description.as_str()])
+                    .add_row(vec![
+                        "KUBERNETES VERSIONS",
+                        &release
+                            .supported_kubernetes_versions
+                            .as_ref()
+                            .map_or_else(|| "any".to_owned(), ToString::to_string),
+                    ])
                     .add_row(vec![
                         "INCLUDED PRODUCTS",
                         product_table.to_string().as_str(),
@@ -322,6 +529,138 @@ async fn describe_cmd(
     }
 }
 
+#[instrument(skip(cli, release_list, transfer_client), fields(indicatif.pb_show = true))]
+async fn diff_cmd(
+    args: &ReleaseDiffArgs,
+    cli: &Cli,
+    release_list: release::ReleaseList,
+    transfer_client: &xfer::Client,
+) -> Result<String, CmdError> {
+    info!(from = %args.from, to = %args.to, "Comparing releases");
+    Span::current().pb_set_message("Comparing releases");
+
+    let from = release_list.get(&args.from).context(NoSuchReleaseSnafu {
+        release: &args.from,
+    })?;
+    let to = release_list
+        .get(&args.to)
+        .context(NoSuchReleaseSnafu { release: &args.to })?;
+
+    let mut diff = ReleaseDiff::new(&args.from, from, &args.to, to);
+
+    if args.crds {
+        diff = diff
+            .with_crds(
+                from,
+                to,
+                &cli.chart_source(),
+                &cli.helm_client(),
+                transfer_client,
+            )
+            .await
+            .context(DiffCrdsSnafu)?;
+    }
+
+    match args.output_type {
+        OutputType::Plain | OutputType::Table => {
+            let (arrangement, preset) = match args.output_type {
+                OutputType::Plain => (ContentArrangement::Disabled, NOTHING),
+                _ => (ContentArrangement::Dynamic, UTF8_FULL),
+            };
+
+            let mut output = String::new();
+
+            if diff.operators.is_empty() {
+                output.push_str("No operator changes\n");
+            } else {
+                let mut table = Table::new();
+                table
+                    .set_header(vec![
+                        "PRODUCT",
+                        args.from.as_str(),
+                        args.to.as_str(),
+                        "CHANGE",
+                    ])
+                    .set_content_arrangement(arrangement.clone())
+                    .load_preset(preset);
+
+                for operator in &diff.operators {
+                    table.add_row(vec![
+                        operator.name.clone(),
+                        operator.from_version.clone().unwrap_or_default(),
+                        operator.to_version.clone().unwrap_or_default(),
+                        operator.change.to_string(),
+                    ]);
+                }
+
+                output.push_str(&table.to_string());
+                output.push('\n');
+            }
+
+            if args.crds {
+                output.push('\n');
+
+                if diff.crds.is_empty() {
+                    output.push_str("No CRD changes\n");
+                } else {
+                    let mut table = Table::new();
+                    table
+                        .set_header(vec!["CRD", "CHANGE", "DETAILS"])
+                        .set_content_arrangement(arrangement)
+                        .load_preset(preset);
+
+                    for crd in &diff.crds {
+                        let details = [
+                            ("added version", &crd.added_versions),
+                            ("removed version", &crd.removed_versions),
+                            ("added field", &crd.added_fields),
+                            ("removed field", &crd.removed_fields),
+                            ("changed field", &crd.changed_fields),
+                        ]
+                        .iter()
+                        .flat_map(|(kind, entries)| {
+                            entries.iter().map(move |entry| format!("{kind} {entry}"))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                        table.add_row(vec![
+                            crd.name.clone(),
+                            crd.change
+                                .map(|change| change.to_string())
+                                .unwrap_or_default(),
+                            details,
+                        ]);
+                    }
+
+                    output.push_str(&table.to_string());
+                    output.push('\n');
+                }
+
+                if !diff.crds_unavailable.is_empty() {
+                    output.push_str(&format!(
+                        "\nCRDs of the following operators were not compared, because they are maintained by the operators themselves: {operators}\n",
+                        operators = diff.crds_unavailable.join(", ")
+                    ));
+                }
+            }
+
+            let mut result = Cli::result();
+
+            result
+                .with_command_hint(
+                    format!("stackablectl release upgrade {release}", release = args.to),
+                    "upgrade to the newer release",
+                )
+                .with_output(output.trim_end());
+
+            Ok(result.render())
+        }
+        OutputType::Json => serde_json::to_string(&diff).context(SerializeJsonOutputSnafu),
+        OutputType::Yaml => serde_yaml::to_string(&diff).context(SerializeYamlOutputSnafu),
+    }
+}
+
 #[instrument(skip(cli, release_list, transfer_client), fields(indicatif.pb_show = true))]
 async fn install_cmd(
     args: &ReleaseInstallArgs,
@@ -336,6 +675,44 @@ async fn install_cmd(
         Some(release) => {
             let mut output = Cli::result();
 
+            // Products included with a version are installed in that version
+            // instead of the one defined by the release
+            let release = release
+                .with_version_overrides(&args.included_products)
+                .context(OverrideVersionsSnafu {
+                    release: &args.release,
+                })?;
+            let included_products: Vec<String> = args
+                .included_products
+                .iter()
+                .map(|operator| operator.name.clone())
+                .collect();
+
+            // Verify the release against the lockfile before touching the cluster
+            let mut plan = InstallPlan::default();
+            plan.add_release(
+                &release,
+                &included_products,
+                &args.excluded_products,
+                &args.operator_namespace,
+                &cli.chart_source(),
+            );
+            args.lockfile
+                .verify_plan(&plan, cli)
+                .await
+                .context(CommonLockfileArgsSnafu)?;
+
+            let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+            let operator_values =
+                load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+                    .await
+                    .context(LoadOperatorValuesSnafu)?;
+
+            args.values
+                .validate_plan(&plan, &operator_values, cli, transfer_client)
+                .await
+                .context(CommonValuesArgsSnafu)?;
+
             // Install local cluster if needed
             args.local_cluster
                 .install_if_needed()
@@ -344,6 +721,11 @@ async fn install_cmd(
 
             let client = Client::new().await.context(KubeClientCreateSnafu)?;
 
+            release
+                .check_prerequisites(&args.release, &client, args.skip_kubernetes_version_check)
+                .await
+                .context(ReleasePrerequisitesSnafu)?;
+
             // Create operator namespace if needed
             namespace::create_if_needed(&client, args.operator_namespace.clone())
                 .await
@@ -351,18 +733,22 @@ async fn install_cmd(
                     namespace: args.operator_namespace.clone(),
                 })?;
 
-            let values_file = cli.get_values_file().context(PathOrUrlParseSnafu)?;
-            let operator_values = load_operator_values(values_file.as_ref(), transfer_client)
-                .await
-                .context(LoadOperatorValuesSnafu)?;
+            let parameters = ReleaseInstallParameters {
+                operator_namespace: args.operator_namespace.clone(),
+                chart_source: cli.chart_source(),
+                operator_values,
+                force: false,
+            };
 
             release
                 .install(
-                    &args.included_products,
+                    &included_products,
                     &args.excluded_products,
-                    &args.operator_namespace,
-                    &ChartSourceType::from(cli.chart_type()),
-                    &operator_values,
+                    &parameters,
+                    &args
+                        .lockfile
+                        .helm_client(cli)
+                        .context(CommonLockfileArgsSnafu)?,
                 )
                 .await
                 .context(ReleaseInstallSnafu)?;
@@ -395,91 +781,400 @@ async fn upgrade_cmd(
     info!(release = %args.release, "Upgrading release");
     Span::current().pb_set_message("Upgrading release");
 
-    match release_list.get(&args.release) {
-        Some(release) => {
-            let mut output = Cli::result();
-            let client = Client::new().await.context(KubeClientCreateSnafu)?;
+    let release = release_list
+        .get(&args.release)
+        .context(NoSuchReleaseSnafu {
+            release: &args.release,
+        })?;
+
+    let mut output = Cli::result();
+    let helm_client = cli.helm_client();
+    let state_file = cli.release_upgrade_state_file(&args.operator_namespace);
+
+    // Resume a previously failed upgrade, as the operators which were already
+    // uninstalled can't be determined from the cluster anymore
+    let previous_state = UpgradeState::load(&state_file)
+        .context(UpgradeStateSnafu)?
+        .filter(|_| !args.restart);
+
+    let mut state = match previous_state {
+        Some(state) if state.plan.release != args.release => {
+            return UpgradeInProgressSnafu {
+                release: state.plan.release,
+                namespace: &args.operator_namespace,
+            }
+            .fail();
+        }
+        Some(state) => {
+            info!(
+                failed_step = state.failed_step.map(tracing::field::display),
+                "Resuming previously failed upgrade"
+            );
+            state
+        }
+        None => {
+            let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+            let operator_values =
+                load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+                    .await
+                    .context(LoadOperatorValuesSnafu)?;
+
+            let parameters = UpgradeParameters {
+                release_name: args.release.clone(),
+                include_products: args.included_products.clone(),
+                exclude_products: args.excluded_products.clone(),
+                operator_namespace: args.operator_namespace.clone(),
+                operator_values,
+                chart_source: cli.chart_source(),
+                crd_github_fallback: args.crd_github_fallback,
+            };
 
-            // Get all currently installed operators to only upgrade those
-            let installed_charts: Vec<Release> =
-                helm::list_releases(&args.operator_namespace).context(HelmSnafu)?;
+            let plan = UpgradePlan::new(release, &parameters, &helm_client, transfer_client)
+                .await
+                .context(PlanUpgradeSnafu)?;
 
-            let mut operators: Vec<String> = operator::VALID_OPERATORS
-                .iter()
+            UpgradeState::new(plan)
+        }
+    };
+
+    if state.plan.operators.is_empty() {
+        output
+            .with_command_hint(
+                format!(
+                    "stackablectl release upgrade {release} --include <PRODUCT>",
+                    release = args.release
+                ),
+                "install additional operators as part of the upgrade",
+            )
+            .with_output("No installed operators to upgrade");
+
+        return Ok(output.render());
+    }
+
+    let plan_table = upgrade_plan_table(&state);
+
+    if args.dry_run {
+        output
+            .with_command_hint(
+                format!(
+                    "stackablectl release upgrade {release}",
+                    release = args.release
+                ),
+                "execute the upgrade",
+            )
+            .with_output(plan_table);
+
+        return Ok(output.render());
+    }
+
+    let client = Client::new().await.context(KubeClientCreateSnafu)?;
+    release
+        .check_prerequisites(&args.release, &client, args.skip_kubernetes_version_check)
+        .await
+        .context(ReleasePrerequisitesSnafu)?;
+
+    let proceed_with_upgrade = args.prompt_args.assume_yes
+        || {
+            // Show the plan the user is asked to confirm
+            indicatif_println!("{plan_table}\n");
+
+            tracing_indicatif::suspend_tracing_indicatif(|| -> Result<bool, CmdError> {
+                Confirm::new()
+                .with_prompt(format!(
+                    "Upgrading to release {release:?} uninstalls the listed operators and replaces their CRDs. Proceed?",
+                    release = args.release
+                ))
+                .default(false)
+                .interact()
+                .context(ConfirmDialogSnafu)
+            })?
+        };
+
+    if !proceed_with_upgrade {
+        output.with_output(format!(
+            "Upgrade to release {release:?} canceled",
+            release = args.release
+        ));
+
+        return Ok(output.render());
+    }
+
+    let operators = state.plan.operators();
+
+    // Only the planned operators are upgraded, their dependencies must not be
+    // pulled in, as they might be installed in a different version
+    let unplanned_operators: Vec<String> = release
+        .products
+        .keys()
+        .filter(|product| !operators.contains(product))
+        .cloned()
+        .collect();
+
+    // Uninstall the old operator releases first. Operators which were already
+    // uninstalled by a previous run are skipped.
+    if !state.is_completed(UpgradeStep::UninstallOperators) {
+        let result = async {
+            let installed_releases = helm_client
+                .list_releases(&args.operator_namespace)
+                .await
+                .context(HelmSnafu)?;
+
+            let installed_operators: Vec<String> = state
+                .plan
+                .installed_operators()
+                .into_iter()
                 .filter(|operator| {
-                    installed_charts
+                    installed_releases
                         .iter()
                         .any(|release| release.name == utils::operator_chart_name(operator))
                 })
-                .map(|operator| operator.to_string())
                 .collect();
 
-            // Uninstall the old operator release first
-            release
-                .uninstall(
-                    &operators,
-                    &args.excluded_products,
-                    &args.operator_namespace,
-                )
-                .context(ReleaseUninstallSnafu)?;
-
-            // If operators were added to args.included_products, install them as well
-            for product in &args.included_products {
-                if !operators.contains(product) {
-                    operators.push(product.clone());
-                }
+            // An empty list would uninstall all operators of the release
+            if !installed_operators.is_empty() {
+                release
+                    .uninstall(
+                        &installed_operators,
+                        &[],
+                        &args.operator_namespace,
+                        &helm_client,
+                    )
+                    .await
+                    .context(ReleaseUninstallSnafu)?;
             }
 
-            // Upgrade the CRDs for all the operators to be upgraded
-            release
-                .upgrade_crds(
-                    &operators,
-                    &args.excluded_products,
-                    &args.operator_namespace,
-                    &client,
-                    transfer_client,
-                )
-                .await
-                .context(CrdUpgradeSnafu)?;
+            Ok::<(), CmdError>(())
+        }
+        .await;
+
+        record_upgrade_step(
+            &mut state,
+            UpgradeStep::UninstallOperators,
+            &state_file,
+            result,
+        )?;
+    }
 
-            let values_file = cli.get_values_file().context(PathOrUrlParseSnafu)?;
-            let operator_values = load_operator_values(values_file.as_ref(), transfer_client)
-                .await
-                .context(LoadOperatorValuesSnafu)?;
+    // Upgrade the CRDs for all the operators to be upgraded
+    let mut crd_report = None;
+    if !state.is_completed(UpgradeStep::ReplaceCrds) {
+        let result = async {
+            let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+            let operator_values =
+                load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+                    .await
+                    .context(LoadOperatorValuesSnafu)?;
+
+            let parameters = CrdUpgradeParameters {
+                operator_namespace: args.operator_namespace.clone(),
+                chart_source: cli.chart_source(),
+                operator_values,
+                github_fallback: args.crd_github_fallback,
+            };
+
+            crd_report = Some(
+                release
+                    .upgrade_crds(
+                        &operators,
+                        &unplanned_operators,
+                        &parameters,
+                        &client,
+                        &helm_client,
+                        transfer_client,
+                    )
+                    .await
+                    .context(CrdUpgradeSnafu)?,
+            );
+
+            Ok::<(), CmdError>(())
+        }
+        .await;
+
+        record_upgrade_step(&mut state, UpgradeStep::ReplaceCrds, &state_file, result)?;
+    }
+
+    // Install the new operator release
+    if !state.is_completed(UpgradeStep::InstallOperators) {
+        let result = async {
+            let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+            let operator_values =
+                load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+                    .await
+                    .context(LoadOperatorValuesSnafu)?;
+
+            let parameters = ReleaseInstallParameters {
+                operator_namespace: args.operator_namespace.clone(),
+                chart_source: cli.chart_source(),
+                operator_values,
+                force: args.force,
+            };
 
-            // Install the new operator release
             release
-                .install(
-                    &operators,
-                    &args.excluded_products,
-                    &args.operator_namespace,
-                    &ChartSourceType::from(cli.chart_type()),
-                    &operator_values,
-                )
+                .install(&operators, &unplanned_operators, &parameters, &helm_client)
                 .await
-                .context(ReleaseInstallSnafu)?;
+                .context(ReleaseInstallSnafu)
+        }
+        .await;
+
+        record_upgrade_step(
+            &mut state,
+            UpgradeStep::InstallOperators,
+            &state_file,
+            result,
+        )?;
+    }
 
-            output
-                .with_command_hint(
-                    "stackablectl operator installed",
-                    "list installed operators",
-                )
-                .with_output(format!(
-                    "Upgraded to release {release:?}",
-                    release = args.release
-                ));
+    UpgradeState::remove(&state_file).context(UpgradeStateSnafu)?;
 
-            Ok(output.render())
+    let mut upgrade_output = format!("Upgraded to release {release:?}", release = args.release);
+    if let Some(crd_report) = &crd_report {
+        upgrade_output.push_str(&format!(
+            "\n\n{report}",
+            report = crd_report_output(crd_report)
+        ));
+    }
+
+    if crd_report.is_some_and(|report| !report.missing.is_empty()) {
+        output.with_command_hint(
+            format!(
+                "stackablectl release upgrade {release} --crd-github-fallback",
+                release = args.release
+            ),
+            "retrieve missing CRDs from GitHub",
+        );
+    }
+
+    output
+        .with_command_hint(
+            "stackablectl operator installed",
+            "list installed operators",
+        )
+        .with_output(upgrade_output);
+
+    Ok(output.render())
+}
+
+/// Persists the outcome of an upgrade `step`, so that a failed upgrade can be
+/// resumed by re-running the command.
+fn record_upgrade_step(
+    state: &mut UpgradeState,
+    step: UpgradeStep,
+    state_file: &Path,
+    result: Result<(), CmdError>,
+) -> Result<(), CmdError> {
+    match result {
+        Ok(()) => state.complete(step, state_file).context(UpgradeStateSnafu),
+        Err(err) => {
+            if let Err(state_err) = state.fail(step, state_file) {
+                warn!(error = %state_err, "Failed to persist upgrade state");
+            }
+
+            Err(err).context(UpgradeStepSnafu {
+                step,
+                release: &state.plan.release,
+            })
         }
-        None => Err(CmdError::NoSuchRelease {
-            release: args.release.clone(),
-        }),
     }
 }
 
-#[instrument(skip(release_list), fields(indicatif.pb_show = true))]
+/// Renders which CRDs were replaced and which operators were skipped.
+fn crd_report_output(report: &CrdUpgradeReport) -> String {
+    let mut output = String::new();
+
+    if report.replaced.is_empty() {
+        output.push_str("No CRDs were replaced");
+    } else {
+        let mut table = Table::new();
+        table
+            .set_header(vec!["OPERATOR", "REPLACED CRD", "SOURCE"])
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .load_preset(NOTHING);
+
+        for crd in &report.replaced {
+            table.add_row(vec![
+                crd.operator.clone(),
+                crd.name.clone(),
+                match crd.source {
+                    CrdSource::Chart => "chart".to_owned(),
+                    CrdSource::GitHub => "GitHub".to_owned(),
+                },
+            ]);
+        }
+
+        output.push_str(&table.to_string());
+    }
+
+    if !report.operator_managed.is_empty() {
+        output.push_str(&format!(
+            "\n\nSkipped CRDs maintained by the operators: {operators}",
+            operators = report.operator_managed.join(", ")
+        ));
+    }
+
+    if !report.missing.is_empty() {
+        output.push_str(&format!(
+            "\n\nNo CRDs found in the charts of: {operators}",
+            operators = report.missing.join(", ")
+        ));
+    }
+
+    output
+}
+
+/// Renders the upgrade plan as a table, including the progress of a previously
+/// failed upgrade.
+fn upgrade_plan_table(state: &UpgradeState) -> String {
+    let mut table = Table::new();
+    table
+        .set_header(vec![
+            "PRODUCT",
+            "CURRENT VERSION",
+            "TARGET VERSION",
+            "CRDS TO REPLACE",
+            "CHANGED VALUES",
+        ])
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(UTF8_FULL);
+
+    for operator in &state.plan.operators {
+        table.add_row(vec![
+            operator.name.clone(),
+            operator
+                .current_version
+                .clone()
+                .unwrap_or_else(|| "not installed".to_owned()),
+            operator.target_version.clone(),
+            match &operator.crds {
+                CrdStatus::Replace {
+                    source: CrdSource::Chart,
+                    names,
+                } => names.join("\n"),
+                CrdStatus::Replace {
+                    source: CrdSource::GitHub,
+                    names,
+                } => format!("{names} (from GitHub)", names = names.join("\n")),
+                CrdStatus::OperatorManaged => "managed by operator".to_owned(),
+                CrdStatus::Missing => "none found in chart".to_owned(),
+            },
+            operator.changed_values.join("\n"),
+        ]);
+    }
+
+    let mut output = table.to_string();
+
+    if let Some(failed_step) = state.failed_step {
+        output.push_str(&format!(
+            "\n\nResuming previous upgrade, which failed to {failed_step}"
+        ));
+    }
+
+    output
+}
+
+#[instrument(skip(cli, release_list), fields(indicatif.pb_show = true))]
 async fn uninstall_cmd(
     args: &ReleaseUninstallArgs,
+    cli: &Cli,
     release_list: release::ReleaseList,
 ) -> Result<String, CmdError> {
     Span::current().pb_set_message("Uninstalling release");
@@ -487,7 +1182,13 @@ async fn uninstall_cmd(
     match release_list.get(&args.release) {
         Some(release) => {
             release
-                .uninstall(&Vec::new(), &Vec::new(), &args.operator_namespace)
+                .uninstall(
+                    &Vec::new(),
+                    &Vec::new(),
+                    &args.operator_namespace,
+                    &cli.helm_client(),
+                )
+                .await
                 .context(ReleaseUninstallSnafu)?;
 
             let mut result = Cli::result();
@@ -506,3 +1207,162 @@ async fn uninstall_cmd(
         }),
     }
 }
+
+#[instrument(skip(cli, release_list, transfer_client), fields(indicatif.pb_show = true))]
+async fn lock_cmd(
+    args: &ReleaseLockArgs,
+    cli: &Cli,
+    release_list: release::ReleaseList,
+    transfer_client: &xfer::Client,
+) -> Result<String, CmdError> {
+    info!(release = %args.release, "Locking release");
+    Span::current().pb_set_message("Locking release");
+
+    let release = release_list
+        .get(&args.release)
+        .context(NoSuchReleaseSnafu {
+            release: args.release.clone(),
+        })?;
+
+    // Lock all operators of the release, a stack or demo might only install
+    // a subset of them
+    let mut plan = InstallPlan::default();
+    plan.add_release(
+        release,
+        &[],
+        &[],
+        DEFAULT_OPERATOR_NAMESPACE,
+        &cli.chart_source(),
+    );
+
+    // Stacks and demos are located in the branch of the release
+    let release_branch = if args.release == "dev" {
+        "main".to_owned()
+    } else {
+        format!("release-{release}", release = args.release)
+    };
+
+    if let Some(stack_name) = &args.stack {
+        let files = cli
+            .get_stack_files(&release_branch)
+            .context(PathOrUrlParseSnafu)?;
+        let stack_list = stack::StackList::build(&files, transfer_client)
+            .await
+            .context(BuildListSnafu)?;
+        let stack_spec = stack_list
+            .get(stack_name)
+            .context(NoSuchStackSnafu { name: stack_name })?;
+
+        let install_parameters = StackInstallParameters {
+            stack_name: stack_name.clone(),
+            demo_name: None,
+            operator_namespace: DEFAULT_OPERATOR_NAMESPACE.to_owned(),
+            // The namespace doesn't influence which charts are installed, but
+            // it needs to be supported by the stack
+            stack_namespace: stack_spec
+                .supported_namespaces
+                .first()
+                .unwrap_or(stack_name)
+                .clone(),
+            parameters: Vec::new(),
+            skip_release: true,
+            skip_kubernetes_version_check: false,
+            labels: Labels::new(),
+            chart_source: cli.chart_source(),
+            operator_values: Mapping::new(),
+        };
+
+        let stack_plan = stack_spec
+            .plan(&release_list, &install_parameters, transfer_client)
+            .await
+            .context(PlanStackSnafu { stack_name })?;
+        plan.helm_releases.extend(stack_plan.helm_releases);
+    }
+
+    if let Some(demo_name) = &args.demo {
+        let files = cli
+            .get_stack_files(&release_branch)
+            .context(PathOrUrlParseSnafu)?;
+        let stack_list = stack::StackList::build(&files, transfer_client)
+            .await
+            .context(BuildListSnafu)?;
+
+        let files = cli
+            .get_demo_files(&release_branch)
+            .context(PathOrUrlParseSnafu)?;
+        let demo_list = demo::List::build(&files, transfer_client)
+            .await
+            .context(BuildListSnafu)?;
+        let demo_spec = demo_list
+            .get(demo_name)
+            .context(NoSuchDemoSnafu { name: demo_name })?;
+
+        let install_parameters = DemoInstallParameters {
+            stack_name: demo_spec.stack.clone(),
+            demo_name: demo_name.clone(),
+            operator_namespace: DEFAULT_OPERATOR_NAMESPACE.to_owned(),
+            demo_namespace: demo_spec
+                .supported_namespaces
+                .first()
+                .unwrap_or(demo_name)
+                .clone(),
+            stack_parameters: Vec::new(),
+            parameters: Vec::new(),
+            skip_release: true,
+            skip_kubernetes_version_check: false,
+            stack_labels: Labels::new(),
+            labels: Labels::new(),
+            chart_source: cli.chart_source(),
+            operator_values: Mapping::new(),
+        };
+
+        let demo_plan = demo_spec
+            .plan(
+                &stack_list,
+                &release_list,
+                &install_parameters,
+                transfer_client,
+            )
+            .await
+            .context(PlanDemoSnafu { demo_name })?;
+        plan.helm_releases.extend(demo_plan.helm_releases);
+    }
+
+    let charts = cli
+        .chart_locker()
+        .lock_plan(&plan)
+        .await
+        .context(LockChartsSnafu {
+            release: args.release.clone(),
+        })?;
+
+    let lockfile = Lockfile {
+        release: args.release.clone(),
+        charts,
+    };
+
+    let contents = serde_yaml::to_string(&lockfile).context(SerializeLockfileSnafu)?;
+    std::fs::write(&args.lockfile, contents).context(WriteLockfileSnafu {
+        path: args.lockfile.clone(),
+    })?;
+
+    let mut result = Cli::result();
+
+    result
+        .with_command_hint(
+            format!(
+                "stackablectl release install {release} --lockfile {lockfile}",
+                release = args.release,
+                lockfile = args.lockfile.display()
+            ),
+            "install the locked release",
+        )
+        .with_output(format!(
+            "Locked {count} charts of release {release:?} in {lockfile:?}",
+            count = lockfile.charts.len(),
+            release = args.release,
+            lockfile = args.lockfile
+        ));
+
+    Ok(result.render())
+}
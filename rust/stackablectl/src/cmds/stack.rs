@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueHint};
 use comfy_table::{
     ContentArrangement, Table,
     presets::{NOTHING, UTF8_FULL},
@@ -11,7 +11,7 @@ use stackable_cockpit::{
     common::list,
     constants::{DEFAULT_NAMESPACE, DEFAULT_OPERATOR_NAMESPACE},
     platform::{
-        operator::ChartSourceType,
+        export::{self, ExportParameters},
         release,
         stack::{self, StackInstallParameters, StackUninstallParameters},
     },
@@ -26,9 +26,12 @@ use tracing::{Span, debug, info, instrument};
 use tracing_indicatif::span_ext::IndicatifSpanExt as _;
 
 use crate::{
-    args::{CommonClusterArgs, CommonClusterArgsError, CommonNamespaceArgs, CommonPromptArgs},
+    args::{
+        CommonClusterArgs, CommonClusterArgsError, CommonLockfileArgs, CommonLockfileArgsError,
+        CommonNamespaceArgs, CommonPromptArgs, CommonValuesArgs, CommonValuesArgsError,
+    },
     cli::{Cli, OutputType},
-    utils::load_operator_values,
+    utils::{WriteExportError, load_operator_values, render_install_plan, write_export},
 };
 
 #[derive(Debug, Args)]
@@ -59,6 +62,9 @@ pub enum StackCommands {
     /// the operators and provided operator namespace, and all Stackable CRDs
     #[command(aliases(["u", "un"]))]
     Uninstall(StackUninstallArgs),
+
+    /// Export a specific stack as a bundle of manifests for GitOps tools like Argo CD
+    Export(StackExportArgs),
 }
 
 #[derive(Debug, Args)]
@@ -94,6 +100,10 @@ to specify operator versions."
     )]
     skip_release: bool,
 
+    /// Only warn instead of failing if the stack or release doesn't support the Kubernetes version of the cluster
+    #[arg(long)]
+    skip_kubernetes_version_check: bool,
+
     /// List of parameters to use when installing the stack
     #[arg(long)]
     stack_parameters: Vec<String>,
@@ -112,6 +122,18 @@ can be specified and are space separated. Valid parameters are:
 Use \"stackablectl stack describe <STACK>\" to list available parameters for each stack.")]
     parameters: Vec<String>,
 
+    /// Print what would be installed without touching the cluster
+    #[arg(long)]
+    #[arg(
+        long_help = "Print what would be installed without touching the cluster
+
+The release is resolved and all manifests are rendered, but no local cluster,
+namespace, operator or object is created. The printed plan lists the operators
+to install, the namespaces to create, the objects to apply and the Helm
+releases to install or upgrade."
+    )]
+    dry_run: bool,
+
     #[command(flatten)]
     local_cluster: CommonClusterArgs,
 
@@ -120,6 +142,12 @@ Use \"stackablectl stack describe <STACK>\" to list available parameters for eac
 
     #[command(flatten)]
     prompt_args: CommonPromptArgs,
+
+    #[command(flatten)]
+    lockfile: CommonLockfileArgs,
+
+    #[command(flatten)]
+    values: CommonValuesArgs,
 }
 
 #[derive(Debug, Args)]
@@ -138,6 +166,46 @@ pub struct StackUninstallArgs {
     prompt_args: CommonPromptArgs,
 }
 
+#[derive(Debug, Args)]
+pub struct StackExportArgs {
+    /// Name of the stack to export
+    stack_name: String,
+
+    /// Directory the bundle is written to, defaults to the name of the stack
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    #[arg(
+        long_help = "Directory the bundle is written to, defaults to the name of the stack
+
+The bundle contains a directory per namespace with the rendered manifests, the
+values files of the operators and Helm charts and a kustomization.yaml, which
+references the operators and Helm charts in its 'helmCharts' section. A
+top-level kustomization.yaml references the namespace directories. Build the
+bundle using \"kustomize build --enable-helm <DIR>\"."
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// Overwrite existing files if the output directory is not empty
+    #[arg(long)]
+    force: bool,
+
+    /// Skip the operators of the release during the stack export
+    #[arg(long)]
+    skip_release: bool,
+
+    /// List of parameters to use when exporting the stack
+    #[arg(long)]
+    #[arg(long_help = "List of parameters to use when exporting the stack
+
+All parameters must have the format '<parameter>=<value>'. Multiple parameters
+can be specified and are space separated.
+
+Use \"stackablectl stack describe <STACK>\" to list available parameters for each stack.")]
+    parameters: Vec<String>,
+
+    #[command(flatten)]
+    namespaces: CommonNamespaceArgs,
+}
+
 #[derive(Debug, Snafu)]
 pub enum CmdError {
     #[snafu(display("path/url parse error"))]
@@ -168,6 +236,13 @@ pub enum CmdError {
         stack_name: String,
     },
 
+    #[snafu(display("failed to build install plan for stack {stack_name:?}"))]
+    PlanStack {
+        #[snafu(source(from(stack::Error, Box::new)))]
+        source: Box<stack::Error>,
+        stack_name: String,
+    },
+
     #[snafu(display("failed to confirm user input"))]
     ConfirmDialog { source: dialoguer::Error },
 
@@ -189,6 +264,27 @@ pub enum CmdError {
 
     #[snafu(display("failed to load operator values"))]
     LoadOperatorValues { source: crate::utils::Error },
+
+    #[snafu(display("lockfile argument error"))]
+    CommonLockfileArgs { source: CommonLockfileArgsError },
+
+    #[snafu(display("operator values argument error"))]
+    CommonValuesArgs { source: CommonValuesArgsError },
+
+    #[snafu(display("no stack {name:?}"))]
+    NoSuchStack { name: String },
+
+    #[snafu(display("failed to export stack {stack_name:?}"))]
+    ExportStack {
+        source: export::Error,
+        stack_name: String,
+    },
+
+    #[snafu(display("failed to write export of stack {stack_name:?}"))]
+    WriteExport {
+        source: WriteExportError,
+        stack_name: String,
+    },
 }
 
 impl StackArgs {
@@ -239,6 +335,9 @@ impl StackArgs {
             StackCommands::Uninstall(args) => {
                 uninstall_cmd(args, cli, stack_list, &transfer_client).await
             }
+            StackCommands::Export(args) => {
+                export_cmd(args, cli, stack_list, &transfer_client).await
+            }
         }
     }
 }
@@ -377,14 +476,6 @@ async fn install_cmd(
         Some(stack_spec) => {
             let mut output = Cli::result();
 
-            // Install local cluster if needed
-            args.local_cluster
-                .install_if_needed()
-                .await
-                .context(InstallClusterSnafu)?;
-
-            let client = Client::new().await.context(KubeClientCreateSnafu)?;
-
             // Construct labels which get attached to all dynamic objects which
             // are part of the stack.
             let labels = Labels::try_from([
@@ -410,8 +501,11 @@ async fn install_cmd(
                 .context(ConfirmDialogSnafu)
             };
 
+            // A dry run doesn't install anything, so it is planned with the
+            // suggested namespace without asking
             let stack_namespace = if args.namespaces.namespace == DEFAULT_NAMESPACE {
                 if args.prompt_args.assume_yes
+                    || args.dry_run
                     || tracing_indicatif::suspend_tracing_indicatif(
                         non_default_namespace_confirmation,
                     )?
@@ -427,10 +521,11 @@ async fn install_cmd(
                 args.namespaces.namespace.clone()
             };
 
-            let values_file = cli.get_values_file().context(PathOrUrlParseSnafu)?;
-            let operator_values = load_operator_values(values_file.as_ref(), transfer_client)
-                .await
-                .context(LoadOperatorValuesSnafu)?;
+            let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+            let operator_values =
+                load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+                    .await
+                    .context(LoadOperatorValuesSnafu)?;
 
             let install_parameters = StackInstallParameters {
                 stack_name: args.stack_name.clone(),
@@ -440,13 +535,74 @@ async fn install_cmd(
                 stack_namespace: stack_namespace.clone(),
                 parameters: args.parameters.clone(),
                 skip_release: args.skip_release,
+                skip_kubernetes_version_check: args.skip_kubernetes_version_check,
                 labels,
-                chart_source: ChartSourceType::from(cli.chart_type()),
+                chart_source: cli.chart_source(),
                 operator_values,
             };
 
+            // Verify the stack against the lockfile and validate the operator values
+            // before touching the cluster
+            if args.dry_run
+                || args.lockfile.lockfile.is_some()
+                || !install_parameters.operator_values.is_empty()
+            {
+                let plan = stack_spec
+                    .plan(&release_list, &install_parameters, transfer_client)
+                    .await
+                    .context(PlanStackSnafu {
+                        stack_name: args.stack_name.clone(),
+                    })?;
+
+                args.lockfile
+                    .verify_plan(&plan, cli)
+                    .await
+                    .context(CommonLockfileArgsSnafu)?;
+
+                args.values
+                    .validate_plan(
+                        &plan,
+                        &install_parameters.operator_values,
+                        cli,
+                        transfer_client,
+                    )
+                    .await
+                    .context(CommonValuesArgsSnafu)?;
+
+                if args.dry_run {
+                    output
+                        .with_command_hint(
+                            format!(
+                                "stackablectl stack install {stack_name}",
+                                stack_name = args.stack_name
+                            ),
+                            "install the stack",
+                        )
+                        .with_output(render_install_plan(&plan));
+
+                    return Ok(output.render());
+                }
+            }
+
+            // Install local cluster if needed
+            args.local_cluster
+                .install_if_needed()
+                .await
+                .context(InstallClusterSnafu)?;
+
+            let client = Client::new().await.context(KubeClientCreateSnafu)?;
+
             stack_spec
-                .install(release_list, install_parameters, &client, transfer_client)
+                .install(
+                    release_list,
+                    install_parameters,
+                    &client,
+                    &args
+                        .lockfile
+                        .helm_client(cli)
+                        .context(CommonLockfileArgsSnafu)?,
+                    transfer_client,
+                )
                 .await
                 .context(InstallStackSnafu {
                     stack_name: args.stack_name.clone(),
@@ -547,6 +703,7 @@ async fn uninstall_cmd(
                         skip_crds: args.skip_operators_and_crds,
                     },
                     &client,
+                    &cli.helm_client(),
                     transfer_client,
                 )
                 .await
@@ -564,3 +721,94 @@ async fn uninstall_cmd(
         None => Ok("No such stack".into()),
     }
 }
+
+#[instrument(skip(cli, stack_list, transfer_client), fields(indicatif.pb_show = true))]
+async fn export_cmd(
+    args: &StackExportArgs,
+    cli: &Cli,
+    stack_list: stack::StackList,
+    transfer_client: &xfer::Client,
+) -> Result<String, CmdError> {
+    info!(stack_name = %args.stack_name, "Exporting stack");
+    Span::current().pb_set_message(&format!(
+        "Exporting stack {stack_name}",
+        stack_name = args.stack_name
+    ));
+
+    let stack_spec = stack_list.get(&args.stack_name).context(NoSuchStackSnafu {
+        name: &args.stack_name,
+    })?;
+
+    let files = cli.get_release_files().context(PathOrUrlParseSnafu)?;
+    let release_list = release::ReleaseList::build(&files, transfer_client)
+        .await
+        .context(BuildListSnafu)?;
+
+    // The same labels are attached as when installing the stack
+    let labels = Labels::try_from([
+        ("stackable.tech/managed-by", "stackablectl"),
+        ("stackable.tech/stack", &args.stack_name),
+        ("stackable.tech/vendor", "Stackable"),
+    ])
+    .context(BuildLabelsSnafu)?;
+
+    let values_files = cli.get_values_files().context(PathOrUrlParseSnafu)?;
+    let operator_values =
+        load_operator_values(&values_files, &cli.files.operator_set, transfer_client)
+            .await
+            .context(LoadOperatorValuesSnafu)?;
+
+    let install_parameters = StackInstallParameters {
+        stack_name: args.stack_name.clone(),
+        demo_name: None,
+        operator_namespace: args.namespaces.operator_namespace.clone(),
+        stack_namespace: args.namespaces.namespace.clone(),
+        parameters: args.parameters.clone(),
+        skip_release: args.skip_release,
+        skip_kubernetes_version_check: true,
+        labels: labels.clone(),
+        chart_source: cli.chart_source(),
+        operator_values,
+    };
+
+    let plan = stack_spec
+        .plan(&release_list, &install_parameters, transfer_client)
+        .await
+        .context(PlanStackSnafu {
+            stack_name: &args.stack_name,
+        })?;
+
+    let export_parameters = ExportParameters {
+        helm_repo_urls: cli.helm_repo_urls(),
+        operator_values: install_parameters.operator_values,
+        labels,
+    };
+    let files = export::export_plan(&plan, &export_parameters).context(ExportStackSnafu {
+        stack_name: &args.stack_name,
+    })?;
+
+    let output_dir = args
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(&args.stack_name));
+    write_export(&output_dir, &files, args.force).context(WriteExportSnafu {
+        stack_name: &args.stack_name,
+    })?;
+
+    let mut output = Cli::result();
+    output
+        .with_command_hint(
+            format!(
+                "kustomize build --enable-helm {output_dir}",
+                output_dir = output_dir.display()
+            ),
+            "render the exported stack",
+        )
+        .with_output(format!(
+            "Exported stack {stack_name:?} to {output_dir:?} ({count} files)",
+            stack_name = args.stack_name,
+            count = files.len()
+        ));
+
+    Ok(output.render())
+}
@@ -2,10 +2,15 @@ use std::path::{Path, PathBuf};
 
 use serde::Deserialize;
 use snafu::{ResultExt, Snafu};
+use stackable_cockpit::constants::REDACTED_PASSWORD;
 
 #[derive(Debug, Default, Deserialize)]
 pub struct UserConfig {
+    #[serde(default)]
     pub version: VersionOptions,
+
+    #[serde(default)]
+    pub repository: RepositoryOptions,
 }
 
 #[derive(Debug, Deserialize)]
@@ -13,6 +18,32 @@ pub struct VersionOptions {
     pub check_enabled: bool,
 }
 
+/// Credentials and TLS settings used to access the Helm repositories and OCI
+/// registry. CLI arguments and env vars take precedence over these options.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct RepositoryOptions {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub password_file: Option<PathBuf>,
+    pub ca_file: Option<PathBuf>,
+}
+
+// Make sure the password never ends up in logs
+impl std::fmt::Debug for RepositoryOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RepositoryOptions")
+            .field("username", &self.username)
+            .field(
+                "password",
+                &self.password.as_ref().map(|_| REDACTED_PASSWORD),
+            )
+            .field("password_file", &self.password_file)
+            .field("ca_file", &self.ca_file)
+            .finish()
+    }
+}
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("failed to read config file from {path}", path = path.display()))]
@@ -1,6 +1,9 @@
 pub const ENV_KEY_RELEASE_FILES: &str = "STACKABLE_RELEASE_FILES";
 pub const ENV_KEY_STACK_FILES: &str = "STACKABLE_STACK_FILES";
 pub const ENV_KEY_DEMO_FILES: &str = "STACKABLE_DEMO_FILES";
+pub const ENV_KEY_REPO_USERNAME: &str = "STACKABLE_REPO_USERNAME";
+pub const ENV_KEY_REPO_PASSWORD: &str = "STACKABLE_REPO_PASSWORD";
+pub const ENV_KEY_REPO_CA_FILE: &str = "STACKABLE_REPO_CA_FILE";
 
 pub const DEMOS_REPOSITORY_URL_BASE: &str = "https://raw.githubusercontent.com/stackabletech/demos";
 pub const DEMOS_REPOSITORY_DEMOS_SUBPATH: &str = "demos/demos-v2.yaml";
@@ -12,6 +15,9 @@ pub const REMOTE_RELEASE_FILE: &str =
 pub const HELM_REPO_URL_STABLE: &str = "https://repo.stackable.tech/repository/helm-stable/";
 pub const HELM_REPO_URL_TEST: &str = "https://repo.stackable.tech/repository/helm-test/";
 pub const HELM_REPO_URL_DEV: &str = "https://repo.stackable.tech/repository/helm-dev/";
+pub const HELM_OCI_REGISTRY: &str = "oci.stackable.tech/sdp-charts";
+
+pub const DEFAULT_LOCKFILE: &str = "stackable.lock.yaml";
 
 pub const USER_DIR_APPLICATION_NAME: &str = "stackablectl";
 pub const USER_DIR_ORGANIZATION_NAME: &str = "Stackable";
@@ -2,6 +2,7 @@ use clap::Parser;
 use dotenvy::dotenv;
 use stackable_cockpit::PROGRESS_SPINNER_STYLE;
 use stackablectl::cli::{Cli, Error};
+use tokio_util::sync::CancellationToken;
 use tracing::{Level, metadata::LevelFilter};
 use tracing_indicatif::{
     IndicatifLayer,
@@ -69,11 +70,16 @@ async fn main() -> Result<(), Error> {
         }
     }
 
+    // The first Ctrl-C cancels pending Helm operations, which lets the current
+    // command fail gracefully once Helm marked the affected releases as failed.
+    // The second one exits immediately.
+    tokio::spawn(handle_ctrl_c(cli.cancellation_token.clone()));
+
     match cli.run().await {
         Ok(result) => indicatif_println!("{result}"),
         Err(err) => {
             let mut output = Cli::error();
-            output.with_error_report(err);
+            output.with_helm_hints(&err).with_error_report(err);
 
             indicatif_eprintln!("{error}", error = output.render());
             std::process::exit(1);
@@ -82,3 +88,19 @@ async fn main() -> Result<(), Error> {
 
     Ok(())
 }
+
+async fn handle_ctrl_c(cancellation_token: CancellationToken) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+
+    indicatif_eprintln!(
+        "Cancelling pending operations and waiting for them to stop, press Ctrl-C again to exit immediately"
+    );
+    cancellation_token.cancel();
+
+    if tokio::signal::ctrl_c().await.is_ok() {
+        // Use the conventional exit code for processes terminated by SIGINT
+        std::process::exit(130);
+    }
+}
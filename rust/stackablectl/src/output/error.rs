@@ -1,4 +1,7 @@
-use stackable_cockpit::constants::{DEFAULT_NAMESPACE, DEFAULT_OPERATOR_NAMESPACE};
+use stackable_cockpit::{
+    constants::{DEFAULT_NAMESPACE, DEFAULT_OPERATOR_NAMESPACE},
+    helm::{self, HelmErrorKind},
+};
 
 use crate::output::{ContextExt, ErrorReport, OutputKind};
 
@@ -50,4 +53,53 @@ impl ErrorContext {
         self.post_hints.push(post_hint.into());
         self
     }
+
+    /// Adds targeted hints if the error was caused by a failed Helm
+    /// operation. Errors not caused by Helm are ignored.
+    pub fn with_helm_hints(&mut self, error: &(dyn std::error::Error + 'static)) -> &mut Self {
+        let Some(helm_error) = helm::find_helm_error(error) else {
+            return self;
+        };
+
+        match helm_error.kind {
+            HelmErrorKind::ReleaseNotFound => {
+                self.with_post_hint(
+                    "The Helm release doesn't exist. Use \"stackablectl operator installed\" to list installed operators.",
+                );
+            }
+            HelmErrorKind::Conflict => {
+                self.with_post_hint(
+                    "Another Helm operation might still be in progress or resources managed by Helm already exist. Retry later or check the release using \"stackablectl operator history <OPERATOR>\".",
+                );
+            }
+            HelmErrorKind::Timeout => {
+                self.with_post_hint(
+                    "The Helm operation timed out. Check if all Pods are able to start using \"kubectl get pods -n <NAMESPACE>\".",
+                );
+            }
+            HelmErrorKind::AuthFailed => {
+                self.with_post_hint(
+                    "Authentication or authorization failed. Make sure your kubeconfig is valid and grants the required permissions, and that the chart source is accessible.",
+                );
+            }
+            HelmErrorKind::ChartNotFound => {
+                self.with_post_hint(
+                    "The Helm chart (or requested version) could not be found. Check the version and use --chart-source to select a different chart source if needed.",
+                );
+            }
+            HelmErrorKind::Other => {}
+        }
+
+        if let Some(resource) = &helm_error.resource {
+            self.with_post_hint(format!("The affected resource is {resource}."));
+        }
+
+        if let Some(status) = helm_error.status.as_deref().filter(|s| *s != "deployed") {
+            self.with_post_hint(format!(
+                "The Helm release is in status {status:?}. Use \"stackablectl operator rollback <OPERATOR>\" to return to a working revision."
+            ));
+        }
+
+        self
+    }
 }
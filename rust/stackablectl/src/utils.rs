@@ -1,15 +1,20 @@
-use std::env;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
+use comfy_table::{ContentArrangement, Table, presets::NOTHING};
 use serde_yaml::{Mapping, Value};
 use snafu::{ResultExt, Snafu};
 use stackable_cockpit::{
-    constants::{HELM_REPO_NAME_DEV, HELM_REPO_NAME_STABLE, HELM_REPO_NAME_TEST},
-    utils::path::PathOrUrl,
+    platform::{export::ExportedFile, plan::InstallPlan},
+    utils::{
+        path::PathOrUrl,
+        yaml::{self, InvalidSetExpressionError},
+    },
     xfer::{self, processor::Yaml},
 };
 
-use crate::constants::{HELM_REPO_URL_DEV, HELM_REPO_URL_STABLE, HELM_REPO_URL_TEST};
-
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("failed to open or transfer values file '{path}'"))]
@@ -22,31 +27,39 @@ pub enum Error {
         "value for key '{key}' in operator values file '{path}' must be a YAML mapping"
     ))]
     InvalidEntryType { key: String, path: String },
+
+    #[snafu(display("failed to apply operator value override"))]
+    InvalidOverride { source: InvalidSetExpressionError },
 }
 
 #[derive(Debug, Snafu)]
-#[snafu(display("Invalid Helm repo name ({name}), cannot resolve to repo URL"))]
-pub struct InvalidRepoNameError {
-    name: String,
+pub enum WriteExportError {
+    #[snafu(display("directory {path:?} is not empty, use --force to overwrite existing files"))]
+    DirectoryNotEmpty { path: PathBuf },
+
+    #[snafu(display("failed to read directory {path:?}"))]
+    ReadDirectory {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to create directory {path:?}"))]
+    CreateDirectory {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("failed to write file {path:?}"))]
+    WriteFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
 }
 
-/// This returns the Helm repository URL based on the repo name. If the provided
-/// repo name is not recognized (invalid), an [`InvalidRepoNameError`] is
-/// returned.
-pub fn helm_repo_name_to_repo_url<'a, T>(repo_name: T) -> Result<&'a str, InvalidRepoNameError>
-where
-    T: AsRef<str>,
-{
-    let repo_name = repo_name.as_ref();
-
-    match repo_name {
-        HELM_REPO_NAME_STABLE => Ok(HELM_REPO_URL_STABLE),
-        HELM_REPO_NAME_TEST => Ok(HELM_REPO_URL_TEST),
-        HELM_REPO_NAME_DEV => Ok(HELM_REPO_URL_DEV),
-        _ => Err(InvalidRepoNameError {
-            name: repo_name.to_string(),
-        }),
-    }
+#[derive(Debug, Snafu)]
+#[snafu(display("Invalid Helm repo name ({name}), cannot resolve to repo URL"))]
+pub struct InvalidRepoNameError {
+    pub name: String,
 }
 
 /// Returns wether the application should use colored output based on the user
@@ -56,9 +69,9 @@ pub fn use_colored_output(use_color: bool) -> bool {
     use_color && env::var_os("NO_COLOR").is_none()
 }
 
-/// Loads operator helm values from a YAML file.
+/// Loads operator helm values from YAML files and applies the `overrides`.
 ///
-/// The file should contain a YAML mapping of operator names to their helm values.
+/// Each file should contain a YAML mapping of operator names to their helm values.
 /// Use YAML anchors and aliases to share values across operators:
 /// ```yaml
 /// airflow-operator:
@@ -73,15 +86,33 @@ pub fn use_colored_output(use_color: bool) -> bool {
 ///   podAnnotations:
 ///     example.com/team: "platform"
 /// ```
+///
+/// The files are deep-merged in order, later files take precedence. The
+/// `overrides` have the form `airflow-operator.image.repository=<REPO>` and
+/// are applied last, see [`yaml::set_value`].
 pub async fn load_operator_values(
-    values_file: Option<&PathOrUrl>,
+    values_files: &[PathOrUrl],
+    overrides: &[String],
     transfer_client: &xfer::Client,
 ) -> Result<Mapping, Error> {
-    let file = match values_file {
-        Some(file) => file,
-        None => return Ok(Mapping::new()),
-    };
+    let mut operator_values = Mapping::new();
+
+    for file in values_files {
+        let values = load_values_file(file, transfer_client).await?;
+        yaml::merge_values(&mut operator_values, &values);
+    }
+
+    for expression in overrides {
+        yaml::set_value(&mut operator_values, expression).context(InvalidOverrideSnafu)?;
+    }
 
+    Ok(operator_values)
+}
+
+async fn load_values_file(
+    file: &PathOrUrl,
+    transfer_client: &xfer::Client,
+) -> Result<Mapping, Error> {
     let path = match file {
         PathOrUrl::Path(p) => p.display().to_string(),
         PathOrUrl::Url(u) => u.to_string(),
@@ -109,3 +140,102 @@ pub async fn load_operator_values(
 
     Ok(mapping)
 }
+
+/// Renders an [`InstallPlan`] as a set of tables, one for each kind of change
+/// the install would make to the cluster.
+pub fn render_install_plan(plan: &InstallPlan) -> String {
+    let mut operator_table = Table::new();
+    operator_table
+        .set_header(vec!["OPERATOR", "VERSION", "NAMESPACE"])
+        .load_preset(NOTHING);
+
+    for operator in &plan.operators {
+        operator_table.add_row(vec![
+            operator.name.as_str(),
+            operator.version.as_str(),
+            operator.namespace.as_str(),
+        ]);
+    }
+
+    let mut object_table = Table::new();
+    object_table
+        .set_header(vec!["KIND", "NAME", "NAMESPACE", "API VERSION", "MANIFEST"])
+        .load_preset(NOTHING);
+
+    for object in &plan.objects {
+        object_table.add_row(vec![
+            object.kind.as_str(),
+            object.name.as_str(),
+            object.namespace.as_str(),
+            object.api_version.as_str(),
+            object.manifest.as_str(),
+        ]);
+    }
+
+    let mut helm_release_table = Table::new();
+    helm_release_table
+        .set_header(vec![
+            "RELEASE",
+            "CHART",
+            "VERSION",
+            "REPO",
+            "NAMESPACE",
+            "VALUES",
+        ])
+        .load_preset(NOTHING);
+
+    for release in &plan.helm_releases {
+        helm_release_table.add_row(vec![
+            release.release_name.as_str(),
+            release.chart_name.as_str(),
+            release.chart_version.as_str(),
+            release.chart_repo.as_str(),
+            release.namespace.as_str(),
+            release.values.as_str(),
+        ]);
+    }
+
+    let mut table = Table::new();
+    table
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(NOTHING)
+        .add_row(vec!["OPERATORS", operator_table.to_string().as_str()])
+        .add_row(vec!["NAMESPACES", plan.namespaces.join(", ").as_str()])
+        .add_row(vec!["OBJECTS", object_table.to_string().as_str()])
+        .add_row(vec![
+            "HELM RELEASES",
+            helm_release_table.to_string().as_str(),
+        ]);
+
+    table.to_string()
+}
+
+/// Writes the `files` of an exported stack or demo to `directory`. Existing
+/// files are only overwritten if `force` is set, files which are not part of
+/// the export are kept.
+pub fn write_export(
+    directory: &Path,
+    files: &[ExportedFile],
+    force: bool,
+) -> Result<(), WriteExportError> {
+    if directory.exists() && !force {
+        let mut entries =
+            std::fs::read_dir(directory).context(ReadDirectorySnafu { path: directory })?;
+
+        if entries.next().is_some() {
+            return DirectoryNotEmptySnafu { path: directory }.fail();
+        }
+    }
+
+    for file in files {
+        let path = directory.join(&file.path);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context(CreateDirectorySnafu { path: parent })?;
+        }
+
+        std::fs::write(&path, &file.content).context(WriteFileSnafu { path: &path })?;
+    }
+
+    Ok(())
+}